@@ -4,7 +4,7 @@ use mix::platform::math::Vec2;
 use mix::platform::area::Area;
 use mix::Cx2d;
 use mix::draw::color::Color;
-use mix::draw::layout::{Layout, LayoutAlign, LayoutDirection};
+use mix::draw::layout::{Layout, LayoutAlign, LayoutDirection, JustifyContent};
 use mix::draw::text::TextAlign;
 use mix::*;
 
@@ -37,7 +37,7 @@ impl CounterApp {
             .with_layout(
                 Layout::horizontal()
                     .with_align_items(LayoutAlign::Center)
-                    .with_justify_content(LayoutAlign::Center)
+                    .with_justify_content(JustifyContent::Center)
                     .with_spacing(10.0)
             );
 
@@ -64,7 +64,7 @@ impl CounterApp {
             .with_layout(
                 Layout::vertical()
                     .with_align_items(LayoutAlign::Center)
-                    .with_justify_content(LayoutAlign::Center)
+                    .with_justify_content(JustifyContent::Center)
                     .with_spacing(20.0)
             );
 
@@ -2,7 +2,7 @@ use mix::Cx;
 use mix::event::Event;
 use mix::platform::math::Vec2;
 use mix::draw::color::Color;
-use mix::draw::layout::{Layout, LayoutAlign};
+use mix::draw::layout::{Layout, LayoutAlign, JustifyContent};
 use mix::Cx2d;
 use mix::*;
 
@@ -20,7 +20,7 @@ impl HelloWorldApp {
             .with_layout(
                 Layout::vertical()
                     .with_align_items(LayoutAlign::Center)
-                    .with_justify_content(LayoutAlign::Center)
+                    .with_justify_content(JustifyContent::Center)
                     .with_spacing(40.0) // Increase spacing for better visibility
             );
 
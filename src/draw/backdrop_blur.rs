@@ -0,0 +1,246 @@
+use crate::platform::geometry::{GeometryId, VertexFormat};
+use crate::platform::shader::{ShaderId, ShaderUniformType};
+use crate::platform::draw_list::{DrawItem, DrawListId, DrawUniform, UniformBinding};
+use crate::platform::pass::{PassId, PassClearColor};
+use crate::platform::texture::{Texture, TextureFormat};
+use crate::platform::math::Vec2;
+use crate::platform::Cx;
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::shader::ShaderBuilder;
+use crate::draw::color::Color;
+use crate::draw::rect::Rect;
+use crate::draw::quad::create_quad_geometry;
+
+// Frosted-glass background for overlays. The content behind the overlay is
+// rendered by an offscreen pass into a texture, which is then sampled with a
+// gaussian blur inside the overlay's rounded rect and tinted.
+#[derive(Clone, Debug)]
+pub struct DrawBackdropBlur {
+    pub blur_radius: f32,
+    pub tint: Color,
+    pub corner_radius: f32,
+    pub pass_id: Option<PassId>,
+    pub texture: Option<Texture>,
+    pub shader_id: Option<ShaderId>,
+    pub geometry_id: Option<GeometryId>,
+}
+
+impl Default for DrawBackdropBlur {
+    fn default() -> Self {
+        Self {
+            blur_radius: 16.0,
+            tint: Color::new(1.0, 1.0, 1.0, 0.2),
+            corner_radius: 0.0,
+            pass_id: None,
+            texture: None,
+            shader_id: None,
+            geometry_id: None,
+        }
+    }
+}
+
+impl DrawBackdropBlur {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_blur_radius(mut self, blur_radius: f32) -> Self {
+        self.blur_radius = blur_radius;
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self {
+        self.corner_radius = corner_radius;
+        self
+    }
+
+    pub fn init(&mut self, cx: &mut Cx) {
+        if self.geometry_id.is_none() {
+            self.geometry_id = Some(create_quad_geometry(cx));
+        }
+
+        if self.shader_id.is_none() {
+            let shader_id = ShaderBuilder::new()
+                .vertex_shader(BACKDROP_BLUR_VERTEX_SHADER)
+                .fragment_shader(BACKDROP_BLUR_FRAGMENT_SHADER)
+                .uniform("backdrop_texture", ShaderUniformType::Texture2D)
+                .uniform("backdrop_size", ShaderUniformType::Vec2)
+                .instance("rect", VertexFormat::Float4)
+                .instance("tint", VertexFormat::Float4)
+                .instance("blur_radius", VertexFormat::Float1)
+                .instance("corner_radius", VertexFormat::Float1)
+                .geometry(self.geometry_id.unwrap())
+                .build(cx)
+                .expect("DrawBackdropBlur shader failed validation");
+            self.shader_id = Some(shader_id);
+        }
+    }
+
+    // Renders `backdrop` into an offscreen pass under `parent_pass` each frame.
+    // `backdrop` must not contain the overlay itself, and `size` is the window
+    // space area it covers (usually the window size).
+    pub fn set_backdrop(&mut self, cx: &mut Cx, parent_pass: PassId, backdrop: DrawListId, size: Vec2) {
+        let width = size.x.ceil().max(1.0) as usize;
+        let height = size.y.ceil().max(1.0) as usize;
+
+        let resized = self.texture.as_ref().is_none_or(|t| t.width != width || t.height != height);
+        if resized {
+            if let Some(old) = self.texture.take() {
+                cx.textures.remove(&old.texture_id);
+            }
+            self.texture = Some(cx.create_texture(width, height, TextureFormat::Rgba8));
+        }
+
+        let pass_id = match self.pass_id {
+            Some(pass_id) => pass_id,
+            None => {
+                let pass_id = cx.create_pass();
+                self.pass_id = Some(pass_id);
+                pass_id
+            },
+        };
+
+        if let (Some(pass), Some(texture)) = (cx.passes.get_mut(&pass_id), &self.texture) {
+            pass.set_pass_parent(parent_pass);
+            pass.set_main_draw_list(backdrop);
+            pass.set_color_texture(texture.clone());
+            pass.set_clear_color(PassClearColor::new(0.0, 0.0, 0.0, 0.0));
+        }
+    }
+
+    // Nothing is drawn until `set_backdrop` has provided the texture
+    pub fn draw(&mut self, cx: &mut Cx2d, draw_list_id: DrawListId, rect: &Rect) {
+        if self.shader_id.is_none() || self.geometry_id.is_none() {
+            self.init(cx);
+        }
+
+        let Some(texture) = &self.texture else {
+            return;
+        };
+
+        if let Some(clip_rect) = cx.clip_rect() {
+            if !clip_rect.intersects(&cx.transformed_rect(rect)) {
+                return;
+            }
+        }
+
+        let backdrop_size = [texture.width as f32, texture.height as f32];
+
+        let mut instances = Vec::with_capacity(10);
+        instances.extend_from_slice(&[rect.x(), rect.y(), rect.width(), rect.height()]);
+        instances.extend_from_slice(&self.tint.to_array());
+        instances.push(self.blur_radius);
+        instances.push(self.corner_radius);
+
+        let draw_item = DrawItem {
+            shader_id: self.shader_id.unwrap(),
+            geometry_id: self.geometry_id.unwrap(),
+            uniforms: vec![UniformBinding::new("backdrop_size", DrawUniform::Vec2(backdrop_size))],
+            textures: vec![texture.clone()],
+            instances,
+            instance_count: 1,
+            clip: cx.current_clip(),
+            transform: cx.current_transform(),
+            blend: cx.current_blend(),
+            sub_list: None,
+        };
+
+        cx.append_draw_item(draw_list_id, draw_item);
+    }
+}
+
+const BACKDROP_BLUR_VERTEX_SHADER: &str = r#"
+#version 300 es
+precision highp float;
+
+in vec2 position;
+in vec2 uv;
+
+// Per instance
+in vec4 rect;
+in vec4 tint;
+in float blur_radius;
+in float corner_radius;
+
+uniform mat4 view_transform;
+uniform mat4 model_transform;
+
+out vec2 v_uv;
+out vec2 v_world;
+flat out vec2 v_size;
+flat out vec4 v_tint;
+flat out float v_blur_radius;
+flat out float v_corner_radius;
+
+void main() {
+    v_uv = uv;
+    v_size = rect.zw;
+    v_tint = tint;
+    v_blur_radius = blur_radius;
+    v_corner_radius = corner_radius;
+    vec2 pos = rect.xy + position * rect.zw;
+    vec4 world = model_transform * vec4(pos, 0.0, 1.0);
+    v_world = world.xy;
+    gl_Position = view_transform * world;
+}
+"#;
+
+const BACKDROP_BLUR_FRAGMENT_SHADER: &str = r#"
+#version 300 es
+precision highp float;
+
+in vec2 v_uv;
+in vec2 v_world;
+flat in vec2 v_size;
+flat in vec4 v_tint;
+flat in float v_blur_radius;
+flat in float v_corner_radius;
+
+uniform sampler2D backdrop_texture;
+uniform vec2 backdrop_size;
+
+// Supplied by the backend from DrawItem::clip
+uniform vec4 clip_rect;
+uniform float clip_radius;
+
+out vec4 frag_color;
+
+float rounded_box(vec2 p, vec2 b, float r) {
+    return length(max(abs(p) - b + r, 0.0)) - r;
+}
+
+void main() {
+    vec2 clip_half = clip_rect.zw * 0.5;
+    if (clip_radius > 0.0 && rounded_box(v_world - clip_rect.xy - clip_half, clip_half, clip_radius) > 0.0) {
+        discard;
+    }
+
+    vec2 center = v_size * 0.5;
+    float box = rounded_box(v_uv * v_size - center, center, v_corner_radius);
+    float coverage = clamp(0.5 - box, 0.0, 1.0);
+    if (coverage <= 0.0) {
+        discard;
+    }
+
+    // 9x9 taps spread over the blur radius
+    float sigma = max(v_blur_radius * 0.5, 0.001);
+    float spacing = v_blur_radius / 4.0;
+    vec4 sum = vec4(0.0);
+    float weight_sum = 0.0;
+    for (int x = -4; x <= 4; x++) {
+        for (int y = -4; y <= 4; y++) {
+            vec2 offset = vec2(float(x), float(y)) * spacing;
+            float weight = exp(-dot(offset, offset) / (2.0 * sigma * sigma));
+            sum += texture(backdrop_texture, (v_world + offset) / backdrop_size) * weight;
+            weight_sum += weight;
+        }
+    }
+    vec3 color = mix(sum.rgb / weight_sum, v_tint.rgb, v_tint.a);
+    frag_color = vec4(color, coverage);
+}
+"#;
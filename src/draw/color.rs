@@ -1,5 +1,6 @@
-use crate::platform::math::Vec4;
+use crate::platform::math::{Vec4, srgb_to_linear, linear_to_srgb};
 
+// Components are sRGB-encoded, as in CSS and hex notation; alpha is straight
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color {
     pub r: f32,
@@ -28,14 +29,60 @@ impl Color {
         Self { r, g, b, a: 1.0 }
     }
     
+    // #RGB, #RGBA, #RRGGBB or #RRGGBBAA, the # optional
     pub fn from_hex_str(hex: &str) -> Option<Self> {
         let hex = hex.trim_start_matches('#');
-        if hex.len() == 6 {
-            if let Ok(hex) = u32::from_str_radix(hex, 16) {
-                return Some(Self::from_hex(hex));
-            }
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let nibble = |shift: u32| ((value >> shift) & 0xF) as f32 / 15.0;
+        let byte = |shift: u32| ((value >> shift) & 0xFF) as f32 / 255.0;
+        match hex.len() {
+            3 => Some(Self::rgb(nibble(8), nibble(4), nibble(0))),
+            4 => Some(Self::new(nibble(12), nibble(8), nibble(4), nibble(0))),
+            6 => Some(Self::from_hex(value)),
+            8 => Some(Self::new(byte(24), byte(16), byte(8), byte(0))),
+            _ => None,
         }
-        None
+    }
+
+    // Hex, a CSS color name, or rgb()/rgba()/hsl()/hsla() in either the
+    // comma or the space and slash syntax
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        if text.starts_with('#') {
+            return Self::from_hex_str(&text);
+        }
+        if let Some(open) = text.find('(') {
+            let args = text[open + 1..].strip_suffix(')')?;
+            let args: Vec<&str> = args.split(|c: char| c == ',' || c == '/' || c.is_whitespace()).filter(|arg| !arg.is_empty()).collect();
+            let alpha = match args.len() {
+                3 => 1.0,
+                4 => parse_alpha(args[3])?,
+                _ => return None,
+            };
+            return match &text[..open] {
+                "rgb" | "rgba" => {
+                    let channel = |arg: &str| match arg.strip_suffix('%') {
+                        Some(percent) => percent.parse::<f32>().ok().map(|p| p / 100.0),
+                        None => arg.parse::<f32>().ok().map(|v| v / 255.0),
+                    };
+                    let clamp = |v: f32| v.clamp(0.0, 1.0);
+                    Some(Self::new(clamp(channel(args[0])?), clamp(channel(args[1])?), clamp(channel(args[2])?), alpha))
+                },
+                "hsl" | "hsla" => {
+                    let hue = args[0].strip_suffix("deg").unwrap_or(args[0]).parse::<f32>().ok()?;
+                    let percent = |arg: &str| arg.strip_suffix('%').unwrap_or(arg).parse::<f32>().ok().map(|p| (p / 100.0).clamp(0.0, 1.0));
+                    Some(Self::from_hsl(hue, percent(args[1])?, percent(args[2])?).with_alpha(alpha))
+                },
+                _ => None,
+            };
+        }
+        if text == "transparent" {
+            return Some(Self::transparent());
+        }
+        CSS_COLORS.iter().find(|(name, _)| *name == text).map(|(_, hex)| Self::from_hex(*hex))
     }
     
     pub fn to_vec4(&self) -> Vec4 {
@@ -45,6 +92,210 @@ impl Color {
     pub fn to_array(&self) -> [f32; 4] {
         [self.r, self.g, self.b, self.a]
     }
+
+    pub fn with_alpha(&self, a: f32) -> Self {
+        Self { a, ..*self }
+    }
+
+    // #RRGGBB, or #RRGGBBAA when not opaque
+    pub fn to_hex_string(&self) -> String {
+        let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        if self.a >= 1.0 {
+            format!("#{:02X}{:02X}{:02X}", byte(self.r), byte(self.g), byte(self.b))
+        } else {
+            format!("#{:02X}{:02X}{:02X}{:02X}", byte(self.r), byte(self.g), byte(self.b), byte(self.a))
+        }
+    }
+
+    // This color drawn over an opaque background, mixed in sRGB the way
+    // browsers composite
+    pub fn over(&self, background: Color) -> Self {
+        let a = self.a.clamp(0.0, 1.0);
+        let mix = |fg: f32, bg: f32| fg * a + bg * (1.0 - a);
+        Self::rgb(mix(self.r, background.r), mix(self.g, background.g), mix(self.b, background.b))
+    }
+
+    // WCAG relative luminance
+    pub fn relative_luminance(&self) -> f32 {
+        let [r, g, b, _] = self.to_linear();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    // WCAG contrast ratio, 1 to 21, of this color drawn over `background`
+    pub fn contrast_ratio(&self, background: Color) -> f32 {
+        let fg = self.over(background).relative_luminance();
+        let bg = background.relative_luminance();
+        (fg.max(bg) + 0.05) / (fg.min(bg) + 0.05)
+    }
+
+    // The closest color by OKLCH lightness, hue and chroma kept, that has at
+    // least `ratio` contrast over `background`. Falls back to whichever
+    // extreme comes nearest when no lightness is enough. Candidates are
+    // rounded to 8 bits first, so the hex of the result passes too.
+    pub fn with_contrast(&self, background: Color, ratio: f32) -> Self {
+        if self.contrast_ratio(background) >= ratio {
+            return *self;
+        }
+        let (l, c, h) = self.to_oklch();
+        let at = |lightness: f32| Self::from_oklch(lightness, c, h).with_alpha(self.a).to_8bit();
+
+        let mut best: Option<(f32, Color)> = None;
+        for target in [1.0, 0.0] {
+            if at(target).contrast_ratio(background) < ratio {
+                continue;
+            }
+            // Contrast grows monotonically towards the extreme
+            let (mut near, mut far) = (l, target);
+            for _ in 0..24 {
+                let mid = (near + far) / 2.0;
+                if at(mid).contrast_ratio(background) >= ratio { far = mid } else { near = mid }
+            }
+            let distance = (far - l).abs();
+            if best.is_none_or(|(d, _)| distance < d) {
+                best = Some((distance, at(far)));
+            }
+        }
+        best.map(|(_, color)| color).unwrap_or_else(|| {
+            let (light, dark) = (at(1.0), at(0.0));
+            if light.contrast_ratio(background) >= dark.contrast_ratio(background) { light } else { dark }
+        })
+    }
+
+    // Each channel rounded to the nearest of 256 steps, as `to_hex_string`
+    // writes it
+    pub fn to_8bit(&self) -> Self {
+        let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() / 255.0;
+        Self::new(byte(self.r), byte(self.g), byte(self.b), byte(self.a))
+    }
+
+    // Linear-light components, for blending and lighting math
+    pub fn to_linear(&self) -> [f32; 4] {
+        [srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b), self.a]
+    }
+
+    pub fn from_linear(linear: [f32; 4]) -> Self {
+        Self::new(linear_to_srgb(linear[0]), linear_to_srgb(linear[1]), linear_to_srgb(linear[2]), linear[3])
+    }
+
+    // Hue in degrees, saturation and lightness in 0..1
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Self {
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Self::from_hue_chroma(h, chroma, l - chroma / 2.0)
+    }
+
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue_max_min();
+        let l = (max + min) / 2.0;
+        let s = if max == min { 0.0 } else { (max - min) / (1.0 - (2.0 * l - 1.0).abs()) };
+        (h, s, l)
+    }
+
+    // Hue in degrees, saturation and value in 0..1
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let chroma = v * s;
+        Self::from_hue_chroma(h, chroma, v - chroma)
+    }
+
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue_max_min();
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+        (h, s, max)
+    }
+
+    // Perceptual lightness 0..1, chroma (about 0..0.4 in sRGB) and hue in
+    // degrees. Colors outside sRGB keep their lightness and hue and lose
+    // chroma until they fit.
+    pub fn from_oklch(l: f32, c: f32, h: f32) -> Self {
+        let l = l.clamp(0.0, 1.0);
+        let (sin, cos) = h.to_radians().sin_cos();
+        let in_gamut = |c: f32| {
+            let rgb = oklab_to_linear(l, c * cos, c * sin);
+            rgb.iter().all(|v| (-1e-4..=1.0 + 1e-4).contains(v)).then_some(rgb)
+        };
+
+        let rgb = match in_gamut(c) {
+            Some(rgb) => rgb,
+            None => {
+                let (mut low, mut high) = (0.0, c);
+                for _ in 0..20 {
+                    let mid = (low + high) / 2.0;
+                    if in_gamut(mid).is_some() { low = mid } else { high = mid }
+                }
+                oklab_to_linear(l, low * cos, low * sin)
+            },
+        };
+        Self::from_linear([rgb[0].clamp(0.0, 1.0), rgb[1].clamp(0.0, 1.0), rgb[2].clamp(0.0, 1.0), 1.0])
+    }
+
+    pub fn to_oklch(&self) -> (f32, f32, f32) {
+        let (l, a, b) = self.to_oklab();
+        let h = b.atan2(a).to_degrees();
+        (l, (a * a + b * b).sqrt(), if h < 0.0 { h + 360.0 } else { h })
+    }
+
+    pub fn to_oklab(&self) -> (f32, f32, f32) {
+        let [r, g, b, _] = self.to_linear();
+        let l = (0.41222146 * r + 0.53633255 * g + 0.051445995 * b).cbrt();
+        let m = (0.2119035 * r + 0.6806995 * g + 0.10739696 * b).cbrt();
+        let s = (0.08830246 * r + 0.28171885 * g + 0.6299787 * b).cbrt();
+        (
+            0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+            1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+            0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+        )
+    }
+
+    // Moves OKLCH lightness by `amount`, so the same step looks the same
+    // for every hue
+    pub fn lighten(&self, amount: f32) -> Self {
+        let (l, c, h) = self.to_oklch();
+        Self::from_oklch(l + amount, c, h).with_alpha(self.a)
+    }
+
+    pub fn darken(&self, amount: f32) -> Self {
+        self.lighten(-amount)
+    }
+
+    // Interpolates in OKLab, which avoids the gray midpoints of sRGB mixing
+    pub fn mix(&self, other: Color, t: f32) -> Self {
+        let (l0, a0, b0) = self.to_oklab();
+        let (l1, a1, b1) = other.to_oklab();
+        let lerp = |x: f32, y: f32| x + (y - x) * t;
+        let rgb = oklab_to_linear(lerp(l0, l1), lerp(a0, a1), lerp(b0, b1));
+        Self::from_linear([rgb[0].clamp(0.0, 1.0), rgb[1].clamp(0.0, 1.0), rgb[2].clamp(0.0, 1.0), lerp(self.a, other.a)])
+    }
+
+    // Shared by HSL and HSV: the hue in degrees and the largest and smallest
+    // channel
+    fn hue_max_min(&self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let delta = max - min;
+        let h = if delta == 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / delta).rem_euclid(6.0)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / delta + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / delta + 4.0)
+        };
+        (h, max, min)
+    }
+
+    fn from_hue_chroma(h: f32, chroma: f32, min: f32) -> Self {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        Self::rgb(r + min, g + min, b + min)
+    }
     
     pub fn black() -> Self {
         Self { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }
@@ -71,5 +322,230 @@ impl Color {
     }
 }
 
+fn parse_alpha(arg: &str) -> Option<f32> {
+    let alpha = match arg.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+        None => arg.parse::<f32>().ok()?,
+    };
+    Some(alpha.clamp(0.0, 1.0))
+}
 
+fn oklab_to_linear(l: f32, a: f32, b: f32) -> [f32; 3] {
+    let l_ = l + 0.39633778 * a + 0.21580376 * b;
+    let m_ = l - 0.105561346 * a - 0.06385417 * b;
+    let s_ = l - 0.08948418 * a - 1.2914855 * b;
+    let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+    [
+        4.0767417 * l - 3.3077116 * m + 0.23096994 * s,
+        -1.268438 * l + 2.6097574 * m - 0.34131938 * s,
+        -0.0041960863 * l - 0.7034186 * m + 1.7076147 * s,
+    ]
+}
+
+// CSS Color Module Level 4 named colors
+const CSS_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contrast_suggestions_still_pass_once_written_as_hex() {
+        let white = Color::white();
+        for hex in ["#777777", "#767676", "#888888", "#3A7BD5", "#FF6B6B"] {
+            let color = Color::parse(hex).unwrap();
+            let suggested = color.with_contrast(white, 4.5);
+            let written = Color::parse(&suggested.to_hex_string()).unwrap();
+            assert!(written.contrast_ratio(white) >= 4.5, "{} became {}", hex, suggested.to_hex_string());
+        }
+        // #777777 is 4.48:1 on white, one step short
+        assert_eq!(Color::parse("#777777").unwrap().with_contrast(white, 4.5).to_hex_string(), "#767676");
+    }
+
+    #[test]
+    fn parse_accepts_css_color_syntax() {
+        let cases = [
+            ("#f80", "#FF8800"),
+            ("#f808", "#FF880088"),
+            ("#3a7bd5", "#3A7BD5"),
+            ("#3a7bd580", "#3A7BD580"),
+            ("rgb(255, 128, 0)", "#FF8000"),
+            ("rgb(255 128 0 / 50%)", "#FF800080"),
+            ("rgba(255, 128, 0, 0.5)", "#FF800080"),
+            ("rgb(100%, 0%, 50%)", "#FF0080"),
+            ("rgb(300, -20, 0)", "#FF0000"),
+            ("hsl(120, 100%, 50%)", "#00FF00"),
+            ("hsl(240deg 100% 25%)", "#000080"),
+            ("hsla(0, 0%, 100%, 0.25)", "#FFFFFF40"),
+            ("  RebeccaPurple ", "#663399"),
+            ("grey", "#808080"),
+            ("transparent", "#00000000"),
+        ];
+        for (text, hex) in cases {
+            let parsed = Color::parse(text).map(|color| color.to_hex_string());
+            assert_eq!(parsed.as_deref(), Some(hex), "{}", text);
+        }
+        assert_eq!(Color::from_hex_str("3A7BD5"), Color::parse("#3a7bd5"));
+    }
+
+    #[test]
+    fn parse_rejects_malformed_colors() {
+        let cases = [
+            "", "#", "#12", "3a7bd5", "#12345", "#1234567", "#ggg", "#+fff",
+            "rgb(1, 2)", "rgb(1, 2, 3, 4, 5)", "rgb(1, 2, x)", "rgb(1, 2, 3", "rgb 1 2 3",
+            "hsl(red, 50%, 50%)", "cmyk(0, 0, 0, 0)", "notacolor",
+        ];
+        for text in cases {
+            assert_eq!(Color::parse(text), None, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::platform::Cx;
+use crate::platform::ContrastSample;
+use crate::platform::draw_list::{self, DrawListId};
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::draw_list_2d::DrawList2d;
+use crate::draw::quad::DrawQuad;
+use crate::draw::color::Color;
+use crate::draw::rect::Rect;
+
+// Text in a live frame whose contrast over what is drawn behind it is below
+// its WCAG minimum
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContrastFailure {
+    pub rect: Rect,
+    pub foreground: Color,
+    pub background: Color,
+    pub ratio: f32,
+    pub min_contrast: f32,
+}
+
+// Outlines low-contrast text on top of everything else. Works from the
+// samples draw lists record while `cx.debug.contrast_overlay` is on.
+#[derive(Clone, Debug)]
+pub struct ContrastOverlay {
+    pub draw_list: DrawList2d,
+    pub outline: DrawQuad,
+    pub failures: Vec<ContrastFailure>,
+}
+
+impl ContrastOverlay {
+    pub fn new(cx: &mut Cx) -> Self {
+        let mut draw_list = DrawList2d::new(cx);
+        draw_list.set_layer(cx, Some(i32::MAX));
+
+        Self {
+            draw_list,
+            outline: DrawQuad::new()
+                .with_color(Color::transparent())
+                .with_border_color(Color::parse("magenta").unwrap())
+                .with_border_width(2.0),
+            failures: Vec::new(),
+        }
+    }
+
+    // Checks every list under `root` and draws an outline for each failure.
+    // Backgrounds are matched by the center of the text, and parents are
+    // taken to draw before their sub-lists.
+    pub fn draw(&mut self, cx: &mut Cx2d, root: DrawListId) {
+        let mut samples = Vec::new();
+        for id in draw_list::list_order(root, &cx.draw_lists) {
+            if let Some(list_samples) = cx.debug.contrast_samples.get(&id) {
+                samples.extend_from_slice(list_samples);
+            }
+        }
+
+        self.failures.clear();
+        for (index, sample) in samples.iter().enumerate() {
+            let ContrastSample::Text { rect, color, min_contrast } = *sample else {
+                continue;
+            };
+            let center = (rect.0 + rect.2 / 2.0, rect.1 + rect.3 / 2.0);
+            let background = samples[..index].iter().fold(Color::white(), |below, sample| match *sample {
+                ContrastSample::Background { rect, color } if contains(rect, center) => color_from(color).over(below),
+                _ => below,
+            });
+            let foreground = color_from(color);
+            let ratio = foreground.contrast_ratio(background);
+            if ratio < min_contrast {
+                self.failures.push(ContrastFailure {
+                    rect: Rect::new(rect.0, rect.1, rect.2, rect.3),
+                    foreground,
+                    background,
+                    ratio,
+                    min_contrast,
+                });
+            }
+        }
+
+        // Outlines are already in window space
+        cx.transform_stack.push(Default::default());
+        self.draw_list.begin(cx);
+        for failure in &self.failures {
+            self.outline.draw(cx, self.draw_list.id(), &failure.rect);
+        }
+        self.draw_list.end(cx);
+        cx.transform_stack.pop();
+    }
+
+    // Removes the outlines once the overlay is turned off. Returns whether
+    // any were on screen, in which case the list holding the overlay has to
+    // be redrawn to drop it.
+    pub fn clear(&mut self, cx: &mut Cx) -> bool {
+        self.failures.clear();
+        match cx.draw_lists.get_mut(&self.draw_list.id()) {
+            Some(draw_list) if !draw_list.draw_items.is_empty() => {
+                draw_list.clear();
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+fn contains(rect: (f32, f32, f32, f32), point: (f32, f32)) -> bool {
+    point.0 >= rect.0 && point.0 <= rect.0 + rect.2 && point.1 >= rect.1 && point.1 <= rect.1 + rect.3
+}
+
+fn color_from(color: [f32; 4]) -> Color {
+    Color::new(color[0], color[1], color[2], color[3])
+}
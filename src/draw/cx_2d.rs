@@ -1,15 +1,47 @@
+use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
 use crate::platform::Cx;
-use crate::platform::math::Vec2;
-use crate::platform::draw_list::DrawListId;
+use crate::platform::math::{Vec2, Affine2};
+use crate::platform::area::Area;
+use crate::platform::pass::GroupTarget;
+use crate::platform::draw_list::{self, DrawListId, DrawClip, BlendMode};
 use crate::draw::turtle::{Turtle, Walk};
-use crate::draw::layout::Layout;
+use crate::draw::layout::{Layout, LayoutSize};
 use crate::draw::rect::Rect;
+use crate::draw::color::Color;
+use crate::draw::image::DrawImage;
 
 pub struct Cx2d<'a> {
     pub cx: &'a mut Cx,
     pub turtles: Vec<Turtle>,
     pub overlay_id: Option<DrawListId>,
+    pub clip_stack: Vec<DrawClip>,
+    pub transform_stack: Vec<Affine2>,
+    // Draw lists between begin and end
+    pub draw_list_stack: Vec<OpenDrawList>,
+    pub blend_stack: Vec<BlendMode>,
+    // None for groups that need no offscreen pass
+    pub opacity_stack: Vec<Option<OpacityGroup>>,
+    // Turtles begun so far this frame, by the draw list their root turtle
+    // began in
+    pub turtles_begun: HashMap<Option<DrawListId>, usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct OpenDrawList {
+    pub draw_list_id: DrawListId,
+    // Whether the list was cleared this frame
+    pub rebuilt: bool,
+    // Lists opened inside it this frame, in order
+    pub sub_lists: Vec<DrawListId>,
+}
+
+#[derive(Clone, Debug)]
+pub struct OpacityGroup {
+    pub index: usize,
+    pub target: GroupTarget,
+    pub rect: Rect,
+    pub opacity: f32,
 }
 
 impl<'a> Deref for Cx2d<'a> {
@@ -32,25 +64,309 @@ impl<'a> Cx2d<'a> {
             cx,
             turtles: Vec::with_capacity(64),
             overlay_id: None,
+            clip_stack: Vec::new(),
+            transform_stack: Vec::new(),
+            draw_list_stack: Vec::new(),
+            blend_stack: Vec::new(),
+            opacity_stack: Vec::new(),
+            turtles_begun: HashMap::new(),
+        }
+    }
+
+    // Opens `draw_list_id` inside the current list. A rebuilt parent links it
+    // right away; a retained one has its links checked in `end_draw_list`.
+    pub fn begin_draw_list(&mut self, draw_list_id: DrawListId, rebuilt: bool) {
+        self.link_sub_list(draw_list_id);
+        self.draw_list_stack.push(OpenDrawList { draw_list_id, rebuilt, sub_lists: Vec::new() });
+    }
+
+    // Closes `draw_list_id` if it is the innermost open list. A retained list
+    // that had lists opened inside it has its links pointed at those, so
+    // children added or removed since it was rebuilt show up. One nothing was
+    // opened in was skipped by its caller and keeps its links.
+    pub fn end_draw_list(&mut self, draw_list_id: DrawListId) {
+        if !self.draw_list_stack.last().is_some_and(|open| open.draw_list_id == draw_list_id) {
+            return;
+        }
+        let open = self.draw_list_stack.pop().unwrap();
+        if open.rebuilt || open.sub_lists.is_empty() {
+            return;
+        }
+        if let Some(draw_list) = self.cx.draw_lists.get_mut(&draw_list_id) {
+            if draw_list.sub_lists() != open.sub_lists {
+                draw_list.replace_sub_lists(&open.sub_lists);
+                self.cx.dirty_regions.mark_all();
+            }
         }
     }
+
+    fn link_sub_list(&mut self, child: DrawListId) {
+        if let Some(parent) = self.draw_list_stack.last_mut() {
+            parent.sub_lists.push(child);
+            if parent.rebuilt {
+                let parent = parent.draw_list_id;
+                self.cx.append_sub_list(parent, child);
+            }
+        }
+    }
+
+    pub fn push_blend(&mut self, blend: BlendMode) {
+        self.blend_stack.push(blend);
+    }
+
+    pub fn pop_blend(&mut self) -> Option<BlendMode> {
+        self.blend_stack.pop()
+    }
+
+    pub fn current_blend(&self) -> BlendMode {
+        self.blend_stack.last().copied().unwrap_or_default()
+    }
+
+    // Composites everything drawn until `pop_opacity` with one alpha, so
+    // overlapping children don't show through each other. Draw lists begun
+    // inside the group render into an offscreen pass covering the current
+    // clip, which is then drawn back premultiplied. Fully opaque groups skip
+    // the pass.
+    pub fn push_opacity(&mut self, opacity: f32) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        let rect = self.clip_rect().or_else(|| self.turtles.first().map(|turtle| turtle.rect));
+        let rect = match rect {
+            Some(rect) if opacity < 1.0 && rect.width() > 0.0 && rect.height() > 0.0 => rect,
+            _ => {
+                self.opacity_stack.push(None);
+                return;
+            },
+        };
+
+        // The texture starts at the window origin so window-space clips and
+        // damage apply to it unchanged
+        let index = self.cx.group_targets_used;
+        self.cx.group_targets_used += 1;
+        let target = self.cx.group_target(index, rect.right().ceil() as usize, rect.bottom().ceil() as usize);
+
+        // The composite keeps the group's place in the parent's draw order
+        self.link_sub_list(target.composite_list);
+        self.draw_list_stack.push(OpenDrawList { draw_list_id: target.content_list, rebuilt: true, sub_lists: Vec::new() });
+        self.opacity_stack.push(Some(OpacityGroup { index, target, rect, opacity }));
+    }
+
+    pub fn pop_opacity(&mut self) {
+        let Some(Some(group)) = self.opacity_stack.pop() else {
+            return;
+        };
+        self.end_draw_list(group.target.content_list);
+
+        let target = &group.target;
+        if let Some(draw_list) = self.cx.draw_lists.get_mut(&target.composite_list) {
+            draw_list.clear();
+        }
+        let texture = self.passes.get(&target.pass_id).and_then(|pass| pass.color_texture.clone());
+        if let (Some(texture), true) = (texture, group.opacity > 0.0) {
+            let opacity = group.opacity;
+            let mut image = DrawImage::new()
+                .with_texture(texture)
+                .with_source_rect(group.rect)
+                .with_tint(Color::new(opacity, opacity, opacity, opacity));
+            image.init(self.cx);
+            self.transform_stack.push(Default::default());
+            self.push_blend(BlendMode::Premultiplied);
+            image.draw(self, target.composite_list, &group.rect);
+            self.pop_blend();
+            self.transform_stack.pop();
+        }
+
+        if self.cx.set_group_opacity(group.index, group.opacity) != group.opacity {
+            let rect = group.rect;
+            self.dirty_regions.add((rect.x(), rect.y(), rect.width(), rect.height()));
+        }
+    }
+    
+    // Composes with the current transform; `transform` applies first
+    pub fn push_transform(&mut self, transform: Affine2) {
+        let combined = self.current_transform().multiply(&transform);
+        self.transform_stack.push(combined);
+    }
+    
+    pub fn pop_transform(&mut self) -> Option<Affine2> {
+        self.transform_stack.pop()
+    }
+    
+    pub fn current_transform(&self) -> Affine2 {
+        self.transform_stack.last().copied().unwrap_or(Affine2::identity())
+    }
+    
+    // Axis-aligned bounds of a local rect in window space
+    pub fn transformed_rect(&self, rect: &Rect) -> Rect {
+        let transform = self.current_transform();
+        if transform.is_identity() {
+            return *rect;
+        }
+        let corners = [
+            transform.transform_point(rect.pos),
+            transform.transform_point(Vec2::new(rect.right(), rect.y())),
+            transform.transform_point(Vec2::new(rect.right(), rect.bottom())),
+            transform.transform_point(Vec2::new(rect.x(), rect.bottom())),
+        ];
+        let min = corners.iter().fold(corners[0], |a, b| a.min(*b));
+        let max = corners.iter().fold(corners[0], |a, b| a.max(*b));
+        Rect::from_pos_size(min, max - min)
+    }
     
+    // Records where an area was drawn, including the active transform so
+    // events can be hit-tested against it
+    // Also ties the area and draw list to the turtle item at `rect`, so they
+    // move with it when the turtle ends
+    pub fn update_area(&mut self, area: Area, rect: &Rect, draw_list_id: DrawListId) {
+        if let Some(item) = self.turtles.last_mut().and_then(|turtle| turtle.items.iter_mut().rev().find(|item| item.rect == *rect)) {
+            if !item.bindings.contains(&(area, draw_list_id)) {
+                item.bindings.push((area, draw_list_id));
+            }
+        }
+        let transform = self.current_transform();
+        self.cx.set_area_rect(area, rect.x(), rect.y(), rect.width(), rect.height());
+        self.cx.set_area_draw_list(area, draw_list_id);
+        self.cx.set_area_transform(area, transform);
+    }
+    
+    pub fn push_clip(&mut self, rect: Rect) {
+        self.push_rounded_clip(rect, 0.0);
+    }
+    
+    // Clips are kept in window space; under a rotation the clip is the bounding
+    // box of the rotated rect. Nested clips intersect with their parent. The
+    // corner radius only applies to the clip being pushed; an inner square clip
+    // keeps the outer radius.
+    pub fn push_rounded_clip(&mut self, rect: Rect, corner_radius: f32) {
+        let rect = self.transformed_rect(&rect);
+        let clip = match self.clip_rect() {
+            Some(parent) => {
+                let rect = parent.intersection(&rect).unwrap_or(Rect::new(rect.x(), rect.y(), 0.0, 0.0));
+                let parent_radius = self.clip_stack.last().map_or(0.0, |c| c.corner_radius);
+                DrawClip {
+                    rect: (rect.x(), rect.y(), rect.width(), rect.height()),
+                    corner_radius: corner_radius.max(parent_radius),
+                }
+            },
+            None => DrawClip {
+                rect: (rect.x(), rect.y(), rect.width(), rect.height()),
+                corner_radius,
+            },
+        };
+        self.clip_stack.push(clip);
+    }
+    
+    pub fn pop_clip(&mut self) -> Option<DrawClip> {
+        self.clip_stack.pop()
+    }
+    
+    pub fn current_clip(&self) -> Option<DrawClip> {
+        self.clip_stack.last().copied()
+    }
+    
+    pub fn clip_rect(&self) -> Option<Rect> {
+        self.clip_stack.last().map(|c| Rect::new(c.rect.0, c.rect.1, c.rect.2, c.rect.3))
+    }
+    
+    // Lays out inside a new item of the enclosing turtle, sized by the
+    // layout's `width` and `height`. A Fit axis has no size until
+    // `end_turtle` measures the content, which then sizes the item.
     pub fn begin_turtle(&mut self, layout: Layout) -> &mut Turtle {
-        let rect = Rect::zero();
-        let turtle = Turtle::new(rect, layout);
-        self.turtles.push(turtle);
-        self.turtles.last_mut().unwrap()
+        let Some(index) = self.turtles.last().map(|turtle| turtle.items.len()) else {
+            return self.push_turtle(Turtle::new(Rect::zero(), layout));
+        };
+        let mut rect = self.add_sized_turtle_item(layout.width, layout.height, Vec2::zero()).unwrap();
+        if layout.width == LayoutSize::Fit {
+            rect.size.x = 0.0;
+        }
+        if layout.height == LayoutSize::Fit {
+            rect.size.y = 0.0;
+        }
+        let mut turtle = Turtle::new(rect, layout);
+        turtle.parent_item = Some(index);
+        self.push_turtle(turtle)
     }
     
     pub fn begin_sized_turtle(&mut self, size: Vec2, layout: Layout) -> &mut Turtle {
         let rect = Rect::new(0.0, 0.0, size.x, size.y);
-        let turtle = Turtle::new(rect, layout);
+        self.push_turtle(Turtle::new(rect, layout))
+    }
+
+    fn push_turtle(&mut self, mut turtle: Turtle) -> &mut Turtle {
+        let root = match self.turtles.first() {
+            Some(root) => root.layout_id.0,
+            None => self.draw_list_stack.first().map(|open| open.draw_list_id),
+        };
+        let begun = self.turtles_begun.entry(root).or_insert(0);
+        turtle.layout_id = (root, *begun);
+        *begun += 1;
         self.turtles.push(turtle);
         self.turtles.last_mut().unwrap()
     }
     
+    // Runs the flex pass and moves every item that didn't end up where it
+    // was provisionally drawn: its areas get the final rect and its draw
+    // lists are translated. An item that changed size is only translated;
+    // it is drawn at the right size from its remembered rect next frame.
     pub fn end_turtle(&mut self) -> Option<Turtle> {
-        self.turtles.pop()
+        let mut turtle = self.turtles.pop()?;
+        let rects = turtle.layout_items();
+        for (index, (item, rect)) in turtle.items.iter_mut().zip(rects).enumerate() {
+            self.cx.next_layout_rects.insert((turtle.layout_id, index), ((rect.x(), rect.y(), rect.width(), rect.height()), item.layout_key));
+            if item.rect == rect {
+                continue;
+            }
+
+            let offset = rect.pos - item.rect.pos;
+            for (area, _) in &item.bindings {
+                self.cx.set_area_rect(*area, rect.x(), rect.y(), rect.width(), rect.height());
+            }
+            for (area, _) in &item.nested {
+                if let Some(area_data) = self.cx.areas.get_mut(area) {
+                    area_data.rect.0 += offset.x;
+                    area_data.rect.1 += offset.y;
+                }
+            }
+            // Nested lists are often also sub-lists of a bound one
+            let mut lists = Vec::new();
+            for (_, draw_list_id) in item.bindings.iter().chain(&item.nested) {
+                for id in draw_list::list_order(*draw_list_id, &self.cx.draw_lists) {
+                    if !lists.contains(&id) {
+                        lists.push(id);
+                    }
+                }
+            }
+            for id in lists {
+                if let Some(draw_list) = self.cx.draw_lists.get_mut(&id) {
+                    for draw_item in draw_list.draw_items.iter_mut().filter(|draw_item| draw_item.sub_list.is_none()) {
+                        draw_item.transform = draw_item.transform.multiply(&Affine2::translation(offset.x, offset.y));
+                    }
+                }
+            }
+            for damaged in [item.rect, rect] {
+                let window_rect = self.transformed_rect(&damaged);
+                self.dirty_regions.add((window_rect.x(), window_rect.y(), window_rect.width(), window_rect.height()));
+            }
+            item.rect = rect;
+        }
+
+        // The item this turtle fills takes the content size along Fit axes,
+        // and carries what was drawn inside along when it moves
+        if let Some(index) = turtle.parent_item {
+            let content_size = turtle.compute_content_size();
+            if turtle.layout.width == LayoutSize::Fit {
+                turtle.rect.size.x = content_size.x;
+            }
+            if turtle.layout.height == LayoutSize::Fit {
+                turtle.rect.size.y = content_size.y;
+            }
+            let nested: Vec<_> = turtle.items.iter().flat_map(|item| item.bindings.iter().chain(&item.nested)).copied().collect();
+            if let Some(item) = self.turtles.last_mut().and_then(|parent| parent.items.get_mut(index)) {
+                item.rect = turtle.rect;
+                item.content_size = content_size;
+                item.nested.extend(nested);
+            }
+        }
+        Some(turtle)
     }
     
     pub fn peek_turtle(&self) -> Option<&Turtle> {
@@ -100,13 +416,143 @@ impl<'a> Cx2d<'a> {
     }
     
     pub fn add_turtle_item(&mut self, size: Vec2) -> Option<Rect> {
-        if let Some(turtle) = self.peek_turtle_mut() {
-            Some(turtle.add_item(size, None))
-        } else {
-            None
-        }
+        self.add_sized_turtle_item(LayoutSize::Fit, LayoutSize::Fit, size)
+    }
+
+    // An item sized by `LayoutSize` along each axis, `content_size` being
+    // what Fit measures. The rect is where the item settled last frame when
+    // it was laid out from the same sizes, so steady layouts are drawn in
+    // place and retained lists, already moved there, aren't moved twice.
+    pub fn add_sized_turtle_item(&mut self, width: LayoutSize, height: LayoutSize, content_size: Vec2) -> Option<Rect> {
+        let turtle = self.turtles.last()?;
+        let index = turtle.items.len();
+        let key = turtle.layout_key(width, height, content_size);
+        let previous = self.cx.layout_rects.get(&(turtle.layout_id, index))
+            .filter(|(_, previous_key)| *previous_key == key)
+            .map(|(r, _)| Rect::new(r.0, r.1, r.2, r.3));
+        let turtle = self.turtles.last_mut()?;
+        Some(turtle.add_sized_item(width, height, content_size, None, previous))
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::pass::PassId;
+    use crate::draw::layout::JustifyContent;
+
+    // Draws one opacity group when `group` is set, returning its pass
+    fn draw_frame(cx: &mut Cx, group: bool) -> Option<PassId> {
+        let mut cx2d = Cx2d::new(cx);
+        cx2d.begin_sized_turtle(Vec2::new(100.0, 100.0), Layout::vertical());
+        let mut pass_id = None;
+        if group {
+            cx2d.push_opacity(0.5);
+            pass_id = cx2d.opacity_stack.last().cloned().flatten().map(|group| group.target.pass_id);
+            cx2d.pop_opacity();
+        }
+        cx2d.end_turtle();
+        pass_id
+    }
+
+    #[test]
+    fn nested_turtles_take_an_item_sized_by_their_layout() {
+        let mut cx = Cx::new();
+        let mut cx2d = Cx2d::new(&mut cx);
+        cx2d.begin_sized_turtle(Vec2::new(200.0, 100.0), Layout::vertical().with_padding(Vec2::new(10.0, 5.0)));
+        let first = cx2d.add_turtle_item(Vec2::new(50.0, 20.0)).unwrap();
+
+        // Fill wide, Fit tall
+        cx2d.begin_turtle(Layout::horizontal());
+        let a = cx2d.add_turtle_item(Vec2::new(30.0, 10.0)).unwrap();
+        let b = cx2d.add_turtle_item(Vec2::new(40.0, 15.0)).unwrap();
+        let nested = cx2d.end_turtle().unwrap();
+
+        let after = cx2d.add_turtle_item(Vec2::new(10.0, 10.0)).unwrap();
+        cx2d.end_turtle();
+
+        assert_eq!(first, Rect::new(10.0, 5.0, 50.0, 20.0));
+        assert_eq!(nested.rect, Rect::new(10.0, 25.0, 180.0, 15.0));
+        assert_eq!(a, Rect::new(10.0, 25.0, 30.0, 10.0));
+        assert_eq!(b, Rect::new(40.0, 25.0, 40.0, 15.0));
+        assert_eq!(after, Rect::new(10.0, 40.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn nested_items_move_with_their_turtle() {
+        let mut cx = Cx::new();
+        let area = cx.create_area();
+        let draw_list_id = cx.create_draw_list();
+        let mut cx2d = Cx2d::new(&mut cx);
+        cx2d.begin_sized_turtle(Vec2::new(100.0, 100.0), Layout::vertical().with_justify_content(JustifyContent::End));
+
+        cx2d.begin_turtle(Layout::vertical());
+        let rect = cx2d.add_turtle_item(Vec2::new(20.0, 20.0)).unwrap();
+        cx2d.update_area(area, &rect, draw_list_id);
+        cx2d.end_turtle();
+        cx2d.end_turtle();
+        drop(cx2d);
+
+        // Pushed to the bottom once the outer turtle knows its content
+        assert_eq!(cx.areas[&area].rect, (0.0, 80.0, 20.0, 20.0));
+    }
+
+    // One frame of a sized turtle holding a single item, returning its rect
+    fn lay_out_item(cx: &mut Cx, width: LayoutSize, size: Vec2) -> Rect {
+        let mut cx2d = Cx2d::new(cx);
+        cx2d.begin_sized_turtle(Vec2::new(100.0, 100.0), Layout::vertical());
+        let rect = cx2d.add_sized_turtle_item(width, LayoutSize::Fit, size).unwrap();
+        cx2d.end_turtle();
+        drop(cx2d);
+        cx.render();
+        rect
+    }
+
+    #[test]
+    fn remembered_rects_are_dropped_when_the_item_changes() {
+        let mut cx = Cx::new();
+        assert_eq!(lay_out_item(&mut cx, LayoutSize::Fit, Vec2::new(20.0, 20.0)), Rect::new(0.0, 0.0, 20.0, 20.0));
+        assert_eq!(lay_out_item(&mut cx, LayoutSize::Fit, Vec2::new(30.0, 10.0)), Rect::new(0.0, 0.0, 30.0, 10.0));
+        assert_eq!(lay_out_item(&mut cx, LayoutSize::Fill, Vec2::new(30.0, 10.0)), Rect::new(0.0, 0.0, 100.0, 10.0));
 
+        // A frame without the turtle forgets its rects
+        drop(Cx2d::new(&mut cx));
+        cx.render();
+        assert!(cx.layout_rects.is_empty());
+    }
 
+    #[test]
+    fn windows_remember_their_layouts_apart() {
+        let mut cx = Cx::new();
+        let windows = [cx.create_draw_list(), cx.create_draw_list()];
+        for (draw_list_id, size) in windows.into_iter().zip([20.0, 30.0]) {
+            let mut cx2d = Cx2d::new(&mut cx);
+            cx2d.begin_draw_list(draw_list_id, true);
+            cx2d.begin_sized_turtle(Vec2::new(100.0, 100.0), Layout::vertical());
+            cx2d.add_turtle_item(Vec2::new(size, size));
+            cx2d.end_turtle();
+            cx2d.end_draw_list(draw_list_id);
+        }
+        cx.render();
+
+        for (draw_list_id, size) in windows.into_iter().zip([20.0, 30.0]) {
+            let (rect, _) = cx.layout_rects[&((Some(draw_list_id), 0), 0)];
+            assert_eq!(rect, (0.0, 0.0, size, size));
+        }
+    }
+
+    #[test]
+    fn group_targets_live_until_a_render_that_did_not_draw_them() {
+        let mut cx = Cx::new();
+
+        let pass_id = draw_frame(&mut cx, true).unwrap();
+        // A second, empty Cx2d in the same frame, as `App::run` makes
+        drop(Cx2d::new(&mut cx));
+        cx.render();
+        assert!(cx.passes[&pass_id].main_draw_list_id.is_some());
+
+        draw_frame(&mut cx, false);
+        cx.render();
+        assert!(cx.passes[&pass_id].main_draw_list_id.is_none());
+    }
+}
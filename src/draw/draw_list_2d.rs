@@ -1,5 +1,5 @@
-use crate::platform::draw_list::DrawListId;
-use crate::platform::math::Mat4;
+use crate::platform::draw_list::{DrawListId, DrawClip, BlendMode};
+use crate::platform::math::{Mat4, Affine2};
 use crate::platform::Cx;
 use crate::draw::rect::Rect;
 use crate::draw::cx_2d::Cx2d;
@@ -7,7 +7,16 @@ use crate::draw::cx_2d::Cx2d;
 #[derive(Clone, Debug)]
 pub struct DrawList2d {
     pub draw_list_id: DrawListId,
+    // Window-space rect the list was last drawn into
     pub dirty_check_rect: Rect,
+    dirty_check_clip: Option<DrawClip>,
+    // The items bake in the transform and blend mode they were drawn with
+    dirty_check_transform: Affine2,
+    dirty_check_blend: BlendMode,
+    needs_redraw: bool,
+    // What the list held when `begin`, which doesn't know the list's rect,
+    // cleared it, so `end` can tell whether anything changed
+    uncached_before: Option<(bool, Vec<DrawListId>)>,
 }
 
 impl DrawList2d {
@@ -17,6 +26,11 @@ impl DrawList2d {
         Self {
             draw_list_id,
             dirty_check_rect: Rect::zero(),
+            dirty_check_clip: None,
+            dirty_check_transform: Affine2::identity(),
+            dirty_check_blend: BlendMode::default(),
+            needs_redraw: true,
+            uncached_before: None,
         }
     }
 
@@ -24,23 +38,94 @@ impl DrawList2d {
         self.draw_list_id
     }
 
-    pub fn begin(&mut self, cx: &mut Cx2d) -> bool {
+    // Makes the next `begin_cached` rebuild the list; call it whenever
+    // something the widget draws from changes
+    pub fn redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    // Draws in a higher layer than the surrounding lists, e.g. for popups.
+    // None inherits the parent list's layer.
+    pub fn set_layer(&mut self, cx: &mut Cx, layer: Option<i32>) {
         if let Some(draw_list) = cx.draw_lists.get_mut(&self.draw_list_id) {
+            draw_list.set_layer(layer);
+        }
+    }
+
+    pub fn begin(&mut self, cx: &mut Cx2d) -> bool {
+        let rebuilt = if let Some(draw_list) = cx.draw_lists.get_mut(&self.draw_list_id) {
+            self.uncached_before = Some((draw_list.has_own_items(), draw_list.sub_lists()));
             draw_list.clear();
+            cx.debug.reset_contrast_samples(self.draw_list_id);
             true
         } else {
             false
-        }
+        };
+        cx.begin_draw_list(self.draw_list_id, rebuilt);
+        rebuilt
     }
 
     pub fn begin_always(&mut self, cx: &mut Cx2d) {
-        if let Some(draw_list) = cx.draw_lists.get_mut(&self.draw_list_id) {
-            draw_list.clear();
+        self.begin(cx);
+    }
+
+    // Starts a retained draw into `rect`. Returns false when the list is
+    // still valid: nothing called `redraw` and the rect, clip, transform and
+    // blend mode are the same as last time, so the caller skips drawing and the list is reused
+    // as-is. Otherwise the list is cleared and both the old and new rects
+    // are marked dirty. Either way `end` has to follow.
+    pub fn begin_cached(&mut self, cx: &mut Cx2d, rect: &Rect) -> bool {
+        let window_rect = cx.transformed_rect(rect);
+        let clip = cx.current_clip();
+        let transform = cx.current_transform();
+        let blend = cx.current_blend();
+        // Lists drawn before the contrast overlay was turned on have no samples
+        let sampled = !cx.debug.contrast_overlay || cx.debug.contrast_samples.contains_key(&self.draw_list_id);
+        let valid = !self.needs_redraw
+            && sampled
+            && window_rect == self.dirty_check_rect
+            && clip == self.dirty_check_clip
+            && transform == self.dirty_check_transform
+            && blend == self.dirty_check_blend;
+        let rebuilt = !valid && match cx.draw_lists.get_mut(&self.draw_list_id) {
+            Some(draw_list) => {
+                draw_list.clear();
+                cx.debug.reset_contrast_samples(self.draw_list_id);
+                true
+            },
+            None => false,
+        };
+        cx.begin_draw_list(self.draw_list_id, rebuilt);
+        if !rebuilt {
+            return false;
         }
+
+        let old = self.dirty_check_rect;
+        cx.dirty_regions.add((old.x(), old.y(), old.width(), old.height()));
+        cx.dirty_regions.add((window_rect.x(), window_rect.y(), window_rect.width(), window_rect.height()));
+
+        self.dirty_check_rect = window_rect;
+        self.dirty_check_clip = clip;
+        self.dirty_check_transform = transform;
+        self.dirty_check_blend = blend;
+        self.needs_redraw = false;
+        true
     }
 
-    pub fn end(&mut self, _cx: &mut Cx2d) {
-        // Finalize the draw list
+    pub fn end(&mut self, cx: &mut Cx2d) {
+        cx.end_draw_list(self.draw_list_id);
+
+        // A list drawn without a rect could have covered anything, unless it
+        // drew nothing of its own before and after and kept the same
+        // sub-lists, which report their own damage
+        if let Some((had_items, sub_lists)) = self.uncached_before.take() {
+            let changed = cx.draw_lists.get(&self.draw_list_id).is_some_and(|list| {
+                had_items || list.has_own_items() || list.sub_lists() != sub_lists
+            });
+            if changed {
+                cx.dirty_regions.mark_all();
+            }
+        }
     }
 
     pub fn set_view_transform(&mut self, cx: &mut Cx2d, transform: &Mat4) {
@@ -50,5 +135,63 @@ impl DrawList2d {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One frame of a retained parent that draws `children` inside it
+    fn draw_frame(cx: &mut Cx, parent: &mut DrawList2d, children: &mut [&mut DrawList2d]) {
+        let mut cx2d = Cx2d::new(cx);
+        parent.begin_cached(&mut cx2d, &Rect::new(0.0, 0.0, 100.0, 100.0));
+        for child in children.iter_mut() {
+            child.begin(&mut cx2d);
+            child.end(&mut cx2d);
+        }
+        parent.end(&mut cx2d);
+    }
+
+    #[test]
+    fn retained_parent_links_the_children_opened_this_frame() {
+        let mut cx = Cx::new();
+        let mut parent = DrawList2d::new(&mut cx);
+        let mut a = DrawList2d::new(&mut cx);
+        let mut b = DrawList2d::new(&mut cx);
+
+        draw_frame(&mut cx, &mut parent, &mut [&mut a, &mut b]);
+        assert_eq!(cx.draw_lists[&parent.id()].sub_lists(), vec![a.id(), b.id()]);
 
+        // The parent is retained from here on
+        draw_frame(&mut cx, &mut parent, &mut [&mut b]);
+        assert_eq!(cx.draw_lists[&parent.id()].sub_lists(), vec![b.id()]);
 
+        draw_frame(&mut cx, &mut parent, &mut [&mut b, &mut a]);
+        assert_eq!(cx.draw_lists[&parent.id()].sub_lists(), vec![b.id(), a.id()]);
+
+        // A caller that skips a retained list's contents keeps its links
+        draw_frame(&mut cx, &mut parent, &mut []);
+        assert_eq!(cx.draw_lists[&parent.id()].sub_lists(), vec![b.id(), a.id()]);
+    }
+
+    #[test]
+    fn cached_lists_redraw_when_the_transform_or_blend_changes() {
+        let mut cx = Cx::new();
+        let mut list = DrawList2d::new(&mut cx);
+        let rect = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let mut frame = |cx: &mut Cx, transform: Affine2, blend: BlendMode| {
+            let mut cx2d = Cx2d::new(cx);
+            cx2d.push_transform(transform);
+            cx2d.push_blend(blend);
+            let rebuilt = list.begin_cached(&mut cx2d, &rect);
+            list.end(&mut cx2d);
+            rebuilt
+        };
+
+        // Mirrored about its center, the rect covers the same window rect
+        let mirrored = Affine2::translation(100.0, 0.0) * Affine2::scale(-1.0, 1.0);
+        assert!(frame(&mut cx, Affine2::identity(), BlendMode::Normal));
+        assert!(!frame(&mut cx, Affine2::identity(), BlendMode::Normal));
+        assert!(frame(&mut cx, mirrored, BlendMode::Normal));
+        assert!(!frame(&mut cx, mirrored, BlendMode::Normal));
+        assert!(frame(&mut cx, mirrored, BlendMode::Additive));
+    }
+}
@@ -0,0 +1,621 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Weak};
+use unicode_segmentation::UnicodeSegmentation;
+use crate::platform::Cx;
+use crate::draw::text::{Font, TextStyle};
+
+thread_local! {
+    static SHARED_FONTS: Rc<RefCell<FontCollection>> = Rc::new(RefCell::new(FontCollection::with_system_fonts()));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FontWeight(pub u16);
+
+impl FontWeight {
+    pub const THIN: FontWeight = FontWeight(100);
+    pub const LIGHT: FontWeight = FontWeight(300);
+    pub const NORMAL: FontWeight = FontWeight(400);
+    pub const MEDIUM: FontWeight = FontWeight(500);
+    pub const SEMI_BOLD: FontWeight = FontWeight(600);
+    pub const BOLD: FontWeight = FontWeight(700);
+    pub const BLACK: FontWeight = FontWeight(900);
+}
+
+impl Default for FontWeight {
+    fn default() -> Self {
+        FontWeight::NORMAL
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+    Oblique,
+}
+
+// A font face found on disk, not yet loaded
+#[derive(Clone, Debug)]
+pub struct FontDescriptor {
+    pub family: String,
+    pub weight: FontWeight,
+    pub style: FontStyle,
+    pub path: PathBuf,
+    pub index: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SystemFonts {
+    pub descriptors: Vec<FontDescriptor>,
+}
+
+impl SystemFonts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Scans the platform font directories. On Linux this reads the fontconfig
+    // configuration directly, so no fontconfig daemon or library is needed.
+    pub fn discover() -> Self {
+        let mut system_fonts = Self::new();
+        for dir in Self::font_dirs() {
+            system_fonts.scan_dir(&dir);
+        }
+        system_fonts
+    }
+
+    pub fn font_dirs() -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+
+        #[cfg(target_os = "linux")]
+        {
+            let mut conf_files = vec![PathBuf::from("/etc/fonts/fonts.conf")];
+            if let Ok(entries) = std::fs::read_dir("/etc/fonts/conf.d") {
+                let mut entries: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+                entries.sort();
+                conf_files.extend(entries);
+            }
+            for conf_file in conf_files {
+                if let Ok(conf) = std::fs::read_to_string(&conf_file) {
+                    dirs.extend(parse_fontconfig_dirs(&conf));
+                }
+            }
+
+            dirs.push(PathBuf::from("/usr/share/fonts"));
+            dirs.push(PathBuf::from("/usr/local/share/fonts"));
+            if let Some(home) = std::env::var_os("HOME") {
+                dirs.push(Path::new(&home).join(".fonts"));
+                dirs.push(Path::new(&home).join(".local/share/fonts"));
+            }
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            dirs.push(PathBuf::from("/System/Library/Fonts"));
+            dirs.push(PathBuf::from("/Library/Fonts"));
+            if let Some(home) = std::env::var_os("HOME") {
+                dirs.push(Path::new(&home).join("Library/Fonts"));
+            }
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            let windir = std::env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into());
+            dirs.push(Path::new(&windir).join("Fonts"));
+            if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+                dirs.push(Path::new(&local).join("Microsoft\\Windows\\Fonts"));
+            }
+        }
+
+        let mut unique = Vec::new();
+        for dir in dirs {
+            if !unique.contains(&dir) {
+                unique.push(dir);
+            }
+        }
+        unique
+    }
+
+    pub fn scan_dir(&mut self, dir: &Path) {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+
+        for path in paths {
+            if path.is_dir() {
+                self.scan_dir(&path);
+            } else if is_font_file(&path) {
+                self.add_file(&path);
+            }
+        }
+    }
+
+    // Reads just the table directory and the name, OS/2 and post tables of
+    // each face; the glyph data is left on disk until a face is loaded
+    pub fn add_file(&mut self, path: &Path) {
+        if self.descriptors.iter().any(|d| d.path == path) {
+            return;
+        }
+        let Ok(mut file) = File::open(path) else {
+            return;
+        };
+
+        for (index, info) in read_face_info(&mut file).into_iter().enumerate() {
+            if let Some((family, weight, style)) = info {
+                self.descriptors.push(FontDescriptor {
+                    family,
+                    weight,
+                    style,
+                    path: path.to_path_buf(),
+                    index: index as u32,
+                });
+            }
+        }
+    }
+
+    pub fn families(&self) -> Vec<&str> {
+        let mut families: Vec<&str> = self.descriptors.iter().map(|d| d.family.as_str()).collect();
+        families.sort_unstable();
+        families.dedup();
+        families
+    }
+
+    // Picks the face of `family` closest to the requested weight and style
+    pub fn find(&self, family: &str, weight: FontWeight, style: FontStyle) -> Option<&FontDescriptor> {
+        self.descriptors
+            .iter()
+            .filter(|d| d.family.eq_ignore_ascii_case(family))
+            .min_by_key(|d| match_score(d.weight, d.style, weight, style))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FontCollection {
+    pub fonts: Vec<Font>,
+    pub fallback_chains: HashMap<String, Vec<String>>,
+    pub system_fonts: SystemFonts,
+    // Index into `fonts` of each face loaded from `system_fonts`
+    loaded_faces: HashMap<(PathBuf, u32), usize>,
+    // Font files by path, shared by the faces loaded from them
+    files: HashMap<PathBuf, Weak<[u8]>>,
+}
+
+impl Default for FontCollection {
+    fn default() -> Self {
+        let mut fallback_chains = HashMap::new();
+        fallback_chains.insert("default".to_string(), default_chain(LATIN_SANS_FAMILIES));
+        fallback_chains.insert("serif".to_string(), default_chain(LATIN_SERIF_FAMILIES));
+        fallback_chains.insert("monospace".to_string(), default_chain(LATIN_MONO_FAMILIES));
+
+        Self {
+            fonts: Vec::new(),
+            fallback_chains,
+            system_fonts: SystemFonts::new(),
+            loaded_faces: HashMap::new(),
+            files: HashMap::new(),
+        }
+    }
+}
+
+impl FontCollection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_system_fonts() -> Self {
+        let mut collection = Self::new();
+        collection.load_system_fonts();
+        collection
+    }
+
+    // The collection the built-in widgets draw with. System fonts are
+    // discovered the first time it's used on a thread.
+    pub fn shared() -> Rc<RefCell<FontCollection>> {
+        SHARED_FONTS.with(Rc::clone)
+    }
+
+    pub fn add_font(&mut self, font: Font) {
+        self.fonts.push(font);
+    }
+
+    pub fn set_fallback_chain(&mut self, name: &str, families: &[&str]) {
+        self.fallback_chains.insert(
+            name.to_string(),
+            families.iter().map(|f| f.to_string()).collect(),
+        );
+    }
+
+    // Discovers the system fonts and loads the regular face of the first
+    // installed family of each chain, so text can be measured before it is
+    // drawn. The rest load on demand in `prepare_glyphs`. Returns the number
+    // of faces loaded.
+    pub fn load_system_fonts(&mut self) -> usize {
+        self.system_fonts = SystemFonts::discover();
+
+        let mut primaries: Vec<String> = self.fallback_chains.values()
+            .filter_map(|chain| chain.iter().find(|family| self.system_fonts.find(family, FontWeight::NORMAL, FontStyle::Normal).is_some()))
+            .cloned()
+            .collect();
+        primaries.sort();
+        primaries.dedup();
+
+        let before = self.fonts.len();
+        for family in primaries {
+            self.load_face(&family, FontWeight::NORMAL, FontStyle::Normal);
+        }
+        self.fonts.len() - before
+    }
+
+    // Loads every face of a system font family. Returns the number loaded.
+    pub fn load_family(&mut self, family: &str) -> usize {
+        let descriptors: Vec<FontDescriptor> = self.system_fonts.descriptors
+            .iter()
+            .filter(|d| d.family.eq_ignore_ascii_case(family))
+            .cloned()
+            .collect();
+
+        let before = self.fonts.len();
+        for descriptor in descriptors {
+            self.load_descriptor(&descriptor);
+        }
+        self.fonts.len() - before
+    }
+
+    // Loads the system face of `family` closest to the weight and style,
+    // unless it already is. Returns its index in `fonts`.
+    pub fn load_face(&mut self, family: &str, weight: FontWeight, style: FontStyle) -> Option<usize> {
+        let descriptor = self.system_fonts.find(family, weight, style)?.clone();
+        self.load_descriptor(&descriptor)
+    }
+
+    fn load_descriptor(&mut self, descriptor: &FontDescriptor) -> Option<usize> {
+        let key = (descriptor.path.clone(), descriptor.index);
+        if let Some(index) = self.loaded_faces.get(&key) {
+            return Some(*index);
+        }
+
+        let data = match self.files.get(&descriptor.path).and_then(Weak::upgrade) {
+            Some(data) => data,
+            None => {
+                let data: Arc<[u8]> = Arc::from(std::fs::read(&descriptor.path).ok()?);
+                self.files.insert(descriptor.path.clone(), Arc::downgrade(&data));
+                data
+            },
+        };
+        let font = Font::from_shared(data, descriptor.index)?;
+        self.fonts.push(font);
+        self.loaded_faces.insert(key, self.fonts.len() - 1);
+        Some(self.fonts.len() - 1)
+    }
+
+    pub fn find(&self, family: &str, weight: FontWeight, style: FontStyle) -> Option<&Font> {
+        self.find_index(family, weight, style).map(|index| &self.fonts[index])
+    }
+
+    fn find_index(&self, family: &str, weight: FontWeight, style: FontStyle) -> Option<usize> {
+        self.fonts
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.name.eq_ignore_ascii_case(family))
+            .min_by_key(|(_, f)| match_score(f.weight, f.style, weight, style))
+            .map(|(index, _)| index)
+    }
+
+    // The families tried for a style, in order. A `font_name` that isn't a
+    // chain is tried first and then falls back to the default chain.
+    pub fn chain<'a>(&'a self, font_name: &'a str) -> Vec<&'a str> {
+        if let Some(chain) = self.fallback_chains.get(font_name) {
+            return chain.iter().map(|f| f.as_str()).collect();
+        }
+
+        let mut chain = vec![font_name];
+        if let Some(default) = self.fallback_chains.get("default") {
+            chain.extend(default.iter().map(|f| f.as_str()));
+        }
+        chain
+    }
+
+    pub fn primary_font(&self, style: &TextStyle) -> Option<&Font> {
+        self.chain(&style.font_name)
+            .into_iter()
+            .find_map(|family| self.find(family, style.font_weight, style.font_style))
+            .or_else(|| self.fonts.first())
+    }
+
+    pub fn font_for_char(&self, style: &TextStyle, c: char) -> Option<&Font> {
+        self.font_index_for_char(style, c).map(|index| &self.fonts[index])
+    }
+
+    fn font_index_for_char(&self, style: &TextStyle, c: char) -> Option<usize> {
+        for family in self.chain(&style.font_name) {
+            let index = self.find_index(family, style.font_weight, style.font_style);
+            if let Some(index) = index.filter(|index| self.fonts[*index].has_glyph(c)) {
+                return Some(index);
+            }
+        }
+
+        // Last resort: any loaded font that covers the character
+        self.fonts.iter().position(|f| f.has_glyph(c))
+    }
+
+    // Like `font_for_char`, but loads the system faces of the chain one
+    // family at a time until one covers the character
+    fn load_font_for_char(&mut self, style: &TextStyle, c: char) -> Option<usize> {
+        let chain: Vec<String> = self.chain(&style.font_name).into_iter().map(str::to_string).collect();
+        for family in &chain {
+            let index = self.load_face(family, style.font_weight, style.font_style)
+                .or_else(|| self.find_index(family, style.font_weight, style.font_style));
+            if let Some(index) = index.filter(|index| self.fonts[*index].has_glyph(c)) {
+                return Some(index);
+            }
+        }
+        self.fonts.iter().position(|f| f.has_glyph(c))
+    }
+
+    // Loads the fonts `text` needs and rasterizes the glyphs that aren't in
+    // their font's atlas yet, including ones that fall back to other fonts
+    // in the chain
+    pub fn prepare_glyphs(&mut self, cx: &mut Cx, style: &TextStyle, text: &str) {
+        for grapheme in text.graphemes(true) {
+            let c = grapheme.chars().next().unwrap_or(' ');
+            if let Some(index) = self.load_font_for_char(style, c) {
+                self.fonts[index].rasterize_glyph(cx, c);
+            }
+        }
+    }
+}
+
+const LATIN_SANS_FAMILIES: &[&str] = &[
+    "Noto Sans", "DejaVu Sans", "Liberation Sans", "Cantarell", "Ubuntu",
+    "Segoe UI", "Arial", "Helvetica Neue", "Helvetica",
+];
+
+const LATIN_SERIF_FAMILIES: &[&str] = &[
+    "Noto Serif", "DejaVu Serif", "Liberation Serif", "Times New Roman", "Times",
+];
+
+const LATIN_MONO_FAMILIES: &[&str] = &[
+    "Noto Sans Mono", "DejaVu Sans Mono", "Liberation Mono", "Ubuntu Mono",
+    "Consolas", "Menlo", "Courier New",
+];
+
+const CJK_FAMILIES: &[&str] = &[
+    "Noto Sans CJK SC", "Noto Sans CJK JP", "Noto Sans CJK KR", "Noto Sans CJK TC",
+    "Source Han Sans SC", "WenQuanYi Micro Hei", "WenQuanYi Zen Hei", "Droid Sans Fallback",
+    "Microsoft YaHei", "Yu Gothic", "Malgun Gothic", "PingFang SC", "Hiragino Sans",
+];
+
+const EMOJI_FAMILIES: &[&str] = &[
+    "Noto Color Emoji", "Twemoji", "JoyPixels", "Segoe UI Emoji", "Apple Color Emoji",
+    "Noto Emoji", "Symbola",
+];
+
+fn default_chain(latin: &[&str]) -> Vec<String> {
+    latin.iter()
+        .chain(CJK_FAMILIES)
+        .chain(EMOJI_FAMILIES)
+        .map(|f| f.to_string())
+        .collect()
+}
+
+fn match_score(weight: FontWeight, style: FontStyle, wanted_weight: FontWeight, wanted_style: FontStyle) -> u32 {
+    let style_penalty = if style == wanted_style {
+        0
+    } else if style != FontStyle::Normal && wanted_style != FontStyle::Normal {
+        500
+    } else {
+        1000
+    };
+    style_penalty + (weight.0 as i32 - wanted_weight.0 as i32).unsigned_abs()
+}
+
+fn is_font_file(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => matches!(ext.to_ascii_lowercase().as_str(), "ttf" | "otf" | "ttc" | "otc"),
+        None => false,
+    }
+}
+
+pub(crate) fn face_family_name(names: ttf_parser::name::Names) -> Option<String> {
+    let find_name = |name_id: u16| {
+        names
+            .into_iter()
+            .filter(|n| n.name_id == name_id && n.is_unicode())
+            .find_map(|n| n.to_string())
+    };
+
+    find_name(ttf_parser::name_id::TYPOGRAPHIC_FAMILY)
+        .or_else(|| find_name(ttf_parser::name_id::FAMILY))
+}
+
+// The OS/2 style, with a slanted `post` italic angle counting as italic the
+// way ttf-parser's `Face::is_italic` does
+pub(crate) fn face_style(style: ttf_parser::Style, italic_angle: f32) -> FontStyle {
+    match style {
+        ttf_parser::Style::Italic => FontStyle::Italic,
+        _ if italic_angle != 0.0 => FontStyle::Italic,
+        ttf_parser::Style::Oblique => FontStyle::Oblique,
+        ttf_parser::Style::Normal => FontStyle::Normal,
+    }
+}
+
+// Family, weight and style of each face in a font file or collection, or
+// None for faces without a family name. Reads only the headers and the
+// name, OS/2 and post tables.
+fn read_face_info(file: &mut File) -> Vec<Option<(String, FontWeight, FontStyle)>> {
+    let Some(header) = read_at(file, 0, 12) else {
+        return Vec::new();
+    };
+    let offsets = if &header[0..4] == b"ttcf" {
+        let count = be_u32(&header, 8) as usize;
+        match read_at(file, 12, count * 4) {
+            Some(offsets) => (0..count).map(|i| be_u32(&offsets, i * 4) as u64).collect(),
+            None => return Vec::new(),
+        }
+    } else {
+        vec![0]
+    };
+    offsets.into_iter().map(|offset| read_one_face_info(file, offset)).collect()
+}
+
+fn read_one_face_info(file: &mut File, offset: u64) -> Option<(String, FontWeight, FontStyle)> {
+    let header = read_at(file, offset, 12)?;
+    let table_count = be_u16(&header, 4) as usize;
+    let records = read_at(file, offset + 12, table_count * 16)?;
+    let table = |file: &mut File, tag: &[u8; 4], max_len: usize| {
+        let record = records.chunks_exact(16).find(|record| &record[0..4] == tag)?;
+        let len = (be_u32(record, 12) as usize).min(max_len);
+        read_at(file, be_u32(record, 8) as u64, len)
+    };
+
+    let name = table(file, b"name", usize::MAX)?;
+    let family = face_family_name(ttf_parser::name::Table::parse(&name)?.names)?;
+    let os2 = table(file, b"OS/2", usize::MAX);
+    let os2 = os2.as_deref().and_then(ttf_parser::os2::Table::parse);
+    // The italic angle is a 16.16 fixed-point number after the version
+    let italic_angle = table(file, b"post", 8)
+        .filter(|post| post.len() == 8)
+        .map(|post| be_u32(&post, 4) as i32 as f32 / 65536.0)
+        .unwrap_or(0.0);
+
+    let weight = os2.map(|os2| os2.weight()).unwrap_or_default();
+    let style = os2.map(|os2| os2.style()).unwrap_or_default();
+    Some((family, FontWeight(weight.to_number()), face_style(style, italic_angle)))
+}
+
+fn read_at(file: &mut File, offset: u64, len: usize) -> Option<Vec<u8>> {
+    let mut buffer = vec![0; len];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+fn be_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn be_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+// Extracts the <dir> entries of a fontconfig configuration file
+fn parse_fontconfig_dirs(conf: &str) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let mut rest = conf;
+
+    while let Some(start) = rest.find("<dir") {
+        rest = &rest[start + 4..];
+        let tag_end = match rest.find('>') {
+            Some(tag_end) => tag_end,
+            None => break,
+        };
+        let attributes = &rest[..tag_end];
+        rest = &rest[tag_end + 1..];
+
+        // Skip <dirname> and similar, and self-closing <dir/>
+        if !(attributes.is_empty() || attributes.starts_with(' ')) || attributes.ends_with('/') {
+            continue;
+        }
+
+        let end = match rest.find("</dir>") {
+            Some(end) => end,
+            None => break,
+        };
+        let dir = rest[..end].trim();
+        rest = &rest[end..];
+
+        if attributes.contains("prefix=\"xdg\"") {
+            let data_home = std::env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".local/share")));
+            if let Some(data_home) = data_home {
+                dirs.push(data_home.join(dir));
+            }
+        } else if let Some(stripped) = dir.strip_prefix("~/") {
+            if let Some(home) = std::env::var_os("HOME") {
+                dirs.push(Path::new(&home).join(stripped));
+            }
+        } else if !dir.is_empty() {
+            dirs.push(PathBuf::from(dir));
+        }
+    }
+
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // These run against whatever fonts are installed and pass trivially on
+    // a system without the DejaVu families
+    fn dejavu() -> FontCollection {
+        let mut fonts = FontCollection::new();
+        fonts.system_fonts = SystemFonts::discover();
+        fonts.set_fallback_chain("default", &["DejaVu Sans Mono", "DejaVu Sans"]);
+        fonts
+    }
+
+    #[test]
+    fn discovery_reads_the_same_metadata_as_a_parsed_face() {
+        let fonts = dejavu();
+        for descriptor in fonts.system_fonts.descriptors.iter().take(20) {
+            let font = Font::from_file(&descriptor.path, descriptor.index).unwrap();
+            assert_eq!(
+                (&descriptor.family, descriptor.weight, descriptor.style),
+                (&font.name, font.weight, font.style),
+                "{}", descriptor.path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn faces_load_once_and_share_their_file() {
+        let mut fonts = dejavu();
+        let Some(index) = fonts.load_face("DejaVu Sans", FontWeight::NORMAL, FontStyle::Normal) else {
+            return;
+        };
+        let again = fonts.load_face("DejaVu Sans", FontWeight::NORMAL, FontStyle::Normal).unwrap();
+        assert_eq!(index, again);
+        assert_eq!(fonts.fonts.len(), 1);
+
+        let clone = fonts.fonts[index].clone();
+        assert!(Arc::ptr_eq(clone.data(), fonts.fonts[index].data()));
+    }
+
+    #[test]
+    fn fallback_faces_load_when_a_glyph_misses() {
+        let mut fonts = dejavu();
+        let (Some(mono), Some(sans)) = (
+            fonts.system_fonts.find("DejaVu Sans Mono", FontWeight::NORMAL, FontStyle::Normal).cloned(),
+            fonts.system_fonts.find("DejaVu Sans", FontWeight::NORMAL, FontStyle::Normal).cloned(),
+        ) else {
+            return;
+        };
+        let mono = Font::from_file(&mono.path, mono.index).unwrap();
+        let sans = Font::from_file(&sans.path, sans.index).unwrap();
+        let missing = (0x2000..0x3000u32)
+            .filter_map(char::from_u32)
+            .find(|c| !mono.has_glyph(*c) && sans.has_glyph(*c))
+            .unwrap();
+
+        let mut cx = Cx::new();
+        let style = TextStyle::default();
+        fonts.prepare_glyphs(&mut cx, &style, "a");
+        assert_eq!(fonts.fonts.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), ["DejaVu Sans Mono"]);
+
+        fonts.prepare_glyphs(&mut cx, &style, &missing.to_string());
+        assert_eq!(fonts.fonts.len(), 2);
+        assert_eq!(fonts.fonts[fonts.font_index_for_char(&style, missing).unwrap()].name, "DejaVu Sans");
+    }
+}
@@ -0,0 +1,305 @@
+use crate::platform::math::Vec2;
+use crate::platform::draw_list::{DrawUniform, UniformBinding};
+use crate::draw::color::Color;
+
+// Coordinates are in the quad's local UV space: (0, 0) is the top-left corner
+// and (1, 1) the bottom-right, whatever the quad's pixel size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientKind {
+    Linear { start: Vec2, end: Vec2 },
+    Radial { center: Vec2, radius: f32 },
+    Conic { center: Vec2, angle: f32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: Color,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    // Stops the quad shader can hold; longer gradients are resampled
+    pub const MAX_STOPS: usize = 8;
+
+    pub fn new(kind: GradientKind) -> Self {
+        Self {
+            kind,
+            stops: Vec::new(),
+        }
+    }
+
+    pub fn linear(start: Vec2, end: Vec2) -> Self {
+        Self::new(GradientKind::Linear { start, end })
+    }
+
+    // Top to bottom
+    pub fn vertical() -> Self {
+        Self::linear(Vec2::new(0.5, 0.0), Vec2::new(0.5, 1.0))
+    }
+
+    // Left to right
+    pub fn horizontal() -> Self {
+        Self::linear(Vec2::new(0.0, 0.5), Vec2::new(1.0, 0.5))
+    }
+
+    pub fn radial(center: Vec2, radius: f32) -> Self {
+        Self::new(GradientKind::Radial { center, radius })
+    }
+
+    // `angle` in radians, measured clockwise from the +x axis on screen
+    pub fn conic(center: Vec2, angle: f32) -> Self {
+        Self::new(GradientKind::Conic { center, angle })
+    }
+
+    pub fn with_stop(mut self, offset: f32, color: Color) -> Self {
+        self.add_stop(offset, color);
+        self
+    }
+
+    // Keeps the stops sorted; equal offsets keep insertion order for hard edges
+    pub fn add_stop(&mut self, offset: f32, color: Color) {
+        let offset = offset.clamp(0.0, 1.0);
+        let index = self.stops.iter().position(|s| s.offset > offset).unwrap_or(self.stops.len());
+        self.stops.insert(index, GradientStop { offset, color });
+    }
+
+    // Where along the gradient (0..1) a point in UV space falls
+    pub fn offset_at(&self, uv: Vec2) -> f32 {
+        let t = match self.kind {
+            GradientKind::Linear { start, end } => {
+                let d = end - start;
+                (uv - start).dot(d) / d.length_squared().max(1e-6)
+            },
+            GradientKind::Radial { center, radius } => {
+                (uv - center).length() / radius.max(1e-6)
+            },
+            GradientKind::Conic { center, angle } => {
+                let p = uv - center;
+                let turns = (p.y.atan2(p.x) - angle) / std::f32::consts::TAU;
+                turns - turns.floor()
+            },
+        };
+        t.clamp(0.0, 1.0)
+    }
+
+    // CPU reference of the shader's `gradient_color`
+    pub fn sample(&self, uv: Vec2) -> Color {
+        self.color_at(&self.shader_stops(), self.offset_at(uv))
+    }
+
+    fn color_at(&self, stops: &[GradientStop], t: f32) -> Color {
+        let mut color = match stops.first() {
+            Some(stop) => stop.color,
+            None => return Color::transparent(),
+        };
+        for pair in stops.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if t >= b.offset {
+                color = b.color;
+            } else if t > a.offset {
+                let f = (t - a.offset) / (b.offset - a.offset).max(1e-6);
+                return lerp_color(a.color, b.color, f);
+            }
+        }
+        color
+    }
+
+    // The stops as uploaded: at most MAX_STOPS, evenly resampled if there are more
+    pub fn shader_stops(&self) -> Vec<GradientStop> {
+        if self.stops.len() <= Self::MAX_STOPS {
+            return self.stops.clone();
+        }
+        (0..Self::MAX_STOPS)
+            .map(|i| {
+                let offset = i as f32 / (Self::MAX_STOPS - 1) as f32;
+                GradientStop {
+                    offset,
+                    color: self.color_at(&self.stops, offset),
+                }
+            })
+            .collect()
+    }
+
+    // Bindings for the uniforms GRADIENT_SHADER_LIB declares
+    pub fn uniforms(gradient: Option<&Gradient>) -> Vec<UniformBinding> {
+        let mut offsets = [[0.0; 4]; Self::MAX_STOPS / 4];
+        let mut colors = [[0.0; 4]; Self::MAX_STOPS];
+
+        let (kind, params, count) = match gradient {
+            Some(gradient) => {
+                let stops = gradient.shader_stops();
+                for (i, stop) in stops.iter().enumerate() {
+                    offsets[i / 4][i % 4] = stop.offset;
+                    colors[i] = stop.color.to_array();
+                }
+                let (kind, params) = match gradient.kind {
+                    GradientKind::Linear { start, end } => (1.0, [start.x, start.y, end.x, end.y]),
+                    GradientKind::Radial { center, radius } => (2.0, [center.x, center.y, radius, 0.0]),
+                    GradientKind::Conic { center, angle } => (3.0, [center.x, center.y, angle, 0.0]),
+                };
+                (kind, params, stops.len() as f32)
+            },
+            None => (0.0, [0.0; 4], 0.0),
+        };
+
+        vec![
+            UniformBinding::new("gradient_type", DrawUniform::Float(kind)),
+            UniformBinding::new("gradient_params", DrawUniform::Vec4(params)),
+            UniformBinding::new("gradient_stop_count", DrawUniform::Float(count)),
+            UniformBinding::new("gradient_offsets", DrawUniform::Vec4Array(offsets.to_vec())),
+            UniformBinding::new("gradient_colors", DrawUniform::Vec4Array(colors.to_vec())),
+        ]
+    }
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    Color::new(
+        a.r + (b.r - a.r) * t,
+        a.g + (b.g - a.g) * t,
+        a.b + (b.b - a.b) * t,
+        a.a + (b.a - a.a) * t,
+    )
+}
+
+// Shared by every shader that fills with a `Gradient`. Expects the uniforms
+// produced by `Gradient::uniforms`.
+pub const GRADIENT_SHADER_LIB: &str = r#"
+uniform float gradient_type;
+uniform vec4 gradient_params;
+uniform float gradient_stop_count;
+uniform vec4 gradient_offsets[2];
+uniform vec4 gradient_colors[8];
+
+float gradient_offset(int i) {
+    vec4 o = gradient_offsets[i / 4];
+    int j = i - (i / 4) * 4;
+    return j == 0 ? o.x : j == 1 ? o.y : j == 2 ? o.z : o.w;
+}
+
+vec4 gradient_color(vec2 uv) {
+    float t;
+    if (gradient_type < 1.5) {
+        vec2 d = gradient_params.zw - gradient_params.xy;
+        t = dot(uv - gradient_params.xy, d) / max(dot(d, d), 1e-6);
+    } else if (gradient_type < 2.5) {
+        t = length(uv - gradient_params.xy) / max(gradient_params.z, 1e-6);
+    } else {
+        vec2 p = uv - gradient_params.xy;
+        t = fract((atan(p.y, p.x) - gradient_params.z) / 6.28318530718);
+    }
+    t = clamp(t, 0.0, 1.0);
+
+    int count = int(gradient_stop_count);
+    vec4 color = gradient_colors[0];
+    for (int i = 1; i < 8; i++) {
+        if (i >= count) {
+            break;
+        }
+        float o0 = gradient_offset(i - 1);
+        float o1 = gradient_offset(i);
+        if (t >= o1) {
+            color = gradient_colors[i];
+        } else if (t > o0) {
+            color = mix(gradient_colors[i - 1], gradient_colors[i], (t - o0) / max(o1 - o0, 1e-6));
+            break;
+        }
+    }
+    return color;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // These check the stops and uniforms handed to the shader. Nothing here
+    // renders the shader: there is no software renderer to compare against
+    // reference images yet.
+
+    fn assert_color(actual: Color, expected: Color) {
+        let close = actual.to_array().iter().zip(expected.to_array()).all(|(a, b)| (a - b).abs() < 1e-5);
+        assert!(close, "{:?} != {:?}", actual, expected);
+    }
+
+    fn offsets(stops: &[GradientStop]) -> Vec<f32> {
+        stops.iter().map(|stop| stop.offset).collect()
+    }
+
+    #[test]
+    fn stops_are_sorted_and_clamped() {
+        let gradient = Gradient::horizontal()
+            .with_stop(0.8, Color::red())
+            .with_stop(1.5, Color::green())
+            .with_stop(0.2, Color::blue())
+            .with_stop(-1.0, Color::white())
+            .with_stop(0.2, Color::black());
+
+        assert_eq!(offsets(&gradient.stops), vec![0.0, 0.2, 0.2, 0.8, 1.0]);
+        // Equal offsets keep insertion order, giving a hard edge
+        assert_eq!(gradient.stops[1].color, Color::blue());
+        assert_eq!(gradient.stops[2].color, Color::black());
+    }
+
+    #[test]
+    fn long_gradients_are_resampled_to_max_stops() {
+        let mut gradient = Gradient::horizontal();
+        for i in 0..12 {
+            let t = i as f32 / 11.0;
+            gradient.add_stop(t, Color::new(t, 0.0, 1.0 - t, 1.0));
+        }
+
+        let stops = gradient.shader_stops();
+        assert_eq!(stops.len(), Gradient::MAX_STOPS);
+        assert_eq!(stops[0].offset, 0.0);
+        assert_eq!(stops[Gradient::MAX_STOPS - 1].offset, 1.0);
+        for stop in &stops {
+            assert_color(stop.color, Color::new(stop.offset, 0.0, 1.0 - stop.offset, 1.0));
+        }
+
+        let short = Gradient::vertical().with_stop(0.0, Color::red()).with_stop(1.0, Color::blue());
+        assert_eq!(short.shader_stops(), short.stops);
+    }
+
+    #[test]
+    fn linear_gradients_project_onto_the_axis_and_clamp() {
+        let gradient = Gradient::horizontal().with_stop(0.0, Color::black()).with_stop(1.0, Color::white());
+
+        assert_color(gradient.sample(Vec2::new(0.25, 0.9)), Color::new(0.25, 0.25, 0.25, 1.0));
+        assert_color(gradient.sample(Vec2::new(-1.0, 0.5)), Color::black());
+        assert_color(gradient.sample(Vec2::new(2.0, 0.5)), Color::white());
+
+        let diagonal = Gradient::linear(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0));
+        assert!((diagonal.offset_at(Vec2::new(1.0, 0.0)) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn radial_gradients_grow_with_distance_from_the_center() {
+        let gradient = Gradient::radial(Vec2::new(0.5, 0.5), 0.5)
+            .with_stop(0.0, Color::red())
+            .with_stop(1.0, Color::blue());
+
+        assert_color(gradient.sample(Vec2::new(0.5, 0.5)), Color::red());
+        assert_color(gradient.sample(Vec2::new(0.5, 0.25)), Color::new(0.5, 0.0, 0.5, 1.0));
+        assert_color(gradient.sample(Vec2::new(1.0, 1.0)), Color::blue());
+    }
+
+    #[test]
+    fn conic_gradients_turn_clockwise_from_the_start_angle() {
+        let center = Vec2::new(0.5, 0.5);
+        let gradient = Gradient::conic(center, 0.0);
+
+        assert!((gradient.offset_at(Vec2::new(1.0, 0.5)) - 0.0).abs() < 1e-6);
+        assert!((gradient.offset_at(Vec2::new(0.5, 1.0)) - 0.25).abs() < 1e-6);
+        assert!((gradient.offset_at(Vec2::new(0.0, 0.5)) - 0.5).abs() < 1e-6);
+        assert!((gradient.offset_at(Vec2::new(0.5, 0.0)) - 0.75).abs() < 1e-6);
+
+        let rotated = Gradient::conic(center, std::f32::consts::FRAC_PI_2);
+        assert!((rotated.offset_at(Vec2::new(0.5, 1.0)) - 0.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,489 @@
+use crate::platform::geometry::{GeometryId, VertexFormat};
+use crate::platform::shader::{ShaderId, ShaderUniformType};
+use crate::platform::draw_list::{DrawItem, DrawListId};
+use crate::platform::texture::Texture;
+use crate::platform::Cx;
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::shader::ShaderBuilder;
+use crate::draw::color::Color;
+use crate::draw::rect::Rect;
+use crate::draw::quad::{create_quad_geometry, CornerRadii};
+
+// How the image is sized inside the rect it is drawn into, as CSS object-fit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageFit {
+    // Stretch to the rect, ignoring the aspect ratio
+    #[default]
+    Fill,
+    // Largest size that fits entirely, letterboxed
+    Contain,
+    // Smallest size that covers the rect, cropped
+    Cover,
+    // Natural pixel size, centered and cropped
+    None,
+    // Like None, but shrinks to Contain when the image is larger than the rect
+    ScaleDown,
+}
+
+// Insets in source pixels that stay unscaled; the edges stretch along one
+// axis and the center along both
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NineSlice {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl NineSlice {
+    pub fn new(top: f32, right: f32, bottom: f32, left: f32) -> Self {
+        Self { top, right, bottom, left }
+    }
+
+    pub fn all(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DrawImage {
+    pub texture: Option<Texture>,
+    pub fit: ImageFit,
+    // Normalized sub-rect of the texture, for sprite atlases
+    pub uv_rect: Rect,
+    pub nine_slice: Option<NineSlice>,
+    // Multiplied with the sampled color
+    pub tint: Color,
+    pub opacity: f32,
+    // Rounds the visible part of the image
+    pub corner_radii: CornerRadii,
+    pub shader_id: Option<ShaderId>,
+    pub geometry_id: Option<GeometryId>,
+}
+
+impl Default for DrawImage {
+    fn default() -> Self {
+        Self {
+            texture: None,
+            fit: ImageFit::Fill,
+            uv_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+            nine_slice: None,
+            tint: Color::white(),
+            opacity: 1.0,
+            corner_radii: CornerRadii::default(),
+            shader_id: None,
+            geometry_id: None,
+        }
+    }
+}
+
+impl DrawImage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn with_fit(mut self, fit: ImageFit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    pub fn with_uv_rect(mut self, uv_rect: Rect) -> Self {
+        self.uv_rect = uv_rect;
+        self
+    }
+
+    // Selects a sprite by its pixel rect in the texture
+    pub fn with_source_rect(mut self, source: Rect) -> Self {
+        self.set_source_rect(source);
+        self
+    }
+
+    pub fn with_nine_slice(mut self, nine_slice: NineSlice) -> Self {
+        self.nine_slice = Some(nine_slice);
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self {
+        self.corner_radii = CornerRadii::all(corner_radius);
+        self
+    }
+
+    pub fn with_corner_radii(mut self, corner_radii: CornerRadii) -> Self {
+        self.corner_radii = corner_radii;
+        self
+    }
+
+    pub fn set_texture(&mut self, texture: Texture) {
+        self.texture = Some(texture);
+    }
+
+    // Needs the texture to be set, since uv_rect is normalized
+    pub fn set_source_rect(&mut self, source: Rect) {
+        if let Some(texture) = &self.texture {
+            let (w, h) = (texture.width.max(1) as f32, texture.height.max(1) as f32);
+            self.uv_rect = Rect::new(source.x() / w, source.y() / h, source.width() / w, source.height() / h);
+        }
+    }
+
+    // Size of the selected part of the texture in pixels
+    pub fn natural_size(&self) -> (f32, f32) {
+        match &self.texture {
+            Some(texture) => (
+                self.uv_rect.width().abs() * texture.width as f32,
+                self.uv_rect.height().abs() * texture.height as f32,
+            ),
+            None => (0.0, 0.0),
+        }
+    }
+
+    // Where the whole image lands for `rect` before cropping to it
+    pub fn fit_rect(&self, rect: &Rect) -> Rect {
+        let (sw, sh) = self.natural_size();
+        if sw <= 0.0 || sh <= 0.0 {
+            return *rect;
+        }
+
+        let contain = (rect.width() / sw).min(rect.height() / sh);
+        let scale = match self.fit {
+            ImageFit::Fill => return *rect,
+            ImageFit::Contain => contain,
+            ImageFit::Cover => (rect.width() / sw).max(rect.height() / sh),
+            ImageFit::None => 1.0,
+            ImageFit::ScaleDown => contain.min(1.0),
+        };
+
+        let size = (sw * scale, sh * scale);
+        let center = rect.center();
+        Rect::new(center.x - size.0 * 0.5, center.y - size.1 * 0.5, size.0, size.1)
+    }
+
+    pub fn init(&mut self, cx: &mut Cx) {
+        if self.geometry_id.is_none() {
+            self.geometry_id = Some(create_quad_geometry(cx));
+        }
+
+        if self.shader_id.is_none() {
+            let shader_id = ShaderBuilder::new()
+                .vertex_shader(IMAGE_VERTEX_SHADER)
+                .fragment_shader(IMAGE_FRAGMENT_SHADER)
+                .uniform("image_texture", ShaderUniformType::Texture2D)
+                .instance("rect", VertexFormat::Float4)
+                .instance("uv_rect", VertexFormat::Float4)
+                .instance("frame", VertexFormat::Float4)
+                .instance("tint", VertexFormat::Float4)
+                .instance("corner_radii", VertexFormat::Float4)
+                .geometry(self.geometry_id.unwrap())
+                .build(cx)
+                .expect("DrawImage shader failed validation");
+            self.shader_id = Some(shader_id);
+        }
+    }
+
+    // Nothing is drawn without a texture
+    pub fn draw(&mut self, cx: &mut Cx2d, draw_list_id: DrawListId, rect: &Rect) {
+        if self.shader_id.is_none() || self.geometry_id.is_none() {
+            self.init(cx);
+        }
+
+        let Some(texture) = &self.texture else {
+            return;
+        };
+
+        if let Some(clip_rect) = cx.clip_rect() {
+            if !clip_rect.intersects(&cx.transformed_rect(rect)) {
+                return;
+            }
+        }
+
+        let pieces = self.pieces(rect);
+        let Some(frame) = pieces.iter().map(|(dest, _)| *dest).reduce(|a, b| a.union(&b)) else {
+            return;
+        };
+
+        let tint = Color::new(self.tint.r, self.tint.g, self.tint.b, self.tint.a * self.opacity.clamp(0.0, 1.0));
+        let mut instances = Vec::with_capacity(pieces.len() * 20);
+        for (dest, uv) in &pieces {
+            instances.extend_from_slice(&[dest.x(), dest.y(), dest.width(), dest.height()]);
+            instances.extend_from_slice(&[uv.x(), uv.y(), uv.width(), uv.height()]);
+            instances.extend_from_slice(&[frame.x(), frame.y(), frame.width(), frame.height()]);
+            instances.extend_from_slice(&tint.to_array());
+            instances.extend_from_slice(&self.corner_radii.to_array());
+        }
+
+        let draw_item = DrawItem {
+            shader_id: self.shader_id.unwrap(),
+            geometry_id: self.geometry_id.unwrap(),
+            uniforms: Vec::new(),
+            // Images batch with other draws of the same texture
+            textures: vec![texture.clone()],
+            instances,
+            instance_count: pieces.len() as u32,
+            clip: cx.current_clip(),
+            transform: cx.current_transform(),
+            blend: cx.current_blend(),
+            sub_list: None,
+        };
+
+        cx.append_draw_item(draw_list_id, draw_item);
+    }
+
+    // Destination and uv rects for each visible piece: one for a plain image,
+    // up to nine for a nine-slice, all cropped to `rect`
+    fn pieces(&self, rect: &Rect) -> Vec<(Rect, Rect)> {
+        let frame = self.fit_rect(rect);
+        let uv = self.uv_rect;
+
+        let mut pieces = Vec::with_capacity(9);
+        match self.nine_slice {
+            Some(slice) => {
+                let (sw, sh) = self.natural_size();
+                if sw <= 0.0 || sh <= 0.0 {
+                    return pieces;
+                }
+                let xs = slice_stops(frame.x(), frame.width(), slice.left, slice.right);
+                let ys = slice_stops(frame.y(), frame.height(), slice.top, slice.bottom);
+                let us = [uv.x(), uv.x() + uv.width() * slice.left / sw, uv.right() - uv.width() * slice.right / sw, uv.right()];
+                let vs = [uv.y(), uv.y() + uv.height() * slice.top / sh, uv.bottom() - uv.height() * slice.bottom / sh, uv.bottom()];
+                for row in 0..3 {
+                    for col in 0..3 {
+                        let dest = Rect::new(xs[col], ys[row], xs[col + 1] - xs[col], ys[row + 1] - ys[row]);
+                        let piece_uv = Rect::new(us[col], vs[row], us[col + 1] - us[col], vs[row + 1] - vs[row]);
+                        pieces.push((dest, piece_uv));
+                    }
+                }
+            },
+            None => pieces.push((frame, uv)),
+        }
+
+        pieces.into_iter().filter_map(|(dest, uv)| crop_piece(dest, uv, rect)).collect()
+    }
+}
+
+// Edges of the three slices along one axis. Insets keep their pixel size
+// and shrink proportionally when they don't fit.
+fn slice_stops(start: f32, length: f32, before: f32, after: f32) -> [f32; 4] {
+    let insets = before + after;
+    let scale = if insets > length && insets > 0.0 { length / insets } else { 1.0 };
+    [start, start + before * scale, start + length - after * scale, start + length]
+}
+
+// Crops a piece to `bounds`, moving its uv edges by the same fraction
+fn crop_piece(dest: Rect, uv: Rect, bounds: &Rect) -> Option<(Rect, Rect)> {
+    if dest.width() <= 0.0 || dest.height() <= 0.0 {
+        return None;
+    }
+    let visible = dest.intersection(bounds)?;
+    let u0 = (visible.x() - dest.x()) / dest.width();
+    let u1 = (visible.right() - dest.x()) / dest.width();
+    let v0 = (visible.y() - dest.y()) / dest.height();
+    let v1 = (visible.bottom() - dest.y()) / dest.height();
+    let cropped = Rect::new(
+        uv.x() + uv.width() * u0,
+        uv.y() + uv.height() * v0,
+        uv.width() * (u1 - u0),
+        uv.height() * (v1 - v0),
+    );
+    Some((visible, cropped))
+}
+
+const IMAGE_VERTEX_SHADER: &str = r#"
+#version 300 es
+precision highp float;
+
+in vec2 position;
+in vec2 uv;
+
+// Per instance
+in vec4 rect;
+in vec4 uv_rect;
+in vec4 frame; // visible image bounds, for corner rounding
+in vec4 tint;
+in vec4 corner_radii; // top-left, top-right, bottom-right, bottom-left
+
+uniform mat4 view_transform;
+uniform mat4 model_transform;
+
+out vec2 v_tex_uv;
+out vec2 v_frame_pos;
+out vec2 v_world;
+flat out vec2 v_frame_size;
+flat out vec4 v_tint;
+flat out vec4 v_corner_radii;
+
+void main() {
+    v_tex_uv = uv_rect.xy + uv * uv_rect.zw;
+    vec2 pos = rect.xy + position * rect.zw;
+    v_frame_pos = pos - frame.xy;
+    v_frame_size = frame.zw;
+    v_tint = tint;
+    v_corner_radii = corner_radii;
+    vec4 world = model_transform * vec4(pos, 0.0, 1.0);
+    v_world = world.xy;
+    gl_Position = view_transform * world;
+}
+"#;
+
+const IMAGE_FRAGMENT_SHADER: &str = r#"
+#version 300 es
+precision highp float;
+
+in vec2 v_tex_uv;
+in vec2 v_frame_pos;
+in vec2 v_world;
+flat in vec2 v_frame_size;
+flat in vec4 v_tint;
+flat in vec4 v_corner_radii;
+
+uniform sampler2D image_texture;
+
+// Supplied by the backend from DrawItem::clip
+uniform vec4 clip_rect;
+uniform float clip_radius;
+
+out vec4 frag_color;
+
+float rounded_box(vec2 p, vec2 b, float r) {
+    return length(max(abs(p) - b + r, 0.0)) - r;
+}
+
+float rounded_box4(vec2 p, vec2 b, vec4 r) {
+    float corner = p.x > 0.0 ? (p.y > 0.0 ? r.z : r.y) : (p.y > 0.0 ? r.w : r.x);
+    corner = min(corner, min(b.x, b.y));
+    vec2 q = abs(p) - b + corner;
+    return min(max(q.x, q.y), 0.0) + length(max(q, 0.0)) - corner;
+}
+
+void main() {
+    vec2 clip_half = clip_rect.zw * 0.5;
+    if (clip_radius > 0.0 && rounded_box(v_world - clip_rect.xy - clip_half, clip_half, clip_radius) > 0.0) {
+        discard;
+    }
+
+    float coverage = 1.0;
+    if (max(max(v_corner_radii.x, v_corner_radii.y), max(v_corner_radii.z, v_corner_radii.w)) > 0.0) {
+        vec2 center = v_frame_size * 0.5;
+        float box = rounded_box4(v_frame_pos - center, center, v_corner_radii);
+        coverage = clamp(0.5 - box / max(fwidth(box), 1e-4), 0.0, 1.0);
+    }
+
+    vec4 color = texture(image_texture, v_tex_uv) * v_tint;
+    color.a *= coverage;
+    if (color.a <= 0.0) {
+        discard;
+    }
+    frag_color = color;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::texture::{TextureId, TextureFormat};
+
+    fn image(width: usize, height: usize) -> DrawImage {
+        DrawImage::new().with_texture(Texture::new(TextureId(1), width, height, TextureFormat::Rgba8))
+    }
+
+    fn assert_rect(actual: Rect, expected: (f32, f32, f32, f32)) {
+        let actual_edges = [actual.x(), actual.y(), actual.width(), actual.height()];
+        let close = actual_edges.iter().zip([expected.0, expected.1, expected.2, expected.3]).all(|(a, b)| (a - b).abs() < 1e-5);
+        assert!(close, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn fit_modes_keep_the_aspect_ratio_and_center() {
+        let square = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let large = Rect::new(0.0, 0.0, 400.0, 400.0);
+        let fit = |fit: ImageFit, rect: &Rect| image(200, 100).with_fit(fit).fit_rect(rect);
+
+        assert_eq!(fit(ImageFit::Fill, &square), square);
+        assert_eq!(fit(ImageFit::Contain, &square), Rect::new(0.0, 25.0, 100.0, 50.0));
+        assert_eq!(fit(ImageFit::Cover, &square), Rect::new(-50.0, 0.0, 200.0, 100.0));
+        assert_eq!(fit(ImageFit::None, &large), Rect::new(100.0, 150.0, 200.0, 100.0));
+        assert_eq!(fit(ImageFit::Contain, &large), Rect::new(0.0, 100.0, 400.0, 200.0));
+    }
+
+    #[test]
+    fn scale_down_only_shrinks() {
+        let fit = |rect: &Rect| image(200, 100).with_fit(ImageFit::ScaleDown).fit_rect(rect);
+        // Small enough already: natural size, like None
+        assert_eq!(fit(&Rect::new(0.0, 0.0, 400.0, 400.0)), Rect::new(100.0, 150.0, 200.0, 100.0));
+        // Too large: shrinks like Contain
+        assert_eq!(fit(&Rect::new(0.0, 0.0, 100.0, 100.0)), Rect::new(0.0, 25.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn fit_uses_the_selected_sprite_size() {
+        let sprite = image(200, 100).with_source_rect(Rect::new(0.0, 0.0, 50.0, 100.0)).with_fit(ImageFit::Contain);
+        assert_eq!(sprite.natural_size(), (50.0, 100.0));
+        assert_eq!(sprite.fit_rect(&Rect::new(0.0, 0.0, 100.0, 100.0)), Rect::new(25.0, 0.0, 50.0, 100.0));
+    }
+
+    #[test]
+    fn cover_crops_the_image_and_its_uvs() {
+        let pieces = image(200, 100).with_fit(ImageFit::Cover).pieces(&Rect::new(0.0, 0.0, 100.0, 100.0));
+        assert_eq!(pieces.len(), 1);
+        assert_rect(pieces[0].0, (0.0, 0.0, 100.0, 100.0));
+        assert_rect(pieces[0].1, (0.25, 0.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn nine_slices_keep_their_insets() {
+        let pieces = image(30, 30).with_nine_slice(NineSlice::all(10.0)).pieces(&Rect::new(0.0, 0.0, 90.0, 60.0));
+        assert_eq!(slice_stops(0.0, 90.0, 10.0, 10.0), [0.0, 10.0, 80.0, 90.0]);
+        assert_eq!(pieces.len(), 9);
+
+        let third = 1.0 / 3.0;
+        // Corners stay 10 px, edges stretch along one axis, the center both
+        assert_rect(pieces[0].0, (0.0, 0.0, 10.0, 10.0));
+        assert_rect(pieces[0].1, (0.0, 0.0, third, third));
+        assert_rect(pieces[1].0, (10.0, 0.0, 70.0, 10.0));
+        assert_rect(pieces[4].0, (10.0, 10.0, 70.0, 40.0));
+        assert_rect(pieces[4].1, (third, third, third, third));
+        assert_rect(pieces[8].0, (80.0, 50.0, 10.0, 10.0));
+        assert_rect(pieces[8].1, (2.0 * third, 2.0 * third, third, third));
+    }
+
+    #[test]
+    fn nine_slice_insets_shrink_to_fit() {
+        // 10 + 30 px of insets in 20 px keep their 1:3 ratio
+        assert_eq!(slice_stops(5.0, 20.0, 10.0, 30.0), [5.0, 10.0, 10.0, 25.0]);
+
+        // The middle column is empty and dropped
+        let pieces = image(30, 30).with_nine_slice(NineSlice::all(10.0)).pieces(&Rect::new(0.0, 0.0, 15.0, 60.0));
+        assert_eq!(pieces.len(), 6);
+        assert_rect(pieces[0].0, (0.0, 0.0, 7.5, 10.0));
+        assert_rect(pieces[1].0, (7.5, 0.0, 7.5, 10.0));
+        assert_rect(pieces[1].1, (2.0 / 3.0, 0.0, 1.0 / 3.0, 1.0 / 3.0));
+    }
+
+    #[test]
+    fn cropping_moves_uv_edges_by_the_same_fraction() {
+        let dest = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let (visible, uv) = crop_piece(dest, Rect::new(0.2, 0.4, 0.4, 0.2), &Rect::new(25.0, 0.0, 50.0, 25.0)).unwrap();
+        assert_rect(visible, (25.0, 0.0, 50.0, 25.0));
+        assert_rect(uv, (0.3, 0.4, 0.2, 0.1));
+
+        // Mirrored uvs crop from the mirrored side
+        let (_, uv) = crop_piece(dest, Rect::new(1.0, 0.0, -1.0, 1.0), &Rect::new(0.0, 0.0, 50.0, 50.0)).unwrap();
+        assert_rect(uv, (1.0, 0.0, -0.5, 1.0));
+
+        assert!(crop_piece(dest, Rect::new(0.0, 0.0, 1.0, 1.0), &Rect::new(200.0, 0.0, 10.0, 10.0)).is_none());
+        assert!(crop_piece(Rect::new(0.0, 0.0, 0.0, 50.0), Rect::new(0.0, 0.0, 1.0, 1.0), &dest).is_none());
+    }
+}
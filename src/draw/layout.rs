@@ -15,6 +15,8 @@ pub enum LayoutSize {
 }
 
 impl LayoutSize {
+    // The size on its own, with `available` all to itself. Along a turtle's
+    // direction, Flex and Fill share the space with siblings instead.
     pub fn resolve(&self, available: f32, content: f32) -> f32 {
         match self {
             LayoutSize::Fixed(size) => *size,
@@ -23,6 +25,16 @@ impl LayoutSize {
             LayoutSize::Fit => content,
         }
     }
+
+    // Share of the leftover space along the layout direction; Fill counts
+    // as Flex(1.0)
+    pub fn flex_factor(&self) -> Option<f32> {
+        match self {
+            LayoutSize::Flex(factor) => Some(factor.max(0.0)),
+            LayoutSize::Fill => Some(1.0),
+            LayoutSize::Fixed(_) | LayoutSize::Fit => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -44,13 +56,43 @@ impl LayoutAlign {
     }
 }
 
+// Where items go along the layout direction when they don't fill it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JustifyContent {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+impl JustifyContent {
+    // Offset of the first item and extra gap between items, given the free
+    // space and the item count
+    pub fn resolve(&self, free: f32, count: usize) -> (f32, f32) {
+        let free = free.max(0.0);
+        let n = count as f32;
+        match self {
+            JustifyContent::Start => (0.0, 0.0),
+            JustifyContent::Center => (free * 0.5, 0.0),
+            JustifyContent::End => (free, 0.0),
+            JustifyContent::SpaceBetween if count > 1 => (0.0, free / (n - 1.0)),
+            JustifyContent::SpaceBetween => (0.0, 0.0),
+            JustifyContent::SpaceAround if count > 0 => (free / n * 0.5, free / n),
+            JustifyContent::SpaceAround => (0.0, 0.0),
+            JustifyContent::SpaceEvenly => (free / (n + 1.0), free / (n + 1.0)),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Layout {
     pub direction: LayoutDirection,
     pub width: LayoutSize,
     pub height: LayoutSize,
     pub align_items: LayoutAlign,
-    pub justify_content: LayoutAlign,
+    pub justify_content: JustifyContent,
     pub padding: Vec2,
     pub spacing: f32,
 }
@@ -62,7 +104,7 @@ impl Layout {
             width: LayoutSize::Fill,
             height: LayoutSize::Fit,
             align_items: LayoutAlign::Start,
-            justify_content: LayoutAlign::Start,
+            justify_content: JustifyContent::Start,
             padding: Vec2::new(0.0, 0.0),
             spacing: 0.0,
         }
@@ -74,7 +116,7 @@ impl Layout {
             width: LayoutSize::Fill,
             height: LayoutSize::Fit,
             align_items: LayoutAlign::Start,
-            justify_content: LayoutAlign::Start,
+            justify_content: JustifyContent::Start,
             padding: Vec2::new(0.0, 0.0),
             spacing: 0.0,
         }
@@ -95,7 +137,7 @@ impl Layout {
         self
     }
     
-    pub fn with_justify_content(mut self, justify_content: LayoutAlign) -> Self {
+    pub fn with_justify_content(mut self, justify_content: JustifyContent) -> Self {
         self.justify_content = justify_content;
         self
     }
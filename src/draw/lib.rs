@@ -3,8 +3,18 @@ pub mod draw_list_2d;
 pub mod shader;
 pub mod turtle;
 pub mod text;
+pub mod font_collection;
+pub mod rich_text;
 pub mod color;
 pub mod quad;
+pub mod gradient;
+pub mod shadow;
+pub mod backdrop_blur;
+pub mod tessellator;
+pub mod path;
+pub mod svg;
+pub mod image;
+pub mod contrast_overlay;
 pub mod rect;
 pub mod layout;
 
@@ -14,8 +24,18 @@ pub use crate::draw_list_2d::DrawList2d;
 pub use crate::shader::*;
 pub use crate::draw::turtle::*;
 pub use crate::text::*;
+pub use crate::font_collection::*;
+pub use crate::rich_text::*;
 pub use crate::draw::color::*;
 pub use crate::quad::*;
+pub use crate::gradient::*;
+pub use crate::shadow::*;
+pub use crate::backdrop_blur::*;
+pub use crate::tessellator::*;
+pub use crate::path::*;
+pub use crate::svg::*;
+pub use crate::image::*;
+pub use crate::contrast_overlay::*;
 pub use crate::draw::rect::*;
 pub use crate::draw::layout::*;
 
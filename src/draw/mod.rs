@@ -3,8 +3,18 @@ pub mod draw_list_2d;
 pub mod shader;
 pub mod turtle;
 pub mod text;
+pub mod font_collection;
+pub mod rich_text;
 pub mod color;
 pub mod quad;
+pub mod gradient;
+pub mod shadow;
+pub mod backdrop_blur;
+pub mod tessellator;
+pub mod path;
+pub mod svg;
+pub mod image;
+pub mod contrast_overlay;
 pub mod rect;
 pub mod layout;
 
@@ -14,8 +24,18 @@ pub use crate::draw::draw_list_2d::DrawList2d;
 pub use crate::draw::shader::*;
 pub use crate::draw::turtle::*;
 pub use crate::draw::text::*;
+pub use crate::draw::font_collection::*;
+pub use crate::draw::rich_text::*;
 pub use crate::draw::color::*;
 pub use crate::draw::quad::*;
+pub use crate::draw::gradient::*;
+pub use crate::draw::shadow::*;
+pub use crate::draw::backdrop_blur::*;
+pub use crate::draw::tessellator::*;
+pub use crate::draw::path::*;
+pub use crate::draw::svg::*;
+pub use crate::draw::image::*;
+pub use crate::draw::contrast_overlay::*;
 pub use crate::draw::rect::*;
 pub use crate::draw::layout::*;
 
@@ -0,0 +1,590 @@
+use crate::platform::math::{Vec2, Affine2};
+use crate::platform::geometry::{Geometry, GeometryId, VertexFormat};
+use crate::platform::shader::ShaderId;
+use crate::platform::draw_list::{DrawItem, DrawListId};
+use crate::platform::Cx;
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::shader::ShaderBuilder;
+use crate::draw::color::Color;
+use crate::draw::rect::Rect;
+use crate::draw::gradient::{Gradient, GRADIENT_SHADER_LIB};
+use crate::draw::tessellator::{
+    Polyline, Tessellation, FillRule, StrokeStyle, tessellate_fill, tessellate_stroke,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathCommand {
+    MoveTo(Vec2),
+    LineTo(Vec2),
+    QuadTo(Vec2, Vec2),
+    CubicTo(Vec2, Vec2, Vec2),
+    Close,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    pub commands: Vec<PathCommand>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.commands.clear();
+        self
+    }
+
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.commands.push(PathCommand::MoveTo(Vec2::new(x, y)));
+        self
+    }
+
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.commands.push(PathCommand::LineTo(Vec2::new(x, y)));
+        self
+    }
+
+    pub fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) -> &mut Self {
+        self.commands.push(PathCommand::QuadTo(Vec2::new(cx, cy), Vec2::new(x, y)));
+        self
+    }
+
+    pub fn cubic_to(&mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) -> &mut Self {
+        self.commands.push(PathCommand::CubicTo(Vec2::new(c1x, c1y), Vec2::new(c2x, c2y), Vec2::new(x, y)));
+        self
+    }
+
+    // Circular arc like the canvas API: angles in radians, clockwise on screen
+    // when `end_angle > start_angle`. Connects to the current point with a line,
+    // or starts a new subpath if there is none. Stored as cubics of at most 90°.
+    pub fn arc(&mut self, center_x: f32, center_y: f32, radius: f32, start_angle: f32, end_angle: f32) -> &mut Self {
+        let center = Vec2::new(center_x, center_y);
+        let point_at = |angle: f32| center + Vec2::new(angle.cos(), angle.sin()) * radius;
+
+        let start = point_at(start_angle);
+        match self.commands.last() {
+            None | Some(PathCommand::Close) => self.commands.push(PathCommand::MoveTo(start)),
+            Some(_) => self.commands.push(PathCommand::LineTo(start)),
+        }
+
+        self.push_elliptic_arc(center, Vec2::new(radius, radius), 0.0, start_angle, end_angle - start_angle);
+        self
+    }
+
+    // Elliptical arc from the current point to (x, y) with SVG `A` semantics;
+    // radii too small to reach the end point are scaled up
+    #[allow(clippy::too_many_arguments)]
+    pub fn arc_to(&mut self, rx: f32, ry: f32, x_axis_rotation: f32, large_arc: bool, sweep: bool, x: f32, y: f32) -> &mut Self {
+        let p0 = self.current_point().unwrap_or_default();
+        let p1 = Vec2::new(x, y);
+        if p0 == p1 {
+            return self;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            return self.line_to(x, y);
+        }
+
+        let (sin, cos) = x_axis_rotation.sin_cos();
+        let half = (p0 - p1) * 0.5;
+        let x1 = cos * half.x + sin * half.y;
+        let y1 = -sin * half.x + cos * half.y;
+
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+        let coef = sign * (num / den).max(0.0).sqrt();
+        let cx1 = coef * rx * y1 / ry;
+        let cy1 = -coef * ry * x1 / rx;
+        let mid = (p0 + p1) * 0.5;
+        let center = Vec2::new(cos * cx1 - sin * cy1 + mid.x, sin * cx1 + cos * cy1 + mid.y);
+
+        let angle = |u: Vec2, v: Vec2| u.cross(v).atan2(u.dot(v));
+        let u = Vec2::new((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let v = Vec2::new((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+        let start_angle = angle(Vec2::new(1.0, 0.0), u);
+        let mut sweep_angle = angle(u, v);
+        if !sweep && sweep_angle > 0.0 {
+            sweep_angle -= std::f32::consts::TAU;
+        } else if sweep && sweep_angle < 0.0 {
+            sweep_angle += std::f32::consts::TAU;
+        }
+
+        self.push_elliptic_arc(center, Vec2::new(rx, ry), x_axis_rotation, start_angle, sweep_angle);
+        self
+    }
+
+    // Cubics of at most 90° each; assumes the current point is the arc start
+    fn push_elliptic_arc(&mut self, center: Vec2, radii: Vec2, rotation: f32, start_angle: f32, sweep: f32) {
+        let (sin, cos) = rotation.sin_cos();
+        let rotate = |v: Vec2| Vec2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y);
+        let point_at = |a: f32| center + rotate(Vec2::new(radii.x * a.cos(), radii.y * a.sin()));
+        let tangent_at = |a: f32| rotate(Vec2::new(-radii.x * a.sin(), radii.y * a.cos()));
+
+        let segments = (sweep.abs() / std::f32::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+        let step = sweep / segments as f32;
+        // Control point distance for a cubic approximating `step` radians
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+        for i in 0..segments {
+            let a0 = start_angle + step * i as f32;
+            let a1 = a0 + step;
+            let p1 = point_at(a1);
+            self.commands.push(PathCommand::CubicTo(point_at(a0) + tangent_at(a0) * k, p1 - tangent_at(a1) * k, p1));
+        }
+    }
+
+    // End point of the last command; after `close`, the subpath's start
+    pub fn current_point(&self) -> Option<Vec2> {
+        let mut start = None;
+        let mut current = None;
+        for command in &self.commands {
+            match *command {
+                PathCommand::MoveTo(p) => {
+                    start = Some(p);
+                    current = Some(p);
+                },
+                PathCommand::LineTo(p) | PathCommand::QuadTo(_, p) | PathCommand::CubicTo(_, _, p) => current = Some(p),
+                PathCommand::Close => current = start,
+            }
+        }
+        current
+    }
+
+    pub fn close(&mut self) -> &mut Self {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+
+    pub fn rect(&mut self, rect: &Rect) -> &mut Self {
+        self.move_to(rect.x(), rect.y())
+            .line_to(rect.right(), rect.y())
+            .line_to(rect.right(), rect.bottom())
+            .line_to(rect.x(), rect.bottom())
+            .close()
+    }
+
+    // Always a new subpath, unlike `arc` which would line to the start point
+    pub fn circle(&mut self, center_x: f32, center_y: f32, radius: f32) -> &mut Self {
+        let center = Vec2::new(center_x, center_y);
+        self.commands.push(PathCommand::MoveTo(center + Vec2::new(radius, 0.0)));
+        self.push_elliptic_arc(center, Vec2::new(radius, radius), 0.0, 0.0, std::f32::consts::TAU);
+        self.close()
+    }
+
+    pub fn transform(&mut self, transform: &Affine2) -> &mut Self {
+        let t = |p: Vec2| transform.transform_point(p);
+        for command in &mut self.commands {
+            *command = match *command {
+                PathCommand::MoveTo(p) => PathCommand::MoveTo(t(p)),
+                PathCommand::LineTo(p) => PathCommand::LineTo(t(p)),
+                PathCommand::QuadTo(c, p) => PathCommand::QuadTo(t(c), t(p)),
+                PathCommand::CubicTo(c1, c2, p) => PathCommand::CubicTo(t(c1), t(c2), t(p)),
+                PathCommand::Close => PathCommand::Close,
+            };
+        }
+        self
+    }
+
+    // Bounds of all points including control points, so it may be loose
+    pub fn bounds(&self) -> Option<Rect> {
+        let mut points = self.commands.iter().flat_map(|command| match *command {
+            PathCommand::MoveTo(p) | PathCommand::LineTo(p) => vec![p],
+            PathCommand::QuadTo(c, p) => vec![c, p],
+            PathCommand::CubicTo(c1, c2, p) => vec![c1, c2, p],
+            PathCommand::Close => vec![],
+        });
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+        Some(Rect::from_pos_size(min, max - min))
+    }
+
+    // Curves are split until no chord strays more than `tolerance` from the curve
+    pub fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        let tolerance = tolerance.max(1e-3);
+        let mut polylines = Vec::new();
+        let mut current = Polyline::default();
+        let mut start = Vec2::zero();
+        let mut last = Vec2::zero();
+
+        for command in &self.commands {
+            match *command {
+                PathCommand::MoveTo(p) => {
+                    if current.points.len() > 1 {
+                        polylines.push(std::mem::take(&mut current));
+                    }
+                    current.points.clear();
+                    current.points.push(p);
+                    start = p;
+                    last = p;
+                },
+                PathCommand::LineTo(p) => {
+                    if current.points.is_empty() {
+                        current.points.push(last);
+                    }
+                    current.points.push(p);
+                    last = p;
+                },
+                PathCommand::QuadTo(c, p) => {
+                    if current.points.is_empty() {
+                        current.points.push(last);
+                    }
+                    let dd = (last - c * 2.0 + p).length();
+                    let steps = ((0.25 * dd / tolerance).sqrt().ceil() as usize).clamp(1, 256);
+                    for i in 1..=steps {
+                        let t = i as f32 / steps as f32;
+                        let mt = 1.0 - t;
+                        current.points.push(last * (mt * mt) + c * (2.0 * mt * t) + p * (t * t));
+                    }
+                    last = p;
+                },
+                PathCommand::CubicTo(c1, c2, p) => {
+                    if current.points.is_empty() {
+                        current.points.push(last);
+                    }
+                    let dd = (last - c1 * 2.0 + c2).length().max((c1 - c2 * 2.0 + p).length());
+                    let steps = ((0.75 * dd / tolerance).sqrt().ceil() as usize).clamp(1, 256);
+                    for i in 1..=steps {
+                        let t = i as f32 / steps as f32;
+                        let mt = 1.0 - t;
+                        current.points.push(
+                            last * (mt * mt * mt) + c1 * (3.0 * mt * mt * t) + c2 * (3.0 * mt * t * t) + p * (t * t * t),
+                        );
+                    }
+                    last = p;
+                },
+                PathCommand::Close => {
+                    if !current.points.is_empty() {
+                        current.closed = true;
+                        polylines.push(std::mem::take(&mut current));
+                    }
+                    last = start;
+                },
+            }
+        }
+        if !current.points.is_empty() {
+            polylines.push(current);
+        }
+        polylines
+    }
+
+    pub fn fill(&self, fill_rule: FillRule, tolerance: f32) -> Tessellation {
+        tessellate_fill(&self.flatten(tolerance), fill_rule)
+    }
+
+    pub fn stroke(&self, style: &StrokeStyle, tolerance: f32) -> Tessellation {
+        tessellate_stroke(&self.flatten(tolerance), style, tolerance)
+    }
+}
+
+// Fills and/or strokes a path in the local space of the rect it is drawn at.
+// The tessellation is kept in this DrawPath's geometry and only redone when
+// the path or its style changes, so use one DrawPath per distinct path.
+#[derive(Clone, Debug)]
+pub struct DrawPath {
+    pub path: Path,
+    pub fill_color: Option<Color>,
+    // Spans the fill's bounding box; the fill color multiplies it
+    pub fill_gradient: Option<Gradient>,
+    pub fill_rule: FillRule,
+    pub stroke_color: Option<Color>,
+    pub stroke_gradient: Option<Gradient>,
+    pub stroke: StrokeStyle,
+    // Maximum distance in pixels between curves and their flattened segments
+    pub tolerance: f32,
+    pub shader_id: Option<ShaderId>,
+    pub fill_geometry_id: Option<GeometryId>,
+    pub stroke_geometry_id: Option<GeometryId>,
+    tessellated: Option<(Path, FillRule, StrokeStyle, f32)>,
+    bounds: Rect,
+}
+
+impl Default for DrawPath {
+    fn default() -> Self {
+        Self {
+            path: Path::new(),
+            fill_color: None,
+            fill_gradient: None,
+            fill_rule: FillRule::NonZero,
+            stroke_color: None,
+            stroke_gradient: None,
+            stroke: StrokeStyle::default(),
+            tolerance: 0.25,
+            shader_id: None,
+            fill_geometry_id: None,
+            stroke_geometry_id: None,
+            tessellated: None,
+            bounds: Rect::zero(),
+        }
+    }
+}
+
+impl DrawPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_path(mut self, path: Path) -> Self {
+        self.path = path;
+        self
+    }
+
+    pub fn with_fill(mut self, color: Color) -> Self {
+        self.fill_color = Some(color);
+        self
+    }
+
+    pub fn with_fill_gradient(mut self, gradient: Gradient) -> Self {
+        self.fill_color.get_or_insert(Color::white());
+        self.fill_gradient = Some(gradient);
+        self
+    }
+
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    pub fn with_stroke(mut self, color: Color, stroke: StrokeStyle) -> Self {
+        self.stroke_color = Some(color);
+        self.stroke = stroke;
+        self
+    }
+
+    pub fn with_stroke_gradient(mut self, gradient: Gradient, stroke: StrokeStyle) -> Self {
+        self.stroke_color.get_or_insert(Color::white());
+        self.stroke_gradient = Some(gradient);
+        self.stroke = stroke;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Path {
+        self.path.move_to(x, y)
+    }
+
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Path {
+        self.path.line_to(x, y)
+    }
+
+    pub fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) -> &mut Path {
+        self.path.quad_to(cx, cy, x, y)
+    }
+
+    pub fn cubic_to(&mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) -> &mut Path {
+        self.path.cubic_to(c1x, c1y, c2x, c2y, x, y)
+    }
+
+    pub fn arc(&mut self, center_x: f32, center_y: f32, radius: f32, start_angle: f32, end_angle: f32) -> &mut Path {
+        self.path.arc(center_x, center_y, radius, start_angle, end_angle)
+    }
+
+    pub fn close(&mut self) -> &mut Path {
+        self.path.close()
+    }
+
+    pub fn init(&mut self, cx: &mut Cx) {
+        if self.shader_id.is_none() {
+            let shader_id = ShaderBuilder::new()
+                .vertex_shader(PATH_VERTEX_SHADER)
+                .fragment_shader(&format!("{}{}{}", PATH_FRAGMENT_HEADER, GRADIENT_SHADER_LIB, PATH_FRAGMENT_SHADER))
+                .instance("color", VertexFormat::Float4)
+                .instance("offset", VertexFormat::Float2)
+                .instance("bounds", VertexFormat::Float4)
+                .build(cx)
+                .expect("DrawPath shader failed validation");
+            self.shader_id = Some(shader_id);
+        }
+
+        if self.fill_geometry_id.is_none() {
+            let geometry_id = cx.create_geometry();
+            cx.geometries.insert(geometry_id, Geometry::new(geometry_id));
+            self.fill_geometry_id = Some(geometry_id);
+        }
+
+        if self.stroke_geometry_id.is_none() {
+            let geometry_id = cx.create_geometry();
+            cx.geometries.insert(geometry_id, Geometry::new(geometry_id));
+            self.stroke_geometry_id = Some(geometry_id);
+        }
+    }
+
+    fn update_geometry(&mut self, cx: &mut Cx) {
+        let key = (self.path.clone(), self.fill_rule, self.stroke.clone(), self.tolerance);
+        if self.tessellated.as_ref() == Some(&key) {
+            return;
+        }
+
+        let polylines = self.path.flatten(self.tolerance);
+        let mut points = polylines.iter().flat_map(|p| p.points.iter().copied());
+        self.bounds = match points.next() {
+            Some(first) => {
+                let (min, max) = points.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+                Rect::from_pos_size(min, max - min)
+            },
+            None => Rect::zero(),
+        };
+        let fill = tessellate_fill(&polylines, self.fill_rule);
+        let stroke = tessellate_stroke(&polylines, &self.stroke, self.tolerance);
+
+        if let Some(geometry) = self.fill_geometry_id.and_then(|id| cx.geometries.get_mut(&id)) {
+            fill.write_geometry(geometry);
+        }
+        if let Some(geometry) = self.stroke_geometry_id.and_then(|id| cx.geometries.get_mut(&id)) {
+            stroke.write_geometry(geometry);
+        }
+        self.tessellated = Some(key);
+    }
+
+    // The path is drawn with its origin at `rect.pos`; the rect's size is only
+    // used for culling together with the path bounds
+    pub fn draw(&mut self, cx: &mut Cx2d, draw_list_id: DrawListId, rect: &Rect) {
+        if self.shader_id.is_none() || self.fill_geometry_id.is_none() || self.stroke_geometry_id.is_none() {
+            self.init(cx);
+        }
+
+        let bounds = match self.path.bounds() {
+            Some(bounds) => {
+                let stroke_width = if self.stroke_color.is_some() { self.stroke.width } else { 0.0 };
+                let bounds = Rect::from_pos_size(bounds.pos + rect.pos, bounds.size);
+                // Miter joins can reach miter_limit half-widths out
+                bounds.expand(stroke_width * 0.5 * self.stroke.miter_limit.max(1.0))
+            },
+            None => return,
+        };
+        if let Some(clip_rect) = cx.clip_rect() {
+            if !clip_rect.intersects(&cx.transformed_rect(&bounds)) {
+                return;
+            }
+        }
+
+        self.update_geometry(cx);
+
+        let items = [
+            (self.fill_color, self.fill_gradient.as_ref(), self.fill_geometry_id.unwrap()),
+            (self.stroke_color, self.stroke_gradient.as_ref(), self.stroke_geometry_id.unwrap()),
+        ];
+        for (color, gradient, geometry_id) in items {
+            let color = match color {
+                Some(color) if color.a > 0.0 => color,
+                _ => continue,
+            };
+            if cx.geometries.get(&geometry_id).is_none_or(|g| g.indices.is_empty()) {
+                continue;
+            }
+
+            let mut instances = Vec::with_capacity(10);
+            instances.extend_from_slice(&color.to_array());
+            instances.extend_from_slice(&[rect.x(), rect.y()]);
+            instances.extend_from_slice(&[self.bounds.x(), self.bounds.y(), self.bounds.width(), self.bounds.height()]);
+
+            let draw_item = DrawItem {
+                shader_id: self.shader_id.unwrap(),
+                geometry_id,
+                uniforms: Gradient::uniforms(gradient),
+                textures: Vec::new(),
+                instances,
+                instance_count: 1,
+                clip: cx.current_clip(),
+                transform: cx.current_transform(),
+                blend: cx.current_blend(),
+                sub_list: None,
+            };
+
+            cx.append_draw_item(draw_list_id, draw_item);
+        }
+    }
+}
+
+const PATH_VERTEX_SHADER: &str = r#"
+#version 300 es
+precision highp float;
+
+in vec2 position;
+
+// Per instance
+in vec4 color;
+in vec2 offset;
+in vec4 bounds;
+
+uniform mat4 view_transform;
+uniform mat4 model_transform;
+
+out vec2 v_uv;
+out vec2 v_world;
+flat out vec4 v_color;
+
+void main() {
+    v_color = color;
+    v_uv = (position - bounds.xy) / max(bounds.zw, vec2(1e-6));
+    vec4 world = model_transform * vec4(position + offset, 0.0, 1.0);
+    v_world = world.xy;
+    gl_Position = view_transform * world;
+}
+"#;
+
+// Edges are as sharp as the triangles; smooth edges rely on the backend's
+// multisampling
+const PATH_FRAGMENT_HEADER: &str = r#"
+#version 300 es
+precision highp float;
+"#;
+
+const PATH_FRAGMENT_SHADER: &str = r#"
+in vec2 v_uv;
+in vec2 v_world;
+flat in vec4 v_color;
+
+// Supplied by the backend from DrawItem::clip
+uniform vec4 clip_rect;
+uniform float clip_radius;
+
+out vec4 frag_color;
+
+float rounded_box(vec2 p, vec2 b, float r) {
+    return length(max(abs(p) - b + r, 0.0)) - r;
+}
+
+void main() {
+    vec2 clip_half = clip_rect.zw * 0.5;
+    if (clip_radius > 0.0 && rounded_box(v_world - clip_rect.xy - clip_half, clip_half, clip_radius) > 0.0) {
+        discard;
+    }
+    frag_color = gradient_type > 0.5 ? gradient_color(v_uv) * v_color : v_color;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle_starts_its_own_subpath_without_a_zero_length_segment() {
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0).line_to(5.0, 0.0).circle(20.0, 20.0, 10.0);
+
+        assert_eq!(path.commands[2], PathCommand::MoveTo(Vec2::new(30.0, 20.0)));
+        assert!(path.commands[3..].iter().all(|c| matches!(c, PathCommand::CubicTo(..) | PathCommand::Close)));
+
+        let polylines = path.flatten(0.1);
+        assert_eq!(polylines.len(), 2);
+        let circle = &polylines[1];
+        assert!(circle.closed);
+        assert!(circle.points.windows(2).all(|w| w[0].distance(w[1]) > 1e-4));
+    }
+}
@@ -1,17 +1,109 @@
 // No need to import Vec2 directly as it's used through the rect module
 use crate::platform::geometry::{Geometry, GeometryId, VertexFormat};
-use crate::platform::shader::{Shader, ShaderId};
-use crate::platform::draw_list::{DrawItem, DrawUniform};
+use crate::platform::shader::ShaderId;
+use crate::platform::draw_list::DrawItem;
+use crate::platform::ContrastSample;
 use crate::platform::Cx;
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::shader::ShaderBuilder;
 use crate::draw::color::Color;
 use crate::draw::rect::Rect;
+use crate::draw::gradient::{Gradient, GRADIENT_SHADER_LIB};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CornerRadii {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_right: f32,
+    pub bottom_left: f32,
+}
+
+impl CornerRadii {
+    pub fn new(top_left: f32, top_right: f32, bottom_right: f32, bottom_left: f32) -> Self {
+        Self { top_left, top_right, bottom_right, bottom_left }
+    }
+
+    pub fn all(radius: f32) -> Self {
+        Self::new(radius, radius, radius, radius)
+    }
+
+    // Rounds only the top corners, as for tabs
+    pub fn top(radius: f32) -> Self {
+        Self::new(radius, radius, 0.0, 0.0)
+    }
+
+    pub fn bottom(radius: f32) -> Self {
+        Self::new(0.0, 0.0, radius, radius)
+    }
+
+    pub fn left(radius: f32) -> Self {
+        Self::new(radius, 0.0, 0.0, radius)
+    }
+
+    pub fn right(radius: f32) -> Self {
+        Self::new(0.0, radius, radius, 0.0)
+    }
+
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.top_left, self.top_right, self.bottom_right, self.bottom_left]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BorderWidths {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl BorderWidths {
+    pub fn new(top: f32, right: f32, bottom: f32, left: f32) -> Self {
+        Self { top, right, bottom, left }
+    }
+
+    pub fn all(width: f32) -> Self {
+        Self::new(width, width, width, width)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.top <= 0.0 && self.right <= 0.0 && self.bottom <= 0.0 && self.left <= 0.0
+    }
+
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.top, self.right, self.bottom, self.left]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BorderStyle {
+    #[default]
+    Solid,
+    // Lengths in pixels along each side, restarting at every side
+    Dashed { dash: f32, gap: f32 },
+    // Round dots as wide as the border, one border width apart
+    Dotted,
+}
+
+impl BorderStyle {
+    fn to_array(self) -> [f32; 4] {
+        match self {
+            BorderStyle::Solid => [0.0, 0.0, 0.0, 0.0],
+            BorderStyle::Dashed { dash, gap } => [1.0, dash, gap, 0.0],
+            BorderStyle::Dotted => [2.0, 0.0, 0.0, 0.0],
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct DrawQuad {
     pub color: Color,
     pub border_color: Color,
-    pub border_width: f32,
-    pub corner_radius: f32,
+    pub border_widths: BorderWidths,
+    pub border_style: BorderStyle,
+    pub corner_radii: CornerRadii,
+    // Replaces `color` for the fill when set
+    pub gradient: Option<Gradient>,
     pub shader_id: Option<ShaderId>,
     pub geometry_id: Option<GeometryId>,
 }
@@ -21,8 +113,10 @@ impl Default for DrawQuad {
         Self {
             color: Color::white(),
             border_color: Color::transparent(),
-            border_width: 0.0,
-            corner_radius: 0.0,
+            border_widths: BorderWidths::default(),
+            border_style: BorderStyle::Solid,
+            corner_radii: CornerRadii::default(),
+            gradient: None,
             shader_id: None,
             geometry_id: None,
         }
@@ -45,102 +139,151 @@ impl DrawQuad {
     }
 
     pub fn with_border_width(mut self, border_width: f32) -> Self {
-        self.border_width = border_width;
+        self.border_widths = BorderWidths::all(border_width);
         self
     }
 
-    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self {
-        self.corner_radius = corner_radius;
+    pub fn with_border_widths(mut self, border_widths: BorderWidths) -> Self {
+        self.border_widths = border_widths;
         self
     }
 
-    pub fn init(&mut self, cx: &mut Cx) {
-        if self.shader_id.is_none() {
-            let shader_id = cx.create_shader();
-            let mut shader = Shader::new(shader_id);
+    pub fn with_border_style(mut self, border_style: BorderStyle) -> Self {
+        self.border_style = border_style;
+        self
+    }
 
-            shader.set_vertex_shader(QUAD_VERTEX_SHADER);
-            shader.set_fragment_shader(QUAD_FRAGMENT_SHADER);
+    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self {
+        self.corner_radii = CornerRadii::all(corner_radius);
+        self
+    }
 
-            shader.add_uniform("color", crate::platform::shader::ShaderUniformType::Vec4);
-            shader.add_uniform("border_color", crate::platform::shader::ShaderUniformType::Vec4);
-            shader.add_uniform("border_width", crate::platform::shader::ShaderUniformType::Float);
-            shader.add_uniform("corner_radius", crate::platform::shader::ShaderUniformType::Float);
-            shader.add_uniform("size", crate::platform::shader::ShaderUniformType::Vec2);
+    pub fn with_corner_radii(mut self, corner_radii: CornerRadii) -> Self {
+        self.corner_radii = corner_radii;
+        self
+    }
 
-            cx.shaders.insert(shader_id, shader);
-            self.shader_id = Some(shader_id);
-        }
+    pub fn with_gradient(mut self, gradient: Gradient) -> Self {
+        self.gradient = Some(gradient);
+        self
+    }
 
+    pub fn init(&mut self, cx: &mut Cx) {
         if self.geometry_id.is_none() {
-            let geometry_id = cx.create_geometry();
-            let mut geometry = Geometry::new(geometry_id);
-
-            // Define quad vertices
-            let vertices: Vec<f32> = vec![
-                0.0, 0.0,  // Position
-                0.0, 0.0,  // UV
-
-                1.0, 0.0,  // Position
-                1.0, 0.0,  // UV
-
-                1.0, 1.0,  // Position
-                1.0, 1.0,  // UV
-
-                0.0, 1.0,  // Position
-                0.0, 1.0,  // UV
-            ];
-
-            // Convert to bytes
-            let vertices_bytes: Vec<u8> = unsafe {
-                std::slice::from_raw_parts(
-                    vertices.as_ptr() as *const u8,
-                    vertices.len() * std::mem::size_of::<f32>(),
-                ).to_vec()
-            };
-
-            // Define indices
-            let indices: Vec<u16> = vec![
-                0, 1, 2,  // First triangle
-                0, 2, 3,  // Second triangle
-            ];
-
-            geometry.add_vertex_attribute("position", 0, VertexFormat::Float2);
-            geometry.add_vertex_attribute("uv", 8, VertexFormat::Float2);
-
-            geometry.set_vertices(vertices_bytes);
-            geometry.set_indices(indices);
-
-            cx.geometries.insert(geometry_id, geometry);
-            self.geometry_id = Some(geometry_id);
+            self.geometry_id = Some(create_quad_geometry(cx));
+        }
+
+        if self.shader_id.is_none() {
+            let shader_id = ShaderBuilder::new()
+                .vertex_shader(QUAD_VERTEX_SHADER)
+                .fragment_shader(&format!("{}{}{}", QUAD_FRAGMENT_HEADER, GRADIENT_SHADER_LIB, QUAD_FRAGMENT_SHADER))
+                .instance("color", VertexFormat::Float4)
+                .instance("border_color", VertexFormat::Float4)
+                .instance("border_widths", VertexFormat::Float4)
+                .instance("corner_radii", VertexFormat::Float4)
+                .instance("border_style", VertexFormat::Float4)
+                .instance("rect", VertexFormat::Float4)
+                .geometry(self.geometry_id.unwrap())
+                .build(cx)
+                .expect("DrawQuad shader failed validation");
+            self.shader_id = Some(shader_id);
         }
     }
 
-    pub fn draw(&mut self, cx: &mut Cx, draw_list_id: crate::platform::draw_list::DrawListId, rect: &Rect) {
+    pub fn draw(&mut self, cx: &mut Cx2d, draw_list_id: crate::platform::draw_list::DrawListId, rect: &Rect) {
         if self.shader_id.is_none() || self.geometry_id.is_none() {
             self.init(cx);
         }
 
+        // Nothing to draw when the quad is entirely clipped away
+        if let Some(clip_rect) = cx.clip_rect() {
+            if !clip_rect.intersects(&cx.transformed_rect(rect)) {
+                return;
+            }
+        }
+
+        // Gradients have no single color to check text against
+        if cx.debug.contrast_overlay && self.gradient.is_none() && self.color.a > 0.0 {
+            let window_rect = cx.transformed_rect(rect);
+            cx.debug.record_contrast_sample(draw_list_id, ContrastSample::Background {
+                rect: (window_rect.x(), window_rect.y(), window_rect.width(), window_rect.height()),
+                color: self.color.to_array(),
+            });
+        }
+
         let draw_item = DrawItem {
             shader_id: self.shader_id.unwrap(),
             geometry_id: self.geometry_id.unwrap(),
-            uniforms: vec![
-                DrawUniform::Vec4(self.color.to_array()),
-                DrawUniform::Vec4(self.border_color.to_array()),
-                DrawUniform::Float(self.border_width),
-                DrawUniform::Float(self.corner_radius),
-                DrawUniform::Vec2([rect.width(), rect.height()]),
-            ],
+            // Quads only batch with quads sharing the same gradient
+            uniforms: Gradient::uniforms(self.gradient.as_ref()),
             textures: Vec::new(),
+            instances: self.instance_data(rect),
             instance_count: 1,
+            clip: cx.current_clip(),
+            transform: cx.current_transform(),
+            blend: cx.current_blend(),
+            sub_list: None,
         };
 
-        if let Some(draw_list) = cx.draw_lists.get_mut(&draw_list_id) {
-            draw_list.add_draw_item(draw_item);
-        }
+        cx.append_draw_item(draw_list_id, draw_item);
+    }
+
+    // Matches the instance attribute layout declared in `init`
+    fn instance_data(&self, rect: &Rect) -> Vec<f32> {
+        let mut data = Vec::with_capacity(24);
+        data.extend_from_slice(&self.color.to_array());
+        data.extend_from_slice(&self.border_color.to_array());
+        data.extend_from_slice(&self.border_widths.to_array());
+        data.extend_from_slice(&self.corner_radii.to_array());
+        data.extend_from_slice(&self.border_style.to_array());
+        data.extend_from_slice(&[rect.x(), rect.y(), rect.width(), rect.height()]);
+        data
     }
 }
 
+// Unit quad with position and uv in 0..1, registered once and shared by the
+// quad-based drawables
+pub(crate) fn create_quad_geometry(cx: &mut Cx) -> GeometryId {
+    let mut geometry = Geometry::new(GeometryId(0));
+
+    // Define quad vertices
+    let vertices: Vec<f32> = vec![
+        0.0, 0.0,  // Position
+        0.0, 0.0,  // UV
+
+        1.0, 0.0,  // Position
+        1.0, 0.0,  // UV
+
+        1.0, 1.0,  // Position
+        1.0, 1.0,  // UV
+
+        0.0, 1.0,  // Position
+        0.0, 1.0,  // UV
+    ];
+
+    // Convert to bytes
+    let vertices_bytes: Vec<u8> = unsafe {
+        std::slice::from_raw_parts(
+            vertices.as_ptr() as *const u8,
+            vertices.len() * std::mem::size_of::<f32>(),
+        ).to_vec()
+    };
+
+    // Define indices
+    let indices: Vec<u32> = vec![
+        0, 1, 2,  // First triangle
+        0, 2, 3,  // Second triangle
+    ];
+
+    geometry.add_vertex_attribute("position", 0, VertexFormat::Float2);
+    geometry.add_vertex_attribute("uv", 8, VertexFormat::Float2);
+
+    geometry.set_vertices(vertices_bytes);
+    geometry.set_indices(indices);
+
+    cx.register_geometry(geometry)
+}
+
 const QUAD_VERTEX_SHADER: &str = r#"
 #version 300 es
 precision highp float;
@@ -148,31 +291,62 @@ precision highp float;
 in vec2 position;
 in vec2 uv;
 
-uniform vec2 size;
+// Per instance
+in vec4 color;
+in vec4 border_color;
+in vec4 border_widths; // top, right, bottom, left
+in vec4 corner_radii; // top-left, top-right, bottom-right, bottom-left
+in vec4 border_style; // style, dash, gap
+in vec4 rect;
+
 uniform mat4 view_transform;
+uniform mat4 model_transform;
 
 out vec2 v_uv;
 out vec2 v_size;
+out vec2 v_world;
+flat out vec4 v_color;
+flat out vec4 v_border_color;
+flat out vec4 v_border_widths;
+flat out vec4 v_corner_radii;
+flat out vec4 v_border_style;
 
 void main() {
     v_uv = uv;
-    v_size = size;
-    vec2 pos = position * size;
-    gl_Position = view_transform * vec4(pos, 0.0, 1.0);
+    v_size = rect.zw;
+    v_color = color;
+    v_border_color = border_color;
+    v_border_widths = border_widths;
+    v_corner_radii = corner_radii;
+    v_border_style = border_style;
+    vec2 pos = rect.xy + position * rect.zw;
+    vec4 world = model_transform * vec4(pos, 0.0, 1.0);
+    v_world = world.xy;
+    gl_Position = view_transform * world;
 }
 "#;
 
-const QUAD_FRAGMENT_SHADER: &str = r#"
+const QUAD_FRAGMENT_HEADER: &str = r#"
 #version 300 es
 precision highp float;
+"#;
 
+// Coverage comes from the SDF distance scaled by its screen-space derivative,
+// so edges stay one pixel soft under any transform. The fill spans the whole
+// box and the border is composited over it, so dash gaps show the fill.
+const QUAD_FRAGMENT_SHADER: &str = r#"
 in vec2 v_uv;
 in vec2 v_size;
+in vec2 v_world;
+flat in vec4 v_color;
+flat in vec4 v_border_color;
+flat in vec4 v_border_widths;
+flat in vec4 v_corner_radii;
+flat in vec4 v_border_style;
 
-uniform vec4 color;
-uniform vec4 border_color;
-uniform float border_width;
-uniform float corner_radius;
+// Supplied by the backend from DrawItem::clip
+uniform vec4 clip_rect;
+uniform float clip_radius;
 
 out vec4 frag_color;
 
@@ -180,23 +354,113 @@ float rounded_box(vec2 p, vec2 b, float r) {
     return length(max(abs(p) - b + r, 0.0)) - r;
 }
 
+// Exact distance to a box with radii (top-left, top-right, bottom-right, bottom-left), y down
+float rounded_box4(vec2 p, vec2 b, vec4 r) {
+    float corner = p.x > 0.0 ? (p.y > 0.0 ? r.z : r.y) : (p.y > 0.0 ? r.w : r.x);
+    corner = min(corner, min(b.x, b.y));
+    vec2 q = abs(p) - b + corner;
+    return min(max(q.x, q.y), 0.0) + length(max(q, 0.0)) - corner;
+}
+
+// 1 on dashes and dots, 0 in the gaps
+float border_pattern(vec2 pos, float aa) {
+    if (v_border_style.x < 0.5) {
+        return 1.0;
+    }
+
+    // Nearest side: distance across it, position along it and its width
+    float top = pos.y;
+    float right = v_size.x - pos.x;
+    float bottom = v_size.y - pos.y;
+    float left = pos.x;
+    float across = top;
+    float along = pos.x;
+    float width = v_border_widths.x;
+    if (right < across) { across = right; along = pos.y; width = v_border_widths.y; }
+    if (bottom < across) { across = bottom; along = pos.x; width = v_border_widths.z; }
+    if (left < across) { across = left; along = pos.y; width = v_border_widths.w; }
+
+    if (v_border_style.x < 1.5) {
+        float period = max(v_border_style.y + v_border_style.z, 1.0);
+        float d = abs(mod(along, period) - v_border_style.y * 0.5) - v_border_style.y * 0.5;
+        return clamp(0.5 - d / aa, 0.0, 1.0);
+    }
+
+    float period = max(width * 2.0, 1.0);
+    vec2 dot_center = vec2(period * 0.5, width * 0.5);
+    float d = length(vec2(mod(along, period), across) - dot_center) - width * 0.5;
+    return clamp(0.5 - d / aa, 0.0, 1.0);
+}
+
 void main() {
+    vec2 clip_half = clip_rect.zw * 0.5;
+    if (clip_radius > 0.0 && rounded_box(v_world - clip_rect.xy - clip_half, clip_half, clip_radius) > 0.0) {
+        discard;
+    }
+
     vec2 pos = v_uv * v_size;
     vec2 center = v_size * 0.5;
-    vec2 p = pos - center;
 
-    float box = rounded_box(p, center - border_width, corner_radius);
-    float border = rounded_box(p, center, corner_radius);
+    float outer = rounded_box4(pos - center, center, v_corner_radii);
+    float aa = max(fwidth(outer), 1e-4);
+    float outer_coverage = clamp(0.5 - outer / aa, 0.0, 1.0);
+    if (outer_coverage <= 0.0) {
+        discard;
+    }
 
-    if (border_width > 0.0 && border <= 0.0 && box > 0.0) {
-        frag_color = border_color;
-    } else if (box <= 0.0) {
-        frag_color = color;
-    } else {
+    vec4 fill = gradient_type > 0.5 ? gradient_color(v_uv) : v_color;
+    vec4 result = vec4(fill.rgb * fill.a, fill.a) * outer_coverage;
+
+    vec4 w = v_border_widths;
+    if (max(max(w.x, w.y), max(w.z, w.w)) > 0.0) {
+        // Inner edge: the box inset by each side's width, radii shrunk to match
+        vec2 inner_min = vec2(w.w, w.x);
+        vec2 inner_max = v_size - vec2(w.y, w.z);
+        vec2 inner_half = max((inner_max - inner_min) * 0.5, vec2(0.0));
+        vec4 inner_radii = max(v_corner_radii - vec4(max(w.w, w.x), max(w.y, w.x), max(w.y, w.z), max(w.w, w.z)), 0.0);
+        float inner = rounded_box4(pos - (inner_min + inner_max) * 0.5, inner_half, inner_radii);
+        float inner_coverage = clamp(0.5 - inner / aa, 0.0, 1.0);
+
+        float border = max(outer_coverage - inner_coverage, 0.0) * border_pattern(pos, aa);
+        vec4 border_color = vec4(v_border_color.rgb * v_border_color.a, v_border_color.a) * border;
+        result = border_color + result * (1.0 - border_color.a);
+    }
+
+    if (result.a <= 0.0) {
         discard;
     }
+    frag_color = vec4(result.rgb / result.a, result.a);
 }
 "#;
 
-
-
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::math::{Affine2, Vec2};
+    use crate::draw::layout::Layout;
+
+    // Checks the instance data the shader positions quads from. Where the
+    // pixels land isn't checked: there is no software renderer to draw with.
+    #[test]
+    fn quad_instance_carries_turtle_rect_and_transform() {
+        let mut cx = Cx::new();
+        let draw_list_id = cx.create_draw_list();
+        let mut cx2d = Cx2d::new(&mut cx);
+        let mut quad = DrawQuad::new();
+
+        cx2d.push_transform(Affine2::translation(30.0, 40.0));
+        cx2d.begin_sized_turtle(Vec2::new(200.0, 100.0), Layout::vertical().with_padding(Vec2::new(10.0, 5.0)));
+        let rect = cx2d.add_turtle_item(Vec2::new(50.0, 20.0)).unwrap();
+        quad.draw(&mut cx2d, draw_list_id, &rect);
+        cx2d.end_turtle();
+        cx2d.pop_transform();
+        drop(cx2d);
+
+        assert_eq!(rect, Rect::new(10.0, 5.0, 50.0, 20.0));
+        let item = &cx.draw_lists[&draw_list_id].draw_items[0];
+        // The rect is the last attribute in `instance_data`
+        let instance = item.instance(0);
+        assert_eq!(&instance[instance.len() - 4..], &[10.0, 5.0, 50.0, 20.0]);
+        assert_eq!(item.transform, Affine2::translation(30.0, 40.0));
+    }
+}
@@ -49,6 +49,20 @@ impl Rect {
         self.pos.y + self.size.y
     }
     
+    pub fn center(&self) -> Vec2 {
+        self.pos + self.size * 0.5
+    }
+    
+    // Grows the rect by `amount` on every side; negative values shrink it
+    pub fn expand(&self, amount: f32) -> Rect {
+        Rect::new(
+            self.x() - amount,
+            self.y() - amount,
+            (self.width() + amount * 2.0).max(0.0),
+            (self.height() + amount * 2.0).max(0.0),
+        )
+    }
+
     pub fn contains(&self, point: Vec2) -> bool {
         point.x >= self.pos.x && point.x <= self.right() &&
         point.y >= self.pos.y && point.y <= self.bottom()
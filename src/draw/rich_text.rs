@@ -0,0 +1,586 @@
+use crate::platform::math::Vec2;
+use crate::platform::texture::Texture;
+use crate::platform::draw_list::DrawListId;
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::color::Color;
+use crate::draw::rect::Rect;
+use crate::draw::quad::DrawQuad;
+use crate::draw::text::{DrawText, TextStyle, TextAlign, GlyphInfo};
+use crate::draw::font_collection::{FontCollection, FontWeight, FontStyle};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, Debug)]
+pub struct SpanStyle {
+    pub font_name: String,
+    pub font_size: f32,
+    pub font_weight: FontWeight,
+    pub font_style: FontStyle,
+    pub color: Color,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub background: Option<Color>,
+}
+
+impl Default for SpanStyle {
+    fn default() -> Self {
+        Self::from_text_style(&TextStyle::default())
+    }
+}
+
+impl SpanStyle {
+    pub fn from_text_style(style: &TextStyle) -> Self {
+        Self {
+            font_name: style.font_name.clone(),
+            font_size: style.font_size,
+            font_weight: style.font_weight,
+            font_style: style.font_style,
+            color: style.color,
+            underline: false,
+            strikethrough: false,
+            background: None,
+        }
+    }
+
+    pub fn with_font_name(mut self, font_name: &str) -> Self {
+        self.font_name = font_name.to_string();
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn with_font_weight(mut self, font_weight: FontWeight) -> Self {
+        self.font_weight = font_weight;
+        self
+    }
+
+    pub fn with_font_style(mut self, font_style: FontStyle) -> Self {
+        self.font_style = font_style;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_underline(mut self, underline: bool) -> Self {
+        self.underline = underline;
+        self
+    }
+
+    pub fn with_strikethrough(mut self, strikethrough: bool) -> Self {
+        self.strikethrough = strikethrough;
+        self
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    fn text_style(&self) -> TextStyle {
+        TextStyle {
+            font_size: self.font_size,
+            font_name: self.font_name.clone(),
+            font_weight: self.font_weight,
+            font_style: self.font_style,
+            color: self.color,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TextSpan {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+impl TextSpan {
+    pub fn new(text: &str, style: SpanStyle) -> Self {
+        Self {
+            text: text.to_string(),
+            style,
+        }
+    }
+}
+
+// A glyph placed on its line. `pos` is the pen position on the baseline.
+#[derive(Clone, Debug)]
+pub struct RichTextGlyph {
+    pub span_index: usize,
+    pub byte_offset: usize,
+    // Length of the grapheme in the span's text
+    pub byte_len: usize,
+    pub code_point: char,
+    pub pos: Vec2,
+    pub advance: f32,
+    pub glyph: Option<GlyphInfo>,
+    pub texture: Option<Texture>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RichTextLine {
+    pub top: f32,
+    pub baseline: f32,
+    pub height: f32,
+    pub width: f32,
+    pub glyph_start: usize,
+    pub glyph_end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RichTextHit {
+    pub span_index: usize,
+    pub byte_offset: usize,
+    pub line_index: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct RichTextLayout {
+    pub glyphs: Vec<RichTextGlyph>,
+    pub lines: Vec<RichTextLine>,
+    pub size: Vec2,
+}
+
+impl RichTextLayout {
+    // `point` is relative to the top-left of the laid out paragraph
+    pub fn hit_test(&self, point: Vec2) -> Option<RichTextHit> {
+        let line_index = self.lines
+            .iter()
+            .position(|line| point.y >= line.top && point.y < line.top + line.height)?;
+        let line = &self.lines[line_index];
+
+        self.glyphs[line.glyph_start..line.glyph_end]
+            .iter()
+            .find(|g| point.x >= g.pos.x && point.x < g.pos.x + g.advance)
+            .map(|g| RichTextHit {
+                span_index: g.span_index,
+                byte_offset: g.byte_offset,
+                line_index,
+            })
+    }
+
+    // A zero-width rect the height of the line, where a caret before the
+    // grapheme at `byte_offset` in the span goes. Offsets inside a grapheme
+    // snap to its start; the end of the span puts the caret after it.
+    pub fn caret_rect(&self, span_index: usize, byte_offset: usize) -> Option<Rect> {
+        let (index, glyph) = self.glyphs
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, g)| g.span_index == span_index && g.byte_offset <= byte_offset)?;
+        let line = self.lines.iter().find(|line| index >= line.glyph_start && index < line.glyph_end)?;
+        let x = if byte_offset >= glyph.byte_offset + glyph.byte_len {
+            glyph.pos.x + glyph.advance
+        } else {
+            glyph.pos.x
+        };
+        Some(Rect::new(x, line.top, 0.0, line.height))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RichText {
+    pub spans: Vec<TextSpan>,
+    pub align: TextAlign,
+    pub line_height: f32,
+    pub wrap: bool,
+    pub draw_text: DrawText,
+    pub draw_quad: DrawQuad,
+    pub layout: Option<RichTextLayout>,
+    pub origin: Vec2,
+}
+
+impl Default for RichText {
+    fn default() -> Self {
+        Self {
+            spans: Vec::new(),
+            align: TextAlign::Left,
+            line_height: 1.2,
+            wrap: true,
+            draw_text: DrawText::new(),
+            draw_quad: DrawQuad::new(),
+            layout: None,
+            origin: Vec2::zero(),
+        }
+    }
+}
+
+impl RichText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_span(mut self, text: &str, style: SpanStyle) -> Self {
+        self.spans.push(TextSpan::new(text, style));
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_line_height(mut self, line_height: f32) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn push_span(&mut self, text: &str, style: SpanStyle) {
+        self.spans.push(TextSpan::new(text, style));
+    }
+
+    pub fn clear(&mut self) {
+        self.spans.clear();
+        self.layout = None;
+    }
+
+    // Lays the spans out as one paragraph. `width` is used for wrapping (when
+    // enabled) and alignment; without it lines align to the widest line.
+    pub fn compute_layout(&self, fonts: &FontCollection, width: Option<f32>) -> RichTextLayout {
+        let wrap_width = if self.wrap { width } else { None };
+
+        let mut glyphs: Vec<RichTextGlyph> = Vec::new();
+        let mut line_breaks: Vec<usize> = Vec::new();
+        let mut line_start = 0;
+        let mut last_break: Option<usize> = None;
+        let mut x: f32 = 0.0;
+
+        for (span_index, span) in self.spans.iter().enumerate() {
+            let text_style = span.style.text_style();
+            let scale_factor = span.style.font_size / 32.0; // Assuming font metrics are based on 32px
+
+            for (byte_offset, grapheme) in span.text.grapheme_indices(true) {
+                if grapheme == "\n" || grapheme == "\r\n" {
+                    line_breaks.push(glyphs.len());
+                    line_start = glyphs.len();
+                    last_break = None;
+                    x = 0.0;
+                    continue;
+                }
+
+                let c = grapheme.chars().next().unwrap_or(' ');
+                let font = fonts.font_for_char(&text_style, c);
+                let glyph = font.and_then(|font| font.glyph_info(c));
+                let advance = match &glyph {
+                    Some(glyph) => glyph.advance * scale_factor,
+                    None => span.style.font_size * 0.5,
+                };
+                let is_space = c.is_whitespace();
+
+                if let Some(wrap_width) = wrap_width {
+                    if !is_space && x + advance > wrap_width && glyphs.len() > line_start {
+                        // Break after the last space on the line, or mid-word if there is none
+                        let break_at = last_break.filter(|b| *b > line_start).unwrap_or(glyphs.len());
+                        line_breaks.push(break_at);
+                        line_start = break_at;
+                        last_break = None;
+                        x = 0.0;
+                        for glyph in &mut glyphs[break_at..] {
+                            glyph.pos.x = x;
+                            x += glyph.advance;
+                        }
+                    }
+                }
+
+                glyphs.push(RichTextGlyph {
+                    span_index,
+                    byte_offset,
+                    byte_len: grapheme.len(),
+                    code_point: c,
+                    pos: Vec2::new(x, 0.0),
+                    advance,
+                    glyph,
+                    texture: font.and_then(|font| font.texture.clone()),
+                });
+                x += advance;
+
+                if is_space {
+                    last_break = Some(glyphs.len());
+                }
+            }
+        }
+        line_breaks.push(glyphs.len());
+
+        // Vertical metrics: every line shares one baseline across its spans
+        let mut lines = Vec::with_capacity(line_breaks.len());
+        let mut top: f32 = 0.0;
+        let mut start = 0;
+        let mut max_width: f32 = 0.0;
+        for end in line_breaks {
+            let mut ascent: f32 = 0.0;
+            let mut descent: f32 = 0.0;
+            let mut line_box: f32 = 0.0;
+
+            let spans_on_line: Vec<usize> = if start < end {
+                let mut spans: Vec<usize> = glyphs[start..end].iter().map(|g| g.span_index).collect();
+                spans.dedup();
+                spans
+            } else {
+                // Empty line: size it after the span that produced it
+                let span_index = glyphs.get(start).map(|g| g.span_index)
+                    .or_else(|| glyphs.last().map(|g| g.span_index))
+                    .unwrap_or(0);
+                if self.spans.is_empty() { Vec::new() } else { vec![span_index] }
+            };
+
+            for span_index in spans_on_line {
+                let style = &self.spans[span_index].style;
+                let text_style = style.text_style();
+                let (a, d) = match fonts.primary_font(&text_style) {
+                    Some(font) => (font.ascender(style.font_size), font.descender(style.font_size)),
+                    None => (style.font_size * 0.8, style.font_size * 0.2),
+                };
+                ascent = ascent.max(a);
+                descent = descent.max(d);
+                line_box = line_box.max(style.font_size * self.line_height);
+            }
+
+            let height = line_box.max(ascent + descent);
+            let baseline = top + (height - (ascent + descent)) * 0.5 + ascent;
+
+            // Trailing spaces don't count towards the line width
+            let width = glyphs[start..end]
+                .iter()
+                .rev()
+                .find(|g| !g.code_point.is_whitespace())
+                .map(|g| g.pos.x + g.advance)
+                .unwrap_or(0.0);
+            max_width = max_width.max(width);
+
+            lines.push(RichTextLine {
+                top,
+                baseline,
+                height,
+                width,
+                glyph_start: start,
+                glyph_end: end,
+            });
+
+            top += height;
+            start = end;
+        }
+
+        let align_width = width.unwrap_or(max_width);
+        for line in &lines {
+            let offset = match self.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => (align_width - line.width) * 0.5,
+                TextAlign::Right => align_width - line.width,
+            };
+            for glyph in &mut glyphs[line.glyph_start..line.glyph_end] {
+                glyph.pos.x += offset;
+                glyph.pos.y = line.baseline;
+            }
+        }
+
+        RichTextLayout {
+            glyphs,
+            lines,
+            size: Vec2::new(max_width, top),
+        }
+    }
+
+    pub fn measure(&self, fonts: &FontCollection, max_width: Option<f32>) -> Vec2 {
+        self.compute_layout(fonts, max_width).size
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx2d, draw_list_id: DrawListId, rect: &Rect, fonts: &mut FontCollection) {
+        for span in &self.spans {
+            fonts.prepare_glyphs(cx, &span.style.text_style(), &span.text);
+        }
+        let fonts = &*fonts;
+        let layout = self.compute_layout(fonts, Some(rect.width()));
+        let origin = rect.pos;
+
+        // Backgrounds first, one quad per run of a span on a line
+        for (line, run) in self.runs(&layout) {
+            if let Some(background) = self.spans[run.span_index].style.background {
+                self.draw_quad.color = background;
+                let run_rect = Rect::new(origin.x + run.start, origin.y + line.top, run.end - run.start, line.height);
+                self.draw_quad.draw(cx, draw_list_id, &run_rect);
+            }
+        }
+
+        for glyph in &layout.glyphs {
+            if let (Some(info), Some(texture)) = (&glyph.glyph, &glyph.texture) {
+                if info.uv_rect.width() <= 0.0 {
+                    continue;
+                }
+                let style = &self.spans[glyph.span_index].style;
+                let scale_factor = style.font_size / 32.0;
+                let glyph_pos = Vec2::new(
+                    origin.x + glyph.pos.x + info.bearing.x * scale_factor,
+                    origin.y + glyph.pos.y - info.bearing.y * scale_factor,
+                );
+                let glyph_size = Vec2::new(info.size.x * scale_factor, info.size.y * scale_factor);
+                self.draw_text.draw_glyph(cx, draw_list_id, glyph_pos, glyph_size, &info.uv_rect, style.color, texture);
+            }
+        }
+
+        // Decorations go on top of the glyphs
+        for (line, run) in self.runs(&layout) {
+            let style = &self.spans[run.span_index].style;
+            let thickness = (style.font_size / 14.0).max(1.0);
+            let color = style.color;
+            let mut lines_y = Vec::new();
+            if style.underline {
+                lines_y.push(line.baseline + thickness * 1.5);
+            }
+            if style.strikethrough {
+                lines_y.push(line.baseline - style.font_size * 0.3);
+            }
+            for y in lines_y {
+                self.draw_quad.color = color;
+                let decoration = Rect::new(origin.x + run.start, origin.y + y, run.end - run.start, thickness);
+                self.draw_quad.draw(cx, draw_list_id, &decoration);
+            }
+        }
+
+        self.layout = Some(layout);
+        self.origin = origin;
+    }
+
+    // Hit-tests against the layout of the last `draw`, in the same space as its rect
+    pub fn hit_test(&self, point: Vec2) -> Option<RichTextHit> {
+        let layout = self.layout.as_ref()?;
+        layout.hit_test(Vec2::new(point.x - self.origin.x, point.y - self.origin.y))
+    }
+
+    // `RichTextLayout::caret_rect` for the layout of the last `draw`
+    pub fn caret_rect(&self, span_index: usize, byte_offset: usize) -> Option<Rect> {
+        let rect = self.layout.as_ref()?.caret_rect(span_index, byte_offset)?;
+        Some(Rect::new(rect.x() + self.origin.x, rect.y() + self.origin.y, 0.0, rect.height()))
+    }
+
+    fn runs<'l>(&self, layout: &'l RichTextLayout) -> Vec<(&'l RichTextLine, SpanRun)> {
+        let mut runs = Vec::new();
+        for line in &layout.lines {
+            let mut current: Option<SpanRun> = None;
+            for glyph in &layout.glyphs[line.glyph_start..line.glyph_end] {
+                match &mut current {
+                    Some(run) if run.span_index == glyph.span_index => {
+                        run.end = glyph.pos.x + glyph.advance;
+                    },
+                    _ => {
+                        if let Some(run) = current.take() {
+                            runs.push((line, run));
+                        }
+                        current = Some(SpanRun {
+                            span_index: glyph.span_index,
+                            start: glyph.pos.x,
+                            end: glyph.pos.x + glyph.advance,
+                        });
+                    },
+                }
+            }
+            if let Some(run) = current {
+                runs.push((line, run));
+            }
+        }
+        runs
+    }
+}
+
+struct SpanRun {
+    span_index: usize,
+    start: f32,
+    end: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Without fonts every grapheme advances half the font size and lines
+    // use the default 0.8/0.2 ascent and descent
+    fn layout(text: &RichText, width: Option<f32>) -> RichTextLayout {
+        text.compute_layout(&FontCollection::new(), width)
+    }
+
+    fn size(font_size: f32) -> SpanStyle {
+        SpanStyle::default().with_font_size(font_size)
+    }
+
+    fn hit(layout: &RichTextLayout, x: f32, y: f32) -> Option<(usize, usize, usize)> {
+        layout.hit_test(Vec2::new(x, y)).map(|hit| (hit.span_index, hit.byte_offset, hit.line_index))
+    }
+
+    fn caret(layout: &RichTextLayout, span_index: usize, byte_offset: usize) -> Option<(f32, f32, f32)> {
+        layout.caret_rect(span_index, byte_offset).map(|rect| (rect.x(), rect.y(), rect.height()))
+    }
+
+    #[test]
+    fn hits_and_carets_cross_spans_of_different_sizes() {
+        // a, b and the space are 10 wide, c and d 20; the line is 40 * 1.2 tall
+        let text = RichText::new().with_span("ab ", size(20.0)).with_span("cd", size(40.0));
+        let layout = layout(&text, None);
+        assert_eq!(layout.size, Vec2::new(70.0, 48.0));
+
+        assert_eq!(hit(&layout, 15.0, 47.0), Some((0, 1, 0)));
+        assert_eq!(hit(&layout, 35.0, 10.0), Some((1, 0, 0)));
+        assert_eq!(hit(&layout, 69.0, 10.0), Some((1, 1, 0)));
+        assert_eq!(hit(&layout, 75.0, 10.0), None);
+        assert_eq!(hit(&layout, 5.0, 48.0), None);
+
+        assert_eq!(caret(&layout, 0, 0), Some((0.0, 0.0, 48.0)));
+        assert_eq!(caret(&layout, 1, 1), Some((50.0, 0.0, 48.0)));
+        // The end of a span is after its last grapheme
+        assert_eq!(caret(&layout, 1, 2), Some((70.0, 0.0, 48.0)));
+        assert_eq!(caret(&layout, 2, 0), None);
+    }
+
+    #[test]
+    fn hits_and_carets_follow_wrapped_lines() {
+        let text = RichText::new().with_span("ab ", size(20.0)).with_span("cd", size(40.0));
+        let layout = layout(&text, Some(40.0));
+        // "cd" moves down whole, after the space
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!((layout.lines[1].top, layout.lines[1].height), (24.0, 48.0));
+
+        assert_eq!(hit(&layout, 5.0, 10.0), Some((0, 0, 0)));
+        assert_eq!(hit(&layout, 25.0, 30.0), Some((1, 1, 1)));
+        assert_eq!(hit(&layout, 45.0, 30.0), None);
+
+        assert_eq!(caret(&layout, 0, 3), Some((30.0, 0.0, 24.0)));
+        assert_eq!(caret(&layout, 1, 0), Some((0.0, 24.0, 48.0)));
+        assert_eq!(caret(&layout, 1, 2), Some((40.0, 24.0, 48.0)));
+    }
+
+    #[test]
+    fn explicit_breaks_start_lines_within_a_span() {
+        let text = RichText::new().with_span("ab\ncd", size(20.0));
+        let layout = layout(&text, None);
+        assert_eq!(hit(&layout, 5.0, 30.0), Some((0, 3, 1)));
+        // Before the break is the end of the first line
+        assert_eq!(caret(&layout, 0, 2), Some((20.0, 0.0, 24.0)));
+        assert_eq!(caret(&layout, 0, 3), Some((0.0, 24.0, 24.0)));
+    }
+
+    #[test]
+    fn graphemes_hit_and_place_carets_as_one() {
+        // e with a combining acute, a flag, then x: three graphemes, 10 wide each
+        let text = RichText::new().with_span("e\u{301}\u{1F1EB}\u{1F1F7}x", size(20.0));
+        let layout = layout(&text, None);
+        let offsets: Vec<usize> = layout.glyphs.iter().map(|g| g.byte_offset).collect();
+        assert_eq!(offsets, [0, 3, 11]);
+
+        assert_eq!(hit(&layout, 5.0, 5.0), Some((0, 0, 0)));
+        assert_eq!(hit(&layout, 15.0, 5.0), Some((0, 3, 0)));
+        assert_eq!(hit(&layout, 25.0, 5.0), Some((0, 11, 0)));
+
+        // Inside the accent and the flag snap back to their starts
+        assert_eq!(caret(&layout, 0, 1), Some((0.0, 0.0, 24.0)));
+        assert_eq!(caret(&layout, 0, 7), Some((10.0, 0.0, 24.0)));
+        assert_eq!(caret(&layout, 0, 12), Some((30.0, 0.0, 24.0)));
+    }
+}
@@ -1,10 +1,20 @@
-use crate::platform::shader::{Shader, ShaderId};
+use crate::platform::shader::{Shader, ShaderId, ShaderUniformType};
+use crate::platform::shader_reflect::{ShaderError, ShaderReflection, ShaderStage, FRAMEWORK_UNIFORMS};
+use crate::platform::geometry::{GeometryId, VertexFormat};
+use crate::platform::draw_list::{DrawItem, DrawListId, DrawUniform, UniformBinding};
+use crate::platform::texture::Texture;
 use crate::platform::Cx;
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::color::Color;
+use crate::draw::rect::Rect;
+use crate::draw::quad::create_quad_geometry;
 
 pub struct ShaderBuilder {
     vertex_shader: String,
     fragment_shader: String,
-    uniforms: Vec<(String, crate::platform::shader::ShaderUniformType)>,
+    uniforms: Vec<(String, ShaderUniformType)>,
+    instance_attributes: Vec<(String, VertexFormat)>,
+    geometry_id: Option<GeometryId>,
 }
 
 impl ShaderBuilder {
@@ -13,6 +23,8 @@ impl ShaderBuilder {
             vertex_shader: String::new(),
             fragment_shader: String::new(),
             uniforms: Vec::new(),
+            instance_attributes: Vec::new(),
+            geometry_id: None,
         }
     }
 
@@ -26,26 +38,440 @@ impl ShaderBuilder {
         self
     }
 
-    pub fn uniform(mut self, name: &str, uniform_type: crate::platform::shader::ShaderUniformType) -> Self {
+    // Uniforms are reflected from the sources; declaring one here only checks
+    // that the source agrees
+    pub fn uniform(mut self, name: &str, uniform_type: ShaderUniformType) -> Self {
         self.uniforms.push((name.to_string(), uniform_type));
         self
     }
 
-    pub fn build(self, cx: &mut Cx) -> ShaderId {
-        let shader_id = cx.create_shader();
-        let mut shader = Shader::new(shader_id);
+    // Instance attributes are packed in the order they are added here
+    pub fn instance(mut self, name: &str, format: VertexFormat) -> Self {
+        self.instance_attributes.push((name.to_string(), format));
+        self
+    }
+
+    // Checks the vertex inputs against this geometry's attributes at build time
+    pub fn geometry(mut self, geometry_id: GeometryId) -> Self {
+        self.geometry_id = Some(geometry_id);
+        self
+    }
+
+    pub fn build(self, cx: &mut Cx) -> Result<ShaderId, ShaderError> {
+        let vertex = ShaderReflection::parse(&self.vertex_shader, ShaderStage::Vertex)?;
+        let fragment = ShaderReflection::parse(&self.fragment_shader, ShaderStage::Fragment)?;
+        ShaderReflection::link(&vertex, &fragment)?;
+        let uniforms = ShaderReflection::shader_uniforms(&vertex, &fragment)?;
+
+        for (name, uniform_type) in &self.uniforms {
+            match uniforms.iter().find(|u| &u.name == name) {
+                Some(uniform) if uniform.uniform_type != *uniform_type => {
+                    let (stage, variable) = match vertex.uniform(name) {
+                        Some(variable) => (ShaderStage::Vertex, variable),
+                        None => (ShaderStage::Fragment, fragment.uniform(name).unwrap()),
+                    };
+                    return Err(ShaderError::at(
+                        stage,
+                        variable.line,
+                        format!("uniform `{}` is {:?} in source but was declared {:?}", name, uniform.uniform_type, uniform_type),
+                    ));
+                },
+                Some(_) => {},
+                None if FRAMEWORK_UNIFORMS.contains(&name.as_str()) => {
+                    return Err(ShaderError::new(format!("uniform `{}` is supplied by the framework", name)));
+                },
+                None => return Err(ShaderError::new(format!("uniform `{}` is not declared in either stage", name))),
+            }
+        }
+
+        for (name, format) in &self.instance_attributes {
+            match vertex.input(name) {
+                Some(input) if !input.accepts_format(*format) => {
+                    return Err(ShaderError::at(
+                        ShaderStage::Vertex,
+                        input.line,
+                        format!("`{}` is {} but its instance attribute is {:?}", name, input.glsl_type, format),
+                    ));
+                },
+                Some(_) => {},
+                None => {
+                    return Err(ShaderError::in_stage(
+                        ShaderStage::Vertex,
+                        format!("instance attribute `{}` is not an input", name),
+                    ));
+                },
+            }
+        }
+
+        #[cfg(feature = "naga")]
+        {
+            crate::platform::shader_naga::compile(&self.vertex_shader, ShaderStage::Vertex, &vertex, &fragment)?;
+            crate::platform::shader_naga::compile(&self.fragment_shader, ShaderStage::Fragment, &vertex, &fragment)?;
+        }
+
+        let mut shader = Shader::new(ShaderId(0));
 
         shader.set_vertex_shader(&self.vertex_shader);
         shader.set_fragment_shader(&self.fragment_shader);
+        shader.uniforms = uniforms;
+        shader.inputs = vertex.inputs;
+
+        for (name, format) in self.instance_attributes {
+            shader.add_instance_attribute(&name, format);
+        }
+
+        if let Some(geometry) = self.geometry_id.and_then(|id| cx.geometries.get(&id)) {
+            shader.validate_geometry(geometry)?;
+        }
+
+        // Only valid shaders get an id, shared with any identical shader
+        Ok(cx.register_shader(shader))
+    }
+}
+
+// A quad whose look comes from a fragment body written by the widget. The
+// body is the inside of
+//
+//     vec4 shade(vec2 uv, vec2 size, vec4 color)
+//
+// where `uv` runs 0..1 across the rect, `size` is the rect in pixels and
+// `color` is `DrawShader::color`. It can read `time` (seconds, from
+// `Cx::time`), its own uniforms and textures, and the helpers `sdf_fill(d)`
+// and `rounded_box(p, half_size, radius)`. Clipping, transforms, instancing
+// and the quad geometry are handled here.
+//
+//     let ring = DrawShader::new("
+//         vec2 p = (uv - 0.5) * size;
+//         float r = min(size.x, size.y) * 0.5;
+//         float arc = fract(atan(p.x, -p.y) / 6.28318) <= progress ? 1.0 : 0.0;
+//         return color * sdf_fill(abs(length(p) - r * 0.85) - r * 0.1) * arc;
+//     ").with_uniform("progress", DrawUniform::Float(0.25));
+#[derive(Clone, Debug)]
+pub struct DrawShader {
+    pub fragment_body: String,
+    pub color: Color,
+    pub uniforms: Vec<UniformBinding>,
+    pub textures: Vec<(String, Texture)>,
+    pub shader_id: Option<ShaderId>,
+    pub geometry_id: Option<GeometryId>,
+    // Set when the body failed to compile; nothing is drawn
+    pub error: Option<ShaderError>,
+}
+
+impl DrawShader {
+    pub fn new(fragment_body: &str) -> Self {
+        Self {
+            fragment_body: fragment_body.to_string(),
+            color: Color::white(),
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+            shader_id: None,
+            geometry_id: None,
+            error: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    // Declares a uniform typed after `value`
+    pub fn with_uniform(mut self, name: &str, value: DrawUniform) -> Self {
+        self.set_uniform(name, value);
+        self
+    }
+
+    // Declares a `sampler2D` uniform
+    pub fn with_texture(mut self, name: &str, texture: Texture) -> Self {
+        self.set_texture(name, texture);
+        self
+    }
+
+    // Changes a uniform's value. New names only take effect before `init`.
+    pub fn set_uniform(&mut self, name: &str, value: DrawUniform) {
+        match self.uniforms.iter_mut().find(|u| u.name == name) {
+            Some(binding) => binding.value = value,
+            None => self.uniforms.push(UniformBinding::new(name, value)),
+        }
+    }
+
+    pub fn set_texture(&mut self, name: &str, texture: Texture) {
+        match self.textures.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = texture,
+            None => self.textures.push((name.to_string(), texture)),
+        }
+    }
+
+    // Compiles the body. Errors inside it report lines counted from the
+    // body's first line.
+    pub fn init(&mut self, cx: &mut Cx) -> Result<(), ShaderError> {
+        if self.shader_id.is_none() {
+            let mut declarations = String::new();
+            for binding in &self.uniforms {
+                let glsl_type = match binding.value.uniform_type() {
+                    ShaderUniformType::Float => "float".to_string(),
+                    ShaderUniformType::Vec2 => "vec2".to_string(),
+                    ShaderUniformType::Vec3 => "vec3".to_string(),
+                    ShaderUniformType::Vec4 => "vec4".to_string(),
+                    ShaderUniformType::Mat4 => "mat4".to_string(),
+                    ShaderUniformType::Vec4Array(len) => {
+                        declarations.push_str(&format!("uniform vec4 {}[{}];\n", binding.name, len));
+                        continue;
+                    },
+                    ShaderUniformType::Texture2D => unreachable!(),
+                };
+                declarations.push_str(&format!("uniform {} {};\n", glsl_type, binding.name));
+            }
+            for (name, _) in &self.textures {
+                declarations.push_str(&format!("uniform sampler2D {};\n", name));
+            }
+
+            let header = format!("{}{}{}", DRAW_SHADER_FRAGMENT_HEADER, declarations, DRAW_SHADER_FRAGMENT_LIB);
+            let body_offset = header.matches('\n').count();
+            let fragment_shader = format!("{}{}\n}}{}", header, self.fragment_body, DRAW_SHADER_FRAGMENT_MAIN);
+
+            let result = ShaderBuilder::new()
+                .vertex_shader(DRAW_SHADER_VERTEX_SHADER)
+                .fragment_shader(&fragment_shader)
+                .instance("rect", VertexFormat::Float4)
+                .instance("color", VertexFormat::Float4)
+                .build(cx);
+
+            match result {
+                Ok(shader_id) => self.shader_id = Some(shader_id),
+                Err(mut err) => {
+                    let body_lines = self.fragment_body.matches('\n').count() + 1;
+                    if let (Some(ShaderStage::Fragment), Some(line)) = (err.stage, err.line) {
+                        if line > body_offset && line <= body_offset + body_lines {
+                            err.line = Some(line - body_offset);
+                            err.message = format!("{} (in fragment body)", err.message);
+                        }
+                    }
+                    self.error = Some(err.clone());
+                    return Err(err);
+                },
+            }
+        }
+
+        if self.geometry_id.is_none() {
+            self.geometry_id = Some(create_quad_geometry(cx));
+        }
+        Ok(())
+    }
 
-        for (name, uniform_type) in self.uniforms {
-            shader.add_uniform(&name, uniform_type);
+    pub fn draw(&mut self, cx: &mut Cx2d, draw_list_id: DrawListId, rect: &Rect) {
+        if self.error.is_some() {
+            return;
         }
+        if self.shader_id.is_none() || self.geometry_id.is_none() {
+            if let Err(err) = self.init(cx) {
+                cx.debug.report(&format!("DrawShader: {}", err));
+                return;
+            }
+        }
+
+        if let Some(clip_rect) = cx.clip_rect() {
+            if !clip_rect.intersects(&cx.transformed_rect(rect)) {
+                return;
+            }
+        }
+
+        let mut uniforms = self.uniforms.clone();
+        uniforms.push(UniformBinding::new("time", DrawUniform::Float(cx.time() as f32)));
+
+        let mut instances = Vec::with_capacity(8);
+        instances.extend_from_slice(&[rect.x(), rect.y(), rect.width(), rect.height()]);
+        instances.extend_from_slice(&self.color.to_array());
+
+        let draw_item = DrawItem {
+            shader_id: self.shader_id.unwrap(),
+            geometry_id: self.geometry_id.unwrap(),
+            uniforms,
+            textures: self.textures.iter().map(|(_, texture)| texture.clone()).collect(),
+            instances,
+            instance_count: 1,
+            clip: cx.current_clip(),
+            transform: cx.current_transform(),
+            blend: cx.current_blend(),
+            sub_list: None,
+        };
+
+        cx.append_draw_item(draw_list_id, draw_item);
+    }
+}
+
+const DRAW_SHADER_VERTEX_SHADER: &str = r#"
+#version 300 es
+precision highp float;
+
+in vec2 position;
+in vec2 uv;
+
+// Per instance
+in vec4 rect;
+in vec4 color;
+
+uniform mat4 view_transform;
+uniform mat4 model_transform;
+
+out vec2 v_uv;
+out vec2 v_world;
+flat out vec2 v_size;
+flat out vec4 v_color;
+
+void main() {
+    v_uv = uv;
+    v_size = rect.zw;
+    v_color = color;
+    vec2 pos = rect.xy + position * rect.zw;
+    vec4 world = model_transform * vec4(pos, 0.0, 1.0);
+    v_world = world.xy;
+    gl_Position = view_transform * world;
+}
+"#;
+
+const DRAW_SHADER_FRAGMENT_HEADER: &str = r#"
+#version 300 es
+precision highp float;
+
+in vec2 v_uv;
+in vec2 v_world;
+flat in vec2 v_size;
+flat in vec4 v_color;
+
+// Supplied by the backend from DrawItem::clip
+uniform vec4 clip_rect;
+uniform float clip_radius;
+
+uniform float time;
+"#;
+
+// Straight alpha in and out, like the other quad shaders
+const DRAW_SHADER_FRAGMENT_LIB: &str = r#"
+out vec4 frag_color;
+
+float rounded_box(vec2 p, vec2 b, float r) {
+    return length(max(abs(p) - b + r, 0.0)) - r;
+}
+
+// Antialiased coverage of a signed distance, inside where negative
+float sdf_fill(float d) {
+    return clamp(0.5 - d / max(fwidth(d), 1e-4), 0.0, 1.0);
+}
 
-        cx.shaders.insert(shader_id, shader);
+vec4 shade(vec2 uv, vec2 size, vec4 color) {
+"#;
 
-        shader_id
+const DRAW_SHADER_FRAGMENT_MAIN: &str = r#"
+
+void main() {
+    vec2 clip_half = clip_rect.zw * 0.5;
+    if (clip_radius > 0.0 && rounded_box(v_world - clip_rect.xy - clip_half, clip_half, clip_radius) > 0.0) {
+        discard;
+    }
+
+    vec4 result = shade(v_uv, v_size, v_color);
+    if (result.a <= 0.0) {
+        discard;
     }
+    frag_color = result;
 }
+"#;
+
+
+#[cfg(test)]
+mod tests {
+    use super::{DrawShader, ShaderBuilder};
+    use crate::platform::Cx;
+    use crate::platform::shader::{ShaderId, ShaderUniformType};
+    use crate::platform::geometry::VertexFormat;
+    use crate::draw::quad::DrawQuad;
+    use crate::draw::text::DrawText;
+    use crate::draw::shadow::DrawShadow;
+    use crate::draw::backdrop_blur::DrawBackdropBlur;
+    use crate::draw::image::DrawImage;
+    use crate::draw::path::DrawPath;
+    use crate::draw::cx_2d::Cx2d;
+    use crate::draw::rect::Rect;
+
+    fn assert_reflected(cx: &Cx, shader_id: Option<ShaderId>) {
+        let shader = &cx.shaders[&shader_id.unwrap()];
+        assert!(!shader.inputs.is_empty(), "shader {} was not reflected", shader.shader_id.0);
+    }
 
+    #[test]
+    fn built_in_shaders_parse_and_link() {
+        // Each init panics if its shader fails ShaderBuilder validation
+        let mut cx = Cx::new();
 
+        let mut quad = DrawQuad::new();
+        quad.init(&mut cx);
+        assert_reflected(&cx, quad.shader_id);
+
+        let mut text = DrawText::new();
+        text.init(&mut cx);
+        assert_reflected(&cx, text.shader_id);
+
+        let mut shadow = DrawShadow::new();
+        shadow.init(&mut cx);
+        assert_reflected(&cx, shadow.shader_id);
+
+        let mut blur = DrawBackdropBlur::new();
+        blur.init(&mut cx);
+        assert_reflected(&cx, blur.shader_id);
+
+        let mut image = DrawImage::new();
+        image.init(&mut cx);
+        assert_reflected(&cx, image.shader_id);
+
+        let mut path = DrawPath::new();
+        path.init(&mut cx);
+        assert_reflected(&cx, path.shader_id);
+    }
+
+    #[test]
+    fn draw_shader_quads_in_one_frame_batch() {
+        let mut cx = Cx::new();
+        let draw_list_id = cx.create_draw_list();
+        let mut shader = DrawShader::new("return color * (0.5 + 0.5 * sin(time));");
+        let mut cx2d = Cx2d::new(&mut cx);
+        shader.draw(&mut cx2d, draw_list_id, &Rect::new(0.0, 0.0, 10.0, 10.0));
+        shader.draw(&mut cx2d, draw_list_id, &Rect::new(20.0, 0.0, 10.0, 10.0));
+        drop(cx2d);
+
+        assert!(cx.debug.errors.is_empty(), "{:?}", cx.debug.errors);
+        let items = &cx.draw_lists[&draw_list_id].draw_items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].instance_count, 2);
+    }
+
+    fn builder() -> ShaderBuilder {
+        ShaderBuilder::new()
+            .vertex_shader("#version 300 es\nin vec2 position;\nin vec4 rect;\nuniform vec4 color;\nvoid main() { gl_Position = vec4(position, 0.0, 1.0); }\n")
+            .fragment_shader("#version 300 es\nprecision highp float;\nuniform float radius;\nout vec4 frag_color;\nvoid main() { frag_color = vec4(radius); }\n")
+    }
+
+    #[test]
+    fn builder_declarations_are_checked_against_the_source() {
+        let mut cx = Cx::new();
+        let built = builder()
+            .uniform("color", ShaderUniformType::Vec4)
+            .uniform("radius", ShaderUniformType::Float)
+            .instance("rect", VertexFormat::Float4)
+            .build(&mut cx);
+        assert!(built.is_ok(), "{:?}", built);
+
+        let err = builder().uniform("radius", ShaderUniformType::Vec2).build(&mut cx).unwrap_err();
+        assert_eq!(err.to_string(), "fragment shader, line 3: uniform `radius` is Float in source but was declared Vec2");
+
+        let err = builder().uniform("size", ShaderUniformType::Vec2).build(&mut cx).unwrap_err();
+        assert_eq!(err.to_string(), "uniform `size` is not declared in either stage");
+
+        let err = builder().instance("rect", VertexFormat::Float2).build(&mut cx).unwrap_err();
+        assert_eq!(err.to_string(), "vertex shader, line 3: `rect` is vec4 but its instance attribute is Float2");
+
+        let err = builder().instance("offset", VertexFormat::Float2).build(&mut cx).unwrap_err();
+        assert_eq!(err.to_string(), "vertex shader: instance attribute `offset` is not an input");
+    }
+}
@@ -0,0 +1,294 @@
+use crate::platform::geometry::{GeometryId, VertexFormat};
+use crate::platform::shader::ShaderId;
+use crate::platform::draw_list::{DrawItem, DrawListId};
+use crate::platform::math::Vec2;
+use crate::platform::Cx;
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::shader::ShaderBuilder;
+use crate::draw::color::Color;
+use crate::draw::rect::Rect;
+use crate::draw::quad::create_quad_geometry;
+
+// Box shadow for a rounded rect, with the same parameters as CSS `box-shadow`.
+// Draw it before the quad it belongs to; outer shadows are cut out underneath
+// the box so translucent quads don't show their own shadow.
+#[derive(Clone, Debug)]
+pub struct DrawShadow {
+    pub color: Color,
+    pub offset: Vec2,
+    pub blur_radius: f32,
+    pub spread: f32,
+    pub corner_radius: f32,
+    pub inset: bool,
+    pub shader_id: Option<ShaderId>,
+    pub geometry_id: Option<GeometryId>,
+}
+
+impl Default for DrawShadow {
+    fn default() -> Self {
+        Self {
+            color: Color::new(0.0, 0.0, 0.0, 0.25),
+            offset: Vec2::new(0.0, 2.0),
+            blur_radius: 8.0,
+            spread: 0.0,
+            corner_radius: 0.0,
+            inset: false,
+            shader_id: None,
+            geometry_id: None,
+        }
+    }
+}
+
+impl DrawShadow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_offset(mut self, x: f32, y: f32) -> Self {
+        self.offset = Vec2::new(x, y);
+        self
+    }
+
+    pub fn with_blur_radius(mut self, blur_radius: f32) -> Self {
+        self.blur_radius = blur_radius;
+        self
+    }
+
+    pub fn with_spread(mut self, spread: f32) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self {
+        self.corner_radius = corner_radius;
+        self
+    }
+
+    pub fn with_inset(mut self, inset: bool) -> Self {
+        self.inset = inset;
+        self
+    }
+
+    pub fn init(&mut self, cx: &mut Cx) {
+        if self.geometry_id.is_none() {
+            self.geometry_id = Some(create_quad_geometry(cx));
+        }
+
+        if self.shader_id.is_none() {
+            let shader_id = ShaderBuilder::new()
+                .vertex_shader(SHADOW_VERTEX_SHADER)
+                .fragment_shader(SHADOW_FRAGMENT_SHADER)
+                .instance("color", VertexFormat::Float4)
+                .instance("rect", VertexFormat::Float4)
+                .instance("shadow", VertexFormat::Float4)
+                .instance("corner_radius", VertexFormat::Float1)
+                .instance("inset", VertexFormat::Float1)
+                .geometry(self.geometry_id.unwrap())
+                .build(cx)
+                .expect("DrawShadow shader failed validation");
+            self.shader_id = Some(shader_id);
+        }
+    }
+
+    // The area the shadow can touch: the offset box grown by spread and three
+    // standard deviations of blur, or the box itself for inset shadows
+    pub fn bounds(&self, rect: &Rect) -> Rect {
+        if self.inset {
+            return *rect;
+        }
+        let shadow_rect = Rect::from_pos_size(rect.pos + self.offset, rect.size);
+        shadow_rect.expand(self.spread + self.blur_radius * 1.5)
+    }
+
+    // `rect` is the box casting the shadow, not the shadow's extent
+    pub fn draw(&mut self, cx: &mut Cx2d, draw_list_id: DrawListId, rect: &Rect) {
+        if self.shader_id.is_none() || self.geometry_id.is_none() {
+            self.init(cx);
+        }
+
+        if self.color.a <= 0.0 {
+            return;
+        }
+
+        if let Some(clip_rect) = cx.clip_rect() {
+            if !clip_rect.intersects(&cx.transformed_rect(&self.bounds(rect))) {
+                return;
+            }
+        }
+
+        let draw_item = DrawItem {
+            shader_id: self.shader_id.unwrap(),
+            geometry_id: self.geometry_id.unwrap(),
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+            instances: self.instance_data(rect),
+            instance_count: 1,
+            clip: cx.current_clip(),
+            transform: cx.current_transform(),
+            blend: cx.current_blend(),
+            sub_list: None,
+        };
+
+        cx.append_draw_item(draw_list_id, draw_item);
+    }
+
+    // Matches the instance attribute layout declared in `init`
+    fn instance_data(&self, rect: &Rect) -> Vec<f32> {
+        let mut data = Vec::with_capacity(14);
+        data.extend_from_slice(&self.color.to_array());
+        data.extend_from_slice(&[rect.x(), rect.y(), rect.width(), rect.height()]);
+        data.extend_from_slice(&[self.offset.x, self.offset.y, self.blur_radius, self.spread]);
+        data.push(self.corner_radius);
+        data.push(if self.inset { 1.0 } else { 0.0 });
+        data
+    }
+}
+
+const SHADOW_VERTEX_SHADER: &str = r#"
+#version 300 es
+precision highp float;
+
+in vec2 position;
+in vec2 uv;
+
+// Per instance
+in vec4 color;
+in vec4 rect;
+in vec4 shadow; // offset.xy, blur radius, spread
+in float corner_radius;
+in float inset;
+
+uniform mat4 view_transform;
+uniform mat4 model_transform;
+
+out vec2 v_pos;
+out vec2 v_world;
+flat out vec4 v_color;
+flat out vec4 v_rect;
+flat out vec4 v_shadow;
+flat out float v_corner_radius;
+flat out float v_inset;
+
+void main() {
+    v_color = color;
+    v_rect = rect;
+    v_shadow = shadow;
+    v_corner_radius = corner_radius;
+    v_inset = inset;
+
+    vec4 bounds = rect;
+    if (inset < 0.5) {
+        float grow = shadow.w + shadow.z * 1.5;
+        bounds = vec4(rect.xy + shadow.xy - grow, rect.zw + grow * 2.0);
+    }
+
+    v_pos = bounds.xy + position * bounds.zw;
+    vec4 world = model_transform * vec4(v_pos, 0.0, 1.0);
+    v_world = world.xy;
+    gl_Position = view_transform * world;
+}
+"#;
+
+// The blurred box is integrated analytically: exact along x, a four sample
+// gaussian quadrature along y, with the rounded corners folded into the
+// x-extent of each row.
+const SHADOW_FRAGMENT_SHADER: &str = r#"
+#version 300 es
+precision highp float;
+
+in vec2 v_pos;
+in vec2 v_world;
+flat in vec4 v_color;
+flat in vec4 v_rect;
+flat in vec4 v_shadow;
+flat in float v_corner_radius;
+flat in float v_inset;
+
+// Supplied by the backend from DrawItem::clip
+uniform vec4 clip_rect;
+uniform float clip_radius;
+
+out vec4 frag_color;
+
+float rounded_box(vec2 p, vec2 b, float r) {
+    return length(max(abs(p) - b + r, 0.0)) - r;
+}
+
+float gaussian(float x, float sigma) {
+    return exp(-(x * x) / (2.0 * sigma * sigma)) / (2.5066282746 * sigma);
+}
+
+vec2 erf2(vec2 x) {
+    vec2 s = sign(x);
+    vec2 a = abs(x);
+    x = 1.0 + (0.278393 + (0.230389 + 0.078108 * (a * a)) * a) * a;
+    x *= x;
+    return s - s / (x * x);
+}
+
+float shadow_row(float x, float y, float sigma, float corner, vec2 half_size) {
+    float delta = min(half_size.y - corner - abs(y), 0.0);
+    float curved = half_size.x - corner + sqrt(max(0.0, corner * corner - delta * delta));
+    vec2 integral = 0.5 + 0.5 * erf2((x + vec2(-curved, curved)) * (sqrt(0.5) / sigma));
+    return integral.y - integral.x;
+}
+
+float rounded_box_shadow(vec2 lower, vec2 upper, vec2 point, float sigma, float corner) {
+    vec2 center = (lower + upper) * 0.5;
+    vec2 half_size = max((upper - lower) * 0.5, vec2(0.0));
+    corner = min(corner, min(half_size.x, half_size.y));
+    point -= center;
+
+    float low = point.y - half_size.y;
+    float high = point.y + half_size.y;
+    float start = clamp(-3.0 * sigma, low, high);
+    float end = clamp(3.0 * sigma, low, high);
+
+    float step = (end - start) / 4.0;
+    float y = start + step * 0.5;
+    float value = 0.0;
+    for (int i = 0; i < 4; i++) {
+        value += shadow_row(point.x, point.y - y, sigma, corner, half_size) * gaussian(y, sigma) * step;
+        y += step;
+    }
+    return value;
+}
+
+void main() {
+    vec2 clip_half = clip_rect.zw * 0.5;
+    if (clip_radius > 0.0 && rounded_box(v_world - clip_rect.xy - clip_half, clip_half, clip_radius) > 0.0) {
+        discard;
+    }
+
+    // CSS blur radius is twice the standard deviation
+    float sigma = max(v_shadow.z * 0.5, 0.5);
+    vec2 offset = v_shadow.xy;
+    float spread = v_shadow.w;
+
+    vec2 half_size = v_rect.zw * 0.5;
+    float box = rounded_box(v_pos - v_rect.xy - half_size, half_size, v_corner_radius);
+
+    float alpha;
+    if (v_inset > 0.5) {
+        vec2 lower = v_rect.xy + offset + spread;
+        vec2 upper = v_rect.xy + v_rect.zw + offset - spread;
+        alpha = 1.0 - rounded_box_shadow(lower, upper, v_pos, sigma, max(v_corner_radius - spread, 0.0));
+        alpha *= clamp(0.5 - box, 0.0, 1.0);
+    } else {
+        vec2 lower = v_rect.xy + offset - spread;
+        vec2 upper = v_rect.xy + v_rect.zw + offset + spread;
+        alpha = rounded_box_shadow(lower, upper, v_pos, sigma, max(v_corner_radius + spread, 0.0));
+        alpha *= clamp(box + 0.5, 0.0, 1.0);
+    }
+
+    if (alpha <= 0.0) {
+        discard;
+    }
+    frag_color = vec4(v_color.rgb, v_color.a * alpha);
+}
+"#;
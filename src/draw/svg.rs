@@ -0,0 +1,997 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::platform::math::{Vec2, Affine2};
+use crate::platform::draw_list::DrawListId;
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::color::Color;
+use crate::draw::rect::Rect;
+use crate::draw::gradient::Gradient;
+use crate::draw::path::{Path, DrawPath};
+use crate::draw::tessellator::{FillRule, LineCap, LineJoin, StrokeStyle};
+
+#[derive(Debug)]
+pub enum SvgError {
+    Io(std::io::Error),
+    Xml(String),
+    NotSvg,
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SvgError::Io(err) => write!(f, "failed to read svg: {}", err),
+            SvgError::Xml(err) => write!(f, "invalid svg: {}", err),
+            SvgError::NotSvg => write!(f, "root element is not <svg>"),
+        }
+    }
+}
+
+impl std::error::Error for SvgError {}
+
+// Gradients are stored in the shape's bounding box space, like DrawPath uses
+// them. userSpaceOnUse gradients are converted when loading, which is exact
+// unless the shape is rotated or skewed afterwards.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SvgPaint {
+    Color(Color),
+    Gradient(Gradient),
+    CurrentColor,
+}
+
+#[derive(Clone, Debug)]
+pub struct SvgShape {
+    pub path: Path,
+    pub fill: Option<SvgPaint>,
+    pub fill_rule: FillRule,
+    pub stroke: Option<SvgPaint>,
+    pub stroke_style: StrokeStyle,
+    // Element opacity times fill/stroke opacity
+    pub fill_opacity: f32,
+    pub stroke_opacity: f32,
+}
+
+#[derive(Clone, Debug)]
+pub enum SvgNode {
+    Group(SvgGroup),
+    Shape(SvgShape),
+}
+
+#[derive(Clone, Debug)]
+pub struct SvgGroup {
+    pub transform: Affine2,
+    pub opacity: f32,
+    pub children: Vec<SvgNode>,
+}
+
+impl SvgGroup {
+    fn new(transform: Affine2, opacity: f32) -> Self {
+        Self {
+            transform,
+            opacity,
+            children: Vec::new(),
+        }
+    }
+}
+
+// A parsed SVG document, kept as a tree in view box coordinates
+#[derive(Clone, Debug)]
+pub struct SvgScene {
+    pub view_box: Rect,
+    pub root: SvgGroup,
+}
+
+impl SvgScene {
+    pub fn parse(source: &str) -> Result<Self, SvgError> {
+        let document = roxmltree::Document::parse(source).map_err(|err| SvgError::Xml(err.to_string()))?;
+        let svg = document.root_element();
+        if svg.tag_name().name() != "svg" {
+            return Err(SvgError::NotSvg);
+        }
+
+        let width = svg.attribute("width").and_then(parse_length);
+        let height = svg.attribute("height").and_then(parse_length);
+        let view_box = svg
+            .attribute("viewBox")
+            .map(parse_numbers)
+            .filter(|v| v.len() == 4 && v[2] > 0.0 && v[3] > 0.0)
+            .map(|v| Rect::new(v[0], v[1], v[2], v[3]))
+            .unwrap_or_else(|| Rect::new(0.0, 0.0, width.unwrap_or(100.0), height.unwrap_or(100.0)));
+
+        let mut gradients = HashMap::new();
+        for node in document.descendants() {
+            if let Some(id) = node.attribute("id") {
+                if let Some(def) = GradientDef::parse(&node) {
+                    gradients.insert(id.to_string(), def);
+                }
+            }
+        }
+
+        let parser = SvgParser { gradients };
+        let mut root = SvgGroup::new(Affine2::identity(), 1.0);
+        parser.parse_children(&svg, &Style::default(), &mut root);
+        Ok(Self { view_box, root })
+    }
+
+    pub fn from_file(path: &std::path::Path) -> Result<Self, SvgError> {
+        let source = std::fs::read_to_string(path).map_err(SvgError::Io)?;
+        Self::parse(&source)
+    }
+
+    // All shapes with group transforms baked into their paths and group
+    // opacity folded into their own, in drawing order
+    pub fn shapes(&self) -> Vec<SvgShape> {
+        let mut shapes = Vec::new();
+        collect_shapes(&self.root, Affine2::identity(), 1.0, &mut shapes);
+        shapes
+    }
+}
+
+fn collect_shapes(group: &SvgGroup, parent: Affine2, parent_opacity: f32, shapes: &mut Vec<SvgShape>) {
+    let transform = parent.multiply(&group.transform);
+    let opacity = parent_opacity * group.opacity;
+    for child in &group.children {
+        match child {
+            SvgNode::Group(child) => collect_shapes(child, transform, opacity, shapes),
+            SvgNode::Shape(shape) => {
+                let mut shape = shape.clone();
+                if !transform.is_identity() {
+                    shape.path.transform(&transform);
+                    // Non-uniform scales get their average stroke width
+                    shape.stroke_style.width *= transform.determinant().abs().sqrt();
+                }
+                shape.fill_opacity *= opacity;
+                shape.stroke_opacity *= opacity;
+                shapes.push(shape);
+            },
+        }
+    }
+}
+
+// Draws a scene scaled to fit inside a rect, centered and keeping its aspect
+// ratio. `tint` recolors every fill and stroke, keeping their alpha, so icons
+// can follow Theme colors.
+#[derive(Clone, Debug)]
+pub struct DrawSvg {
+    pub scene: SvgScene,
+    pub tint: Option<Color>,
+    // What `currentColor` resolves to without a tint
+    pub current_color: Color,
+    shapes: Vec<SvgShape>,
+    draw_paths: Vec<DrawPath>,
+}
+
+impl DrawSvg {
+    pub fn new(scene: SvgScene) -> Self {
+        Self {
+            scene,
+            tint: None,
+            current_color: Color::black(),
+            shapes: Vec::new(),
+            draw_paths: Vec::new(),
+        }
+    }
+
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+
+    pub fn with_current_color(mut self, color: Color) -> Self {
+        self.current_color = color;
+        self
+    }
+
+    pub fn set_scene(&mut self, scene: SvgScene) {
+        self.scene = scene;
+        self.shapes.clear();
+        self.draw_paths.clear();
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx2d, draw_list_id: DrawListId, rect: &Rect) {
+        let view_box = self.scene.view_box;
+        let scale = (rect.width() / view_box.width()).min(rect.height() / view_box.height());
+        if scale <= 0.0 || !scale.is_finite() {
+            return;
+        }
+
+        if self.draw_paths.is_empty() {
+            self.shapes = self.scene.shapes();
+            self.draw_paths = self
+                .shapes
+                .iter()
+                .map(|shape| {
+                    let mut draw_path = DrawPath::new().with_path(shape.path.clone()).with_fill_rule(shape.fill_rule);
+                    draw_path.stroke = shape.stroke_style.clone();
+                    draw_path
+                })
+                .collect();
+        }
+
+        let offset = rect.pos + (rect.size - view_box.size * scale) * 0.5 - view_box.pos * scale;
+        cx.push_transform(Affine2::translation(offset.x, offset.y).multiply(&Affine2::scale(scale, scale)));
+
+        for (shape, draw_path) in self.shapes.iter().zip(self.draw_paths.iter_mut()) {
+            let (fill_color, fill_gradient) = resolve_paint(shape.fill.as_ref(), shape.fill_opacity, self.tint, self.current_color);
+            let (stroke_color, stroke_gradient) = resolve_paint(shape.stroke.as_ref(), shape.stroke_opacity, self.tint, self.current_color);
+            draw_path.fill_color = fill_color;
+            draw_path.fill_gradient = fill_gradient;
+            draw_path.stroke_color = stroke_color;
+            draw_path.stroke_gradient = stroke_gradient;
+            // Keep curves within a quarter pixel on screen
+            draw_path.tolerance = 0.25 / scale;
+            draw_path.draw(cx, draw_list_id, &Rect::zero());
+        }
+
+        cx.pop_transform();
+    }
+}
+
+fn resolve_paint(paint: Option<&SvgPaint>, opacity: f32, tint: Option<Color>, current_color: Color) -> (Option<Color>, Option<Gradient>) {
+    let paint = match paint {
+        Some(paint) => paint,
+        None => return (None, None),
+    };
+    if let Some(tint) = tint {
+        let alpha = match paint {
+            SvgPaint::Color(color) => color.a,
+            _ => 1.0,
+        };
+        return (Some(Color::new(tint.r, tint.g, tint.b, tint.a * alpha * opacity)), None);
+    }
+    match paint {
+        SvgPaint::Color(color) => (Some(Color::new(color.r, color.g, color.b, color.a * opacity)), None),
+        SvgPaint::CurrentColor => (Some(Color::new(current_color.r, current_color.g, current_color.b, current_color.a * opacity)), None),
+        SvgPaint::Gradient(gradient) => (Some(Color::new(1.0, 1.0, 1.0, opacity)), Some(gradient.clone())),
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PaintSpec {
+    None,
+    Color(Color),
+    CurrentColor,
+    Url(String),
+}
+
+// Presentation properties, inherited from parent elements
+#[derive(Clone, Debug)]
+struct Style {
+    fill: PaintSpec,
+    fill_rule: FillRule,
+    fill_opacity: f32,
+    stroke: PaintSpec,
+    stroke_opacity: f32,
+    stroke_style: StrokeStyle,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: PaintSpec::Color(Color::black()),
+            fill_rule: FillRule::NonZero,
+            fill_opacity: 1.0,
+            stroke: PaintSpec::None,
+            stroke_opacity: 1.0,
+            stroke_style: StrokeStyle::default(),
+        }
+    }
+}
+
+impl Style {
+    // Attributes first, then the `style` attribute which takes precedence
+    fn inherit(&self, node: &roxmltree::Node) -> Style {
+        let mut style = self.clone();
+        for attribute in node.attributes() {
+            style.apply(attribute.name(), attribute.value());
+        }
+        if let Some(declarations) = node.attribute("style") {
+            for declaration in declarations.split(';') {
+                if let Some((name, value)) = declaration.split_once(':') {
+                    style.apply(name.trim(), value.trim());
+                }
+            }
+        }
+        style
+    }
+
+    fn apply(&mut self, name: &str, value: &str) {
+        match name {
+            "fill" => self.fill = parse_paint(value).unwrap_or(PaintSpec::Color(Color::black())),
+            "fill-rule" => self.fill_rule = if value == "evenodd" { FillRule::EvenOdd } else { FillRule::NonZero },
+            "fill-opacity" => self.fill_opacity = parse_opacity(value),
+            "stroke" => self.stroke = parse_paint(value).unwrap_or(PaintSpec::None),
+            "stroke-opacity" => self.stroke_opacity = parse_opacity(value),
+            "stroke-width" => self.stroke_style.width = parse_length(value).unwrap_or(1.0),
+            "stroke-linecap" => {
+                self.stroke_style.cap = match value {
+                    "round" => LineCap::Round,
+                    "square" => LineCap::Square,
+                    _ => LineCap::Butt,
+                }
+            },
+            "stroke-linejoin" => {
+                self.stroke_style.join = match value {
+                    "round" => LineJoin::Round,
+                    "bevel" => LineJoin::Bevel,
+                    _ => LineJoin::Miter,
+                }
+            },
+            "stroke-miterlimit" => self.stroke_style.miter_limit = value.trim().parse().unwrap_or(4.0),
+            "stroke-dasharray" => {
+                self.stroke_style.dashes = if value == "none" { Vec::new() } else { parse_numbers(value) }
+            },
+            "stroke-dashoffset" => self.stroke_style.dash_offset = parse_length(value).unwrap_or(0.0),
+            _ => {},
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum GradientGeometry {
+    Linear { x1: Coord, y1: Coord, x2: Coord, y2: Coord },
+    Radial { cx: Coord, cy: Coord, r: Coord },
+}
+
+// A gradient coordinate; percentages are fractions of the bounding box or
+// view box depending on gradientUnits
+#[derive(Clone, Copy, Debug)]
+enum Coord {
+    Number(f32),
+    Fraction(f32),
+}
+
+impl Coord {
+    fn parse(value: Option<&str>, default: Coord) -> Coord {
+        match value.map(str::trim) {
+            Some(v) if v.ends_with('%') => v[..v.len() - 1].parse().map(|p: f32| Coord::Fraction(p / 100.0)).unwrap_or(default),
+            Some(v) => parse_length(v).map(Coord::Number).unwrap_or(default),
+            None => default,
+        }
+    }
+
+    // Into bounding box space along an axis starting at `origin` with `size`
+    fn resolve(self, user_space: bool, origin: f32, size: f32) -> f32 {
+        match (self, user_space) {
+            (Coord::Fraction(f), _) => f,
+            (Coord::Number(n), false) => n,
+            (Coord::Number(n), true) => (n - origin) / size.max(1e-6),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct GradientDef {
+    geometry: GradientGeometry,
+    user_space: bool,
+    stops: Vec<(f32, Color)>,
+    href: Option<String>,
+}
+
+impl GradientDef {
+    fn parse(node: &roxmltree::Node) -> Option<GradientDef> {
+        let geometry = match node.tag_name().name() {
+            "linearGradient" => GradientGeometry::Linear {
+                x1: Coord::parse(node.attribute("x1"), Coord::Fraction(0.0)),
+                y1: Coord::parse(node.attribute("y1"), Coord::Fraction(0.0)),
+                x2: Coord::parse(node.attribute("x2"), Coord::Fraction(1.0)),
+                y2: Coord::parse(node.attribute("y2"), Coord::Fraction(0.0)),
+            },
+            "radialGradient" => GradientGeometry::Radial {
+                cx: Coord::parse(node.attribute("cx"), Coord::Fraction(0.5)),
+                cy: Coord::parse(node.attribute("cy"), Coord::Fraction(0.5)),
+                r: Coord::parse(node.attribute("r"), Coord::Fraction(0.5)),
+            },
+            _ => return None,
+        };
+
+        let mut stops = Vec::new();
+        for stop in node.children().filter(|n| n.tag_name().name() == "stop") {
+            let offset = match stop.attribute("offset").map(str::trim) {
+                Some(v) if v.ends_with('%') => v[..v.len() - 1].parse::<f32>().unwrap_or(0.0) / 100.0,
+                Some(v) => v.parse().unwrap_or(0.0),
+                None => 0.0,
+            };
+            let mut color = Color::black();
+            let mut opacity = 1.0;
+            let mut apply = |name: &str, value: &str| match name {
+                "stop-color" => {
+                    if let Some(PaintSpec::Color(c)) = parse_paint(value) {
+                        color = c;
+                    }
+                },
+                "stop-opacity" => opacity = parse_opacity(value),
+                _ => {},
+            };
+            for attribute in stop.attributes() {
+                apply(attribute.name(), attribute.value());
+            }
+            if let Some(declarations) = stop.attribute("style") {
+                for declaration in declarations.split(';') {
+                    if let Some((name, value)) = declaration.split_once(':') {
+                        apply(name.trim(), value.trim());
+                    }
+                }
+            }
+            stops.push((offset.clamp(0.0, 1.0), Color::new(color.r, color.g, color.b, color.a * opacity)));
+        }
+
+        let href = node
+            .attributes()
+            .find(|a| a.name() == "href")
+            .and_then(|a| a.value().strip_prefix('#'))
+            .map(str::to_string);
+
+        Some(GradientDef {
+            geometry,
+            user_space: node.attribute("gradientUnits") == Some("userSpaceOnUse"),
+            stops,
+            href,
+        })
+    }
+}
+
+struct SvgParser {
+    gradients: HashMap<String, GradientDef>,
+}
+
+impl SvgParser {
+    fn parse_children(&self, parent: &roxmltree::Node, style: &Style, group: &mut SvgGroup) {
+        for node in parent.children().filter(|n| n.is_element()) {
+            let name = node.tag_name().name();
+            if matches!(name, "defs" | "linearGradient" | "radialGradient" | "title" | "desc" | "metadata" | "clipPath" | "mask") {
+                continue;
+            }
+            if node.attribute("display") == Some("none") {
+                continue;
+            }
+
+            let style = style.inherit(&node);
+            let transform = node.attribute("transform").map(parse_transform).unwrap_or(Affine2::identity());
+            let opacity = node.attribute("opacity").map(parse_opacity).unwrap_or(1.0);
+
+            if matches!(name, "g" | "svg" | "a") {
+                let mut child = SvgGroup::new(transform, opacity);
+                self.parse_children(&node, &style, &mut child);
+                group.children.push(SvgNode::Group(child));
+                continue;
+            }
+
+            let path = match shape_path(&node) {
+                Some(path) if !path.is_empty() => path,
+                _ => continue,
+            };
+            let shape = self.build_shape(path, &style, opacity);
+
+            if transform.is_identity() {
+                group.children.push(SvgNode::Shape(shape));
+            } else {
+                let mut child = SvgGroup::new(transform, 1.0);
+                child.children.push(SvgNode::Shape(shape));
+                group.children.push(SvgNode::Group(child));
+            }
+        }
+    }
+
+    fn build_shape(&self, path: Path, style: &Style, opacity: f32) -> SvgShape {
+        let bounds = path.bounds().unwrap_or(Rect::zero());
+        let fill = self.resolve_paint(&style.fill, &bounds);
+        let stroke = if style.stroke_style.width > 0.0 { self.resolve_paint(&style.stroke, &bounds) } else { None };
+        SvgShape {
+            path,
+            fill,
+            fill_rule: style.fill_rule,
+            stroke,
+            stroke_style: style.stroke_style.clone(),
+            fill_opacity: style.fill_opacity * opacity,
+            stroke_opacity: style.stroke_opacity * opacity,
+        }
+    }
+
+    fn resolve_paint(&self, paint: &PaintSpec, bounds: &Rect) -> Option<SvgPaint> {
+        match paint {
+            PaintSpec::None => None,
+            PaintSpec::Color(color) => Some(SvgPaint::Color(*color)),
+            PaintSpec::CurrentColor => Some(SvgPaint::CurrentColor),
+            PaintSpec::Url(id) => self.resolve_gradient(id, bounds),
+        }
+    }
+
+    fn resolve_gradient(&self, id: &str, bounds: &Rect) -> Option<SvgPaint> {
+        let def = self.gradients.get(id)?;
+
+        // Stops may come from a referenced gradient
+        let mut stops = &def.stops;
+        let mut href = def.href.as_deref();
+        let mut depth = 0;
+        while stops.is_empty() && depth < 8 {
+            let linked = self.gradients.get(href?)?;
+            stops = &linked.stops;
+            href = linked.href.as_deref();
+            depth += 1;
+        }
+        match stops.len() {
+            0 => return None,
+            1 => return Some(SvgPaint::Color(stops[0].1)),
+            _ => {},
+        }
+
+        let user_space = def.user_space;
+        let x = |c: Coord| c.resolve(user_space, bounds.x(), bounds.width());
+        let y = |c: Coord| c.resolve(user_space, bounds.y(), bounds.height());
+        let mut gradient = match def.geometry {
+            GradientGeometry::Linear { x1, y1, x2, y2 } => Gradient::linear(Vec2::new(x(x1), y(y1)), Vec2::new(x(x2), y(y2))),
+            GradientGeometry::Radial { cx, cy, r } => {
+                // Radii are relative to the box width in bounding box space
+                let radius = match (r, user_space) {
+                    (Coord::Number(n), true) => n / bounds.width().max(1e-6),
+                    (Coord::Number(n), false) | (Coord::Fraction(n), _) => n,
+                };
+                Gradient::radial(Vec2::new(x(cx), y(cy)), radius)
+            },
+        };
+        for (offset, color) in stops {
+            gradient.add_stop(*offset, *color);
+        }
+        Some(SvgPaint::Gradient(gradient))
+    }
+}
+
+fn shape_path(node: &roxmltree::Node) -> Option<Path> {
+    let number = |name: &str| node.attribute(name).and_then(parse_length).unwrap_or(0.0);
+    let mut path = Path::new();
+    match node.tag_name().name() {
+        "path" => parse_path_data(node.attribute("d")?, &mut path),
+        "rect" => {
+            let (x, y, w, h) = (number("x"), number("y"), number("width"), number("height"));
+            if w <= 0.0 || h <= 0.0 {
+                return None;
+            }
+            let rx = node.attribute("rx").and_then(parse_length);
+            let ry = node.attribute("ry").and_then(parse_length);
+            let rx = rx.or(ry).unwrap_or(0.0).min(w * 0.5);
+            let ry = ry.or(Some(rx)).unwrap_or(0.0).min(h * 0.5);
+            if rx > 0.0 && ry > 0.0 {
+                path.move_to(x + rx, y)
+                    .line_to(x + w - rx, y)
+                    .arc_to(rx, ry, 0.0, false, true, x + w, y + ry)
+                    .line_to(x + w, y + h - ry)
+                    .arc_to(rx, ry, 0.0, false, true, x + w - rx, y + h)
+                    .line_to(x + rx, y + h)
+                    .arc_to(rx, ry, 0.0, false, true, x, y + h - ry)
+                    .line_to(x, y + ry)
+                    .arc_to(rx, ry, 0.0, false, true, x + rx, y)
+                    .close();
+            } else {
+                path.rect(&Rect::new(x, y, w, h));
+            }
+        },
+        "circle" => {
+            let r = number("r");
+            if r <= 0.0 {
+                return None;
+            }
+            path.circle(number("cx"), number("cy"), r);
+        },
+        "ellipse" => {
+            let (cx, cy, rx, ry) = (number("cx"), number("cy"), number("rx"), number("ry"));
+            if rx <= 0.0 || ry <= 0.0 {
+                return None;
+            }
+            path.move_to(cx + rx, cy)
+                .arc_to(rx, ry, 0.0, false, true, cx - rx, cy)
+                .arc_to(rx, ry, 0.0, false, true, cx + rx, cy)
+                .close();
+        },
+        "line" => {
+            path.move_to(number("x1"), number("y1")).line_to(number("x2"), number("y2"));
+        },
+        "polyline" | "polygon" => {
+            let points = parse_numbers(node.attribute("points")?);
+            for (i, p) in points.chunks_exact(2).enumerate() {
+                if i == 0 {
+                    path.move_to(p[0], p[1]);
+                } else {
+                    path.line_to(p[0], p[1]);
+                }
+            }
+            if node.tag_name().name() == "polygon" {
+                path.close();
+            }
+        },
+        _ => return None,
+    }
+    Some(path)
+}
+
+// Reads SVG path data; stops at the first malformed segment like browsers do
+fn parse_path_data(data: &str, path: &mut Path) {
+    let mut lexer = Lexer::new(data);
+    let mut command = None;
+    let mut current = Vec2::zero();
+    let mut start = Vec2::zero();
+    // Reflected control point for S and T
+    let mut last_control: Option<(char, Vec2)> = None;
+
+    loop {
+        lexer.skip_separators();
+        if let Some(c) = lexer.peek_command() {
+            lexer.pos += 1;
+            command = Some(c);
+        } else if lexer.at_end() {
+            break;
+        }
+        let c = match command {
+            Some(c) => c,
+            None => break,
+        };
+        let relative = c.is_ascii_lowercase();
+        let base = if relative { current } else { Vec2::zero() };
+
+        let ok = (|| -> Option<()> {
+            match c.to_ascii_uppercase() {
+                'M' => {
+                    let p = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    path.move_to(p.x, p.y);
+                    current = p;
+                    start = p;
+                    // Further pairs are implicit line-tos
+                    command = Some(if relative { 'l' } else { 'L' });
+                    last_control = None;
+                },
+                'L' => {
+                    let p = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    path.line_to(p.x, p.y);
+                    current = p;
+                    last_control = None;
+                },
+                'H' => {
+                    let x = lexer.number()? + if relative { current.x } else { 0.0 };
+                    path.line_to(x, current.y);
+                    current.x = x;
+                    last_control = None;
+                },
+                'V' => {
+                    let y = lexer.number()? + if relative { current.y } else { 0.0 };
+                    path.line_to(current.x, y);
+                    current.y = y;
+                    last_control = None;
+                },
+                'C' => {
+                    let c1 = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    let c2 = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    let p = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    path.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+                    current = p;
+                    last_control = Some(('C', c2));
+                },
+                'S' => {
+                    let c1 = match last_control {
+                        Some(('C', c)) => current * 2.0 - c,
+                        _ => current,
+                    };
+                    let c2 = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    let p = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    path.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+                    current = p;
+                    last_control = Some(('C', c2));
+                },
+                'Q' => {
+                    let c1 = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    let p = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    path.quad_to(c1.x, c1.y, p.x, p.y);
+                    current = p;
+                    last_control = Some(('Q', c1));
+                },
+                'T' => {
+                    let c1 = match last_control {
+                        Some(('Q', c)) => current * 2.0 - c,
+                        _ => current,
+                    };
+                    let p = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    path.quad_to(c1.x, c1.y, p.x, p.y);
+                    current = p;
+                    last_control = Some(('Q', c1));
+                },
+                'A' => {
+                    let rx = lexer.number()?;
+                    let ry = lexer.number()?;
+                    let rotation = lexer.number()?.to_radians();
+                    let large_arc = lexer.flag()?;
+                    let sweep = lexer.flag()?;
+                    let p = base + Vec2::new(lexer.number()?, lexer.number()?);
+                    path.arc_to(rx, ry, rotation, large_arc, sweep, p.x, p.y);
+                    current = p;
+                    last_control = None;
+                },
+                'Z' => {
+                    path.close();
+                    current = start;
+                    command = None;
+                    last_control = None;
+                },
+                _ => return None,
+            }
+            Some(())
+        })();
+        if ok.is_none() {
+            break;
+        }
+    }
+}
+
+struct Lexer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { bytes: source.as_bytes(), pos: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn skip_separators(&mut self) {
+        while self.pos < self.bytes.len() && (self.bytes[self.pos].is_ascii_whitespace() || self.bytes[self.pos] == b',') {
+            self.pos += 1;
+        }
+    }
+
+    fn peek_command(&self) -> Option<char> {
+        let c = *self.bytes.get(self.pos)? as char;
+        (c.is_ascii_alphabetic() && c != 'e' && c != 'E').then_some(c)
+    }
+
+    // Arc flags may be written without separators, as in `a1 1 0 00 1 1`
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.bytes.get(self.pos)? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(flag)
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.pos;
+        let bytes = self.bytes;
+        if matches!(bytes.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut seen_dot = false;
+        while let Some(&b) = bytes.get(self.pos) {
+            if b.is_ascii_digit() {
+                self.pos += 1;
+            } else if b == b'.' && !seen_dot {
+                seen_dot = true;
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if matches!(bytes.get(self.pos), Some(b'e' | b'E')) {
+            let mark = self.pos;
+            self.pos += 1;
+            if matches!(bytes.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if bytes.get(self.pos).is_some_and(|b| b.is_ascii_digit()) {
+                while bytes.get(self.pos).is_some_and(|b| b.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+            } else {
+                self.pos = mark;
+            }
+        }
+        std::str::from_utf8(&bytes[start..self.pos]).ok()?.parse().ok()
+    }
+}
+
+fn parse_numbers(source: &str) -> Vec<f32> {
+    let mut lexer = Lexer::new(source);
+    let mut numbers = Vec::new();
+    while let Some(n) = lexer.number() {
+        numbers.push(n);
+    }
+    numbers
+}
+
+// Absolute lengths in user units; relative units other than % are not supported
+fn parse_length(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| c.is_ascii_alphabetic() || c == '%') {
+        Some(i) => value.split_at(i),
+        None => (value, ""),
+    };
+    let n: f32 = number.trim().parse().ok()?;
+    let scale = match unit {
+        "" | "px" => 1.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        _ => return None,
+    };
+    Some(n * scale)
+}
+
+fn parse_opacity(value: &str) -> f32 {
+    let value = value.trim();
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().map(|p| p / 100.0),
+        None => value.parse::<f32>(),
+    };
+    opacity.unwrap_or(1.0).clamp(0.0, 1.0)
+}
+
+fn parse_paint(value: &str) -> Option<PaintSpec> {
+    let value = value.trim();
+    match value {
+        "none" => return Some(PaintSpec::None),
+        "currentColor" => return Some(PaintSpec::CurrentColor),
+        _ => {},
+    }
+    if let Some(rest) = value.strip_prefix("url(") {
+        let id = rest.split(')').next()?.trim().trim_matches(|c| c == '\'' || c == '"');
+        return Some(PaintSpec::Url(id.trim_start_matches('#').to_string()));
+    }
+    Color::parse(value).map(PaintSpec::Color)
+}
+
+fn parse_transform(source: &str) -> Affine2 {
+    let mut transform = Affine2::identity();
+    for item in source.split(')') {
+        let (name, args) = match item.split_once('(') {
+            Some((name, args)) => (name.trim().trim_start_matches(',').trim(), parse_numbers(args)),
+            None => continue,
+        };
+        let arg = |i: usize| args.get(i).copied();
+        let next = match (name, args.len()) {
+            ("matrix", 6) => Affine2::new(args[0], args[1], args[2], args[3], args[4], args[5]),
+            ("translate", 1 | 2) => Affine2::translation(args[0], arg(1).unwrap_or(0.0)),
+            ("scale", 1 | 2) => Affine2::scale(args[0], arg(1).unwrap_or(args[0])),
+            ("rotate", 1) => Affine2::rotation(args[0].to_radians()),
+            ("rotate", 3) => Affine2::around(Vec2::new(args[1], args[2]), Affine2::rotation(args[0].to_radians())),
+            ("skewX", 1) => Affine2::new(1.0, 0.0, args[0].to_radians().tan(), 1.0, 0.0, 0.0),
+            ("skewY", 1) => Affine2::new(1.0, args[0].to_radians().tan(), 0.0, 1.0, 0.0, 0.0),
+            _ => continue,
+        };
+        transform = transform.multiply(&next);
+    }
+    transform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw::path::PathCommand;
+    use PathCommand::{MoveTo, LineTo, QuadTo, CubicTo, Close};
+
+    fn commands(data: &str) -> Vec<PathCommand> {
+        let mut path = Path::new();
+        parse_path_data(data, &mut path);
+        path.commands
+    }
+
+    fn v(x: f32, y: f32) -> Vec2 {
+        Vec2::new(x, y)
+    }
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!((actual - expected).length() < 1e-3, "{:?} != {:?}", actual, expected);
+    }
+
+    // End points of the cubics an arc was split into
+    fn arc_points(data: &str) -> Vec<Vec2> {
+        commands(data).iter().skip(1).map(|command| match command {
+            CubicTo(_, _, p) => *p,
+            other => panic!("expected a cubic, got {:?}", other),
+        }).collect()
+    }
+
+    #[test]
+    fn path_commands_absolute_and_relative_agree() {
+        let expected = vec![
+            MoveTo(v(10.0, 10.0)),
+            LineTo(v(20.0, 10.0)),
+            LineTo(v(30.0, 10.0)),
+            LineTo(v(30.0, 20.0)),
+            CubicTo(v(30.0, 30.0), v(20.0, 30.0), v(20.0, 20.0)),
+            // S and T reflect the previous control point
+            CubicTo(v(20.0, 10.0), v(10.0, 10.0), v(10.0, 20.0)),
+            QuadTo(v(5.0, 25.0), v(0.0, 20.0)),
+            QuadTo(v(-5.0, 15.0), v(0.0, 0.0)),
+            Close,
+        ];
+        assert_eq!(commands("M10 10 L20 10 H30 V20 C30 30 20 30 20 20 S10 10 10 20 Q5 25 0 20 T0 0 Z"), expected);
+        assert_eq!(commands("m10 10 l10 0 h10 v10 c0 10 -10 10 -10 0 s-10 -10 -10 0 q-5 5 -10 0 t0 -20 z"), expected);
+    }
+
+    #[test]
+    fn path_data_shorthands() {
+        // Extra pairs after a move are lines, relative after `m`
+        assert_eq!(commands("M0 0 10 0 10 10"), [MoveTo(v(0.0, 0.0)), LineTo(v(10.0, 0.0)), LineTo(v(10.0, 10.0))]);
+        assert_eq!(commands("m1 1 2 2"), [MoveTo(v(1.0, 1.0)), LineTo(v(3.0, 3.0))]);
+        // Relative moves after a close start from the closed subpath's start
+        assert_eq!(commands("M10 10 L20 10 Z m5 5"), [MoveTo(v(10.0, 10.0)), LineTo(v(20.0, 10.0)), Close, MoveTo(v(15.0, 15.0))]);
+        // Exponents, signs and dots separate numbers
+        assert_eq!(commands("M1e1-2.5L.5.5"), [MoveTo(v(10.0, -2.5)), LineTo(v(0.5, 0.5))]);
+        // S without a previous cubic uses the current point
+        assert_eq!(commands("M0 0 S10 10 20 0"), [MoveTo(v(0.0, 0.0)), CubicTo(v(0.0, 0.0), v(10.0, 10.0), v(20.0, 0.0))]);
+    }
+
+    #[test]
+    fn arcs_become_cubics_on_the_ellipse() {
+        // Half a circle about (10, 0), over the top
+        let points = arc_points("M0 0 A10 10 0 0 1 20 0");
+        assert_eq!(points.len(), 2);
+        assert_near(points[0], v(10.0, -10.0));
+        assert_near(points[1], v(20.0, 0.0));
+        // The other sweep goes under, and the relative form with packed flags matches
+        assert_near(arc_points("M0 0 A10 10 0 0 0 20 0")[0], v(10.0, 10.0));
+        assert_eq!(arc_points("M0 0 a10,10 0 0120,0"), points);
+
+        // Radii too small to reach are scaled up to a half circle
+        let points = arc_points("M0 0 A1 1 0 0 1 20 0");
+        assert_near(points[0], v(10.0, -10.0));
+
+        // The large arc about (0, 10) takes three quarters of the circle
+        let large = arc_points("M0 0 A10 10 0 1 0 10 10");
+        assert_eq!(large.len(), 3);
+        assert_near(large[2], v(10.0, 10.0));
+        let small = arc_points("M0 0 A10 10 0 0 0 10 10");
+        assert_eq!(small.len(), 1);
+
+        // A zero radius is a line
+        assert_eq!(commands("M0 0 A0 10 0 0 1 20 0"), [MoveTo(v(0.0, 0.0)), LineTo(v(20.0, 0.0))]);
+    }
+
+    #[test]
+    fn malformed_path_data_keeps_the_segments_before_it() {
+        assert_eq!(commands("M0 0 L10 0 L5 X 20 20"), [MoveTo(v(0.0, 0.0)), LineTo(v(10.0, 0.0))]);
+        assert_eq!(commands("M0 0 L10"), [MoveTo(v(0.0, 0.0))]);
+        assert_eq!(commands("M0 0 A10 10 0 2 1 20 0"), [MoveTo(v(0.0, 0.0))]);
+        assert_eq!(commands("10 10"), []);
+    }
+
+    #[test]
+    fn nested_group_transforms_and_opacity_are_baked_in() {
+        let scene = SvgScene::parse(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 20 20">
+                <g transform="translate(10 0)">
+                    <g transform="scale(2)" opacity="0.5">
+                        <rect width="5" height="5" transform="rotate(90)" opacity="0.5" stroke="red" stroke-width="1"/>
+                    </g>
+                </g>
+            </svg>
+        "#).unwrap();
+        assert_eq!(scene.view_box, Rect::new(0.0, 0.0, 20.0, 20.0));
+
+        let shapes = scene.shapes();
+        assert_eq!(shapes.len(), 1);
+        // Rotated to x -5..0, doubled, then moved right by 10
+        let bounds = shapes[0].path.bounds().unwrap();
+        assert_near(bounds.pos, v(0.0, 0.0));
+        assert_near(bounds.size, v(10.0, 10.0));
+        assert_eq!(shapes[0].fill_opacity, 0.25);
+        assert_eq!(shapes[0].stroke_style.width, 2.0);
+        assert_eq!(shapes[0].stroke, Some(SvgPaint::Color(Color::red())));
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        assert!(matches!(SvgScene::parse("<svg><g></svg>"), Err(SvgError::Xml(_))));
+        assert!(matches!(SvgScene::parse("<html/>"), Err(SvgError::NotSvg)));
+
+        // Unknown elements and broken shapes are skipped
+        let scene = SvgScene::parse(r#"<svg width="10mm" height="20"><foo/><circle r="-1"/><path d="L"/><line x2="5"/></svg>"#).unwrap();
+        assert_near(scene.view_box.size, v(96.0 / 2.54, 20.0));
+        assert_eq!(scene.shapes().len(), 1);
+    }
+}
@@ -0,0 +1,584 @@
+use crate::platform::math::Vec2;
+use crate::platform::geometry::{Geometry, VertexFormat};
+
+// A flattened subpath
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd,
+}
+
+impl FillRule {
+    fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineJoin {
+    // Falls back to a bevel past StrokeStyle::miter_limit
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+    // Alternating dash and gap lengths; empty for a solid line
+    pub dashes: Vec<f32>,
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn with_miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+
+    pub fn with_dashes(mut self, dashes: Vec<f32>, dash_offset: f32) -> Self {
+        self.dashes = dashes;
+        self.dash_offset = dash_offset;
+        self
+    }
+}
+
+// Triangle list ready for upload
+#[derive(Clone, Debug, Default)]
+pub struct Tessellation {
+    pub vertices: Vec<Vec2>,
+    pub indices: Vec<u32>,
+}
+
+impl Tessellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    pub fn push_triangle(&mut self, a: Vec2, b: Vec2, c: Vec2) {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&[a, b, c]);
+        self.indices.extend_from_slice(&[base, base + 1, base + 2]);
+    }
+
+    // Corners in order around the quad
+    pub fn push_quad(&mut self, a: Vec2, b: Vec2, c: Vec2, d: Vec2) {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&[a, b, c, d]);
+        self.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    pub fn append(&mut self, other: &Tessellation) {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices.extend(other.indices.iter().map(|i| i + base));
+    }
+
+    // Writes a single Float2 `position` attribute
+    pub fn write_geometry(&self, geometry: &mut Geometry) {
+        let mut bytes = Vec::with_capacity(self.vertices.len() * 8);
+        for v in &self.vertices {
+            bytes.extend_from_slice(&v.x.to_ne_bytes());
+            bytes.extend_from_slice(&v.y.to_ne_bytes());
+        }
+        geometry.vertex_attributes.clear();
+        geometry.add_vertex_attribute("position", 0, VertexFormat::Float2);
+        geometry.set_vertices(bytes);
+        geometry.set_indices(self.indices.clone());
+    }
+}
+
+struct Edge {
+    top: Vec2,
+    bottom: Vec2,
+    winding: i32,
+}
+
+impl Edge {
+    fn x_at(&self, y: f32) -> f32 {
+        let t = (y - self.top.y) / (self.bottom.y - self.top.y);
+        self.top.x + (self.bottom.x - self.top.x) * t
+    }
+}
+
+// Scanline trapezoidation. Every subpath is implicitly closed. The plane is cut
+// into horizontal bands at each vertex and each edge crossing, so within a band
+// no two edges swap order and the spans inside the fill rule are exact.
+pub fn tessellate_fill(polylines: &[Polyline], fill_rule: FillRule) -> Tessellation {
+    let mut edges = Vec::new();
+    for polyline in polylines {
+        let points = &polyline.points;
+        for i in 0..points.len() {
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+            if a.y == b.y {
+                continue;
+            }
+            edges.push(if a.y < b.y {
+                Edge { top: a, bottom: b, winding: 1 }
+            } else {
+                Edge { top: b, bottom: a, winding: -1 }
+            });
+        }
+    }
+
+    let mut tessellation = Tessellation::new();
+    if edges.is_empty() {
+        return tessellation;
+    }
+
+    let mut ys: Vec<f32> = edges.iter().flat_map(|e| [e.top.y, e.bottom.y]).collect();
+    edges.sort_by(|a, b| a.top.y.total_cmp(&b.top.y));
+    for i in 0..edges.len() {
+        for j in i + 1..edges.len() {
+            if edges[j].top.y >= edges[i].bottom.y {
+                break;
+            }
+            if let Some(y) = crossing_y(&edges[i], &edges[j]) {
+                ys.push(y);
+            }
+        }
+    }
+    ys.sort_by(|a, b| a.total_cmp(b));
+    ys.dedup_by(|a, b| (*a - *b).abs() < 1e-4);
+
+    let mut active: Vec<(f32, f32, f32, i32)> = Vec::new();
+    for band in ys.windows(2) {
+        let (y0, y1) = (band[0], band[1]);
+        let ym = (y0 + y1) * 0.5;
+
+        active.clear();
+        for edge in &edges {
+            if edge.top.y > ym {
+                break;
+            }
+            if edge.bottom.y > ym {
+                active.push((edge.x_at(ym), edge.x_at(y0), edge.x_at(y1), edge.winding));
+            }
+        }
+        active.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        let mut span_start = None;
+        for &(_, x0, x1, edge_winding) in &active {
+            let was_inside = fill_rule.is_inside(winding);
+            winding += edge_winding;
+            let is_inside = fill_rule.is_inside(winding);
+            if !was_inside && is_inside {
+                span_start = Some((x0, x1));
+            } else if was_inside && !is_inside {
+                if let Some((start0, start1)) = span_start.take() {
+                    tessellation.push_quad(
+                        Vec2::new(start0, y0),
+                        Vec2::new(x0, y0),
+                        Vec2::new(x1, y1),
+                        Vec2::new(start1, y1),
+                    );
+                }
+            }
+        }
+    }
+    tessellation
+}
+
+fn crossing_y(a: &Edge, b: &Edge) -> Option<f32> {
+    let d1 = a.bottom - a.top;
+    let d2 = b.bottom - b.top;
+    let denom = d1.cross(d2);
+    if denom.abs() < 1e-9 {
+        return None;
+    }
+    let t = (b.top - a.top).cross(d2) / denom;
+    let u = (b.top - a.top).cross(d1) / denom;
+    if t > 0.0 && t < 1.0 && u > 0.0 && u < 1.0 {
+        Some(a.top.y + d1.y * t)
+    } else {
+        None
+    }
+}
+
+// Segments are emitted as separate quads with joins and caps filling the gaps,
+// so overlapping parts of a translucent stroke blend twice.
+pub fn tessellate_stroke(polylines: &[Polyline], style: &StrokeStyle, tolerance: f32) -> Tessellation {
+    let mut tessellation = Tessellation::new();
+    if style.width <= 0.0 {
+        return tessellation;
+    }
+    let half = style.width * 0.5;
+
+    for polyline in polylines {
+        let mut points = polyline.points.clone();
+        points.dedup_by(|a, b| a.distance(*b) < 1e-6);
+        if polyline.closed && points.len() > 2 && points[0].distance(points[points.len() - 1]) < 1e-6 {
+            points.pop();
+        }
+        let polyline = Polyline { points, closed: polyline.closed };
+
+        for piece in dash_polyline(&polyline, &style.dashes, style.dash_offset) {
+            stroke_polyline(&mut tessellation, &piece, style, half, tolerance);
+        }
+    }
+    tessellation
+}
+
+fn stroke_polyline(tessellation: &mut Tessellation, polyline: &Polyline, style: &StrokeStyle, half: f32, tolerance: f32) {
+    let points = &polyline.points;
+    let closed = polyline.closed && points.len() > 2;
+    if points.len() < 2 {
+        // A zero-length subpath only shows its round or square caps
+        if let Some(&p) = points.first() {
+            match style.cap {
+                LineCap::Round => push_fan(tessellation, p, half, 0.0, std::f32::consts::TAU, tolerance),
+                LineCap::Square => tessellation.push_quad(
+                    p + Vec2::new(-half, -half),
+                    p + Vec2::new(half, -half),
+                    p + Vec2::new(half, half),
+                    p + Vec2::new(-half, half),
+                ),
+                LineCap::Butt => {},
+            }
+        }
+        return;
+    }
+
+    let segment_count = if closed { points.len() } else { points.len() - 1 };
+    for i in 0..segment_count {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let n = (b - a).normalize().perp() * half;
+        tessellation.push_quad(a + n, b + n, b - n, a - n);
+    }
+
+    let join_range = if closed { 0..points.len() } else { 1..points.len() - 1 };
+    for i in join_range {
+        let prev = points[(i + points.len() - 1) % points.len()];
+        let p = points[i];
+        let next = points[(i + 1) % points.len()];
+        push_join(tessellation, prev, p, next, style, half, tolerance);
+    }
+
+    if !closed {
+        let first_dir = (points[1] - points[0]).normalize();
+        let last_dir = (points[points.len() - 1] - points[points.len() - 2]).normalize();
+        push_cap(tessellation, points[0], -first_dir, style.cap, half, tolerance);
+        push_cap(tessellation, points[points.len() - 1], last_dir, style.cap, half, tolerance);
+    }
+}
+
+fn push_join(tessellation: &mut Tessellation, prev: Vec2, p: Vec2, next: Vec2, style: &StrokeStyle, half: f32, tolerance: f32) {
+    let d0 = (p - prev).normalize();
+    let d1 = (next - p).normalize();
+    let cross = d0.cross(d1);
+    if cross.abs() < 1e-6 && d0.dot(d1) > 0.0 {
+        return;
+    }
+
+    // The outer side of the turn, where the segment quads leave a gap
+    let side = if cross > 0.0 { -1.0 } else { 1.0 };
+    let n0 = d0.perp() * side;
+    let n1 = d1.perp() * side;
+    let a = p + n0 * half;
+    let b = p + n1 * half;
+
+    match style.join {
+        LineJoin::Bevel => tessellation.push_triangle(p, a, b),
+        LineJoin::Round => {
+            let start = n0.y.atan2(n0.x);
+            let mut sweep = n1.y.atan2(n1.x) - start;
+            if sweep > std::f32::consts::PI {
+                sweep -= std::f32::consts::TAU;
+            } else if sweep < -std::f32::consts::PI {
+                sweep += std::f32::consts::TAU;
+            }
+            push_fan(tessellation, p, half, start, sweep, tolerance);
+        },
+        LineJoin::Miter => {
+            let bisector = (n0 + n1).normalize();
+            let cos_half = bisector.dot(n0);
+            if cos_half > 1e-4 && 1.0 / cos_half <= style.miter_limit {
+                let m = p + bisector * (half / cos_half);
+                tessellation.push_triangle(p, a, m);
+                tessellation.push_triangle(p, m, b);
+            } else {
+                tessellation.push_triangle(p, a, b);
+            }
+        },
+    }
+}
+
+// `dir` points away from the line
+fn push_cap(tessellation: &mut Tessellation, p: Vec2, dir: Vec2, cap: LineCap, half: f32, tolerance: f32) {
+    let n = dir.perp() * half;
+    match cap {
+        LineCap::Butt => {},
+        LineCap::Square => {
+            let e = dir * half;
+            tessellation.push_quad(p + n, p + n + e, p - n + e, p - n);
+        },
+        LineCap::Round => {
+            let start = n.y.atan2(n.x);
+            push_fan(tessellation, p, half, start, -std::f32::consts::PI, tolerance);
+        },
+    }
+}
+
+fn push_fan(tessellation: &mut Tessellation, center: Vec2, radius: f32, start: f32, sweep: f32, tolerance: f32) {
+    let steps = arc_steps(radius, sweep, tolerance);
+    let mut prev = center + Vec2::new(start.cos(), start.sin()) * radius;
+    for i in 1..=steps {
+        let angle = start + sweep * i as f32 / steps as f32;
+        let next = center + Vec2::new(angle.cos(), angle.sin()) * radius;
+        tessellation.push_triangle(center, prev, next);
+        prev = next;
+    }
+}
+
+// Segments needed so the chord error of an arc stays under `tolerance`
+fn arc_steps(radius: f32, sweep: f32, tolerance: f32) -> usize {
+    let tolerance = tolerance.max(1e-3);
+    if radius <= tolerance {
+        return 1;
+    }
+    let step = 2.0 * (1.0 - tolerance / radius).acos();
+    ((sweep.abs() / step).ceil() as usize).clamp(1, 256)
+}
+
+// Splits a polyline into its dash pieces, measured along its length
+fn dash_polyline(polyline: &Polyline, dashes: &[f32], dash_offset: f32) -> Vec<Polyline> {
+    let total: f32 = dashes.iter().sum();
+    if dashes.is_empty() || total <= 0.0 || dashes.iter().any(|d| *d < 0.0) {
+        return vec![polyline.clone()];
+    }
+    // An odd pattern repeats twice so dashes and gaps alternate
+    let pattern: Vec<f32> = if dashes.len() % 2 == 1 {
+        dashes.iter().chain(dashes.iter()).copied().collect()
+    } else {
+        dashes.to_vec()
+    };
+    let period: f32 = pattern.iter().sum();
+
+    let mut index = 0;
+    let mut remaining = pattern[0];
+    let mut offset = dash_offset.rem_euclid(period);
+    while offset > 0.0 {
+        if offset >= remaining {
+            offset -= remaining;
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        } else {
+            remaining -= offset;
+            offset = 0.0;
+        }
+    }
+
+    let points = &polyline.points;
+    let mut pieces = Vec::new();
+    let mut current = Polyline::default();
+    if index % 2 == 0 {
+        if let Some(&p) = points.first() {
+            current.points.push(p);
+        }
+    }
+
+    let segment_count = if polyline.closed { points.len() } else { points.len().saturating_sub(1) };
+    for i in 0..segment_count {
+        let mut a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let mut length = a.distance(b);
+        while length > remaining {
+            let p = a.lerp(b, remaining / length);
+            if index % 2 == 0 {
+                current.points.push(p);
+                pieces.push(std::mem::take(&mut current));
+            } else {
+                current.points.push(p);
+            }
+            length -= remaining;
+            a = p;
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+        remaining -= length;
+        if index % 2 == 0 {
+            current.points.push(b);
+        }
+    }
+    if index % 2 == 0 && current.points.len() > 1 {
+        pieces.push(current);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(tessellation: &Tessellation) -> f32 {
+        tessellation.indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|i| tessellation.vertices[t[i] as usize]);
+                (b - a).cross(c - a).abs() * 0.5
+            })
+            .sum()
+    }
+
+    fn square(x: f32, y: f32, size: f32, clockwise: bool) -> Polyline {
+        let mut points = vec![
+            Vec2::new(x, y),
+            Vec2::new(x + size, y),
+            Vec2::new(x + size, y + size),
+            Vec2::new(x, y + size),
+        ];
+        if !clockwise {
+            points.reverse();
+        }
+        Polyline { points, closed: true }
+    }
+
+    fn line(points: &[(f32, f32)]) -> Polyline {
+        Polyline {
+            points: points.iter().map(|&(x, y)| Vec2::new(x, y)).collect(),
+            closed: false,
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32, epsilon: f32) {
+        assert!((actual - expected).abs() < epsilon, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn fill_rules_differ_for_nested_subpaths_with_the_same_winding() {
+        let same = [square(0.0, 0.0, 10.0, true), square(3.0, 3.0, 4.0, true)];
+        assert_close(area(&tessellate_fill(&same, FillRule::NonZero)), 100.0, 1e-3);
+        assert_close(area(&tessellate_fill(&same, FillRule::EvenOdd)), 84.0, 1e-3);
+
+        let opposite = [square(0.0, 0.0, 10.0, true), square(3.0, 3.0, 4.0, false)];
+        assert_close(area(&tessellate_fill(&opposite, FillRule::NonZero)), 84.0, 1e-3);
+        assert_close(area(&tessellate_fill(&opposite, FillRule::EvenOdd)), 84.0, 1e-3);
+    }
+
+    #[test]
+    fn fill_splits_self_intersecting_paths_at_crossings() {
+        // A bow tie: two triangles meeting at (5, 5)
+        let bow_tie = [line(&[(0.0, 0.0), (10.0, 10.0), (10.0, 0.0), (0.0, 10.0)])];
+        assert_close(area(&tessellate_fill(&bow_tie, FillRule::NonZero)), 50.0, 1e-3);
+    }
+
+    #[test]
+    fn caps_extend_open_strokes() {
+        let segment = [line(&[(0.0, 0.0), (10.0, 0.0)])];
+        let stroke = |cap| area(&tessellate_stroke(&segment, &StrokeStyle::new(2.0).with_cap(cap), 0.01));
+
+        assert_close(stroke(LineCap::Butt), 20.0, 1e-3);
+        assert_close(stroke(LineCap::Square), 24.0, 1e-3);
+        assert_close(stroke(LineCap::Round), 20.0 + std::f32::consts::PI, 0.05);
+    }
+
+    #[test]
+    fn joins_fill_the_outer_corner() {
+        let corner = [line(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)])];
+        let join_area = |style: StrokeStyle| area(&tessellate_stroke(&corner, &style, 0.01)) - 40.0;
+
+        assert_close(join_area(StrokeStyle::new(2.0).with_join(LineJoin::Bevel)), 0.5, 1e-3);
+        assert_close(join_area(StrokeStyle::new(2.0).with_join(LineJoin::Miter)), 1.0, 1e-3);
+        assert_close(join_area(StrokeStyle::new(2.0).with_join(LineJoin::Round)), std::f32::consts::FRAC_PI_4, 0.02);
+        // A right angle's miter is sqrt(2) half-widths long
+        assert_close(join_area(StrokeStyle::new(2.0).with_miter_limit(1.2)), 0.5, 1e-3);
+    }
+
+    #[test]
+    fn dashes_follow_the_pattern_and_offset() {
+        let segment = line(&[(0.0, 0.0), (10.0, 0.0)]);
+        let lengths = |dashes: &[f32], offset: f32| -> Vec<f32> {
+            dash_polyline(&segment, dashes, offset)
+                .iter()
+                .map(|piece| piece.points.windows(2).map(|w| w[0].distance(w[1])).sum())
+                .collect()
+        };
+
+        assert_eq!(lengths(&[2.0, 3.0], 0.0), vec![2.0, 2.0]);
+        assert_eq!(lengths(&[2.0, 3.0], 1.0), vec![1.0, 2.0, 1.0]);
+        // An odd pattern alternates which entries are dashes
+        assert_eq!(lengths(&[1.0], 0.0), vec![1.0; 5]);
+
+        let style = StrokeStyle::new(2.0).with_dashes(vec![2.0, 3.0], 1.0);
+        assert_close(area(&tessellate_stroke(&[segment], &style, 0.01)), 8.0, 1e-3);
+    }
+
+    #[test]
+    fn large_tessellations_keep_every_triangle() {
+        let mut tessellation = Tessellation::new();
+        for i in 0..20_000 {
+            let x = i as f32;
+            tessellation.push_quad(Vec2::new(x, 0.0), Vec2::new(x + 1.0, 0.0), Vec2::new(x + 1.0, 1.0), Vec2::new(x, 1.0));
+        }
+        let copy = tessellation.clone();
+        tessellation.append(&copy);
+
+        assert_eq!(tessellation.vertices.len(), 160_000);
+        assert_eq!(tessellation.indices.len(), 240_000);
+        assert_eq!(tessellation.indices.iter().max(), Some(&159_999));
+    }
+}
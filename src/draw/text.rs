@@ -1,32 +1,294 @@
 use crate::platform::math::Vec2;
-use crate::platform::geometry::{Geometry, GeometryId, VertexFormat};
-use crate::platform::shader::{Shader, ShaderId};
-use crate::platform::texture::Texture;
-use crate::platform::draw_list::{DrawItem, DrawUniform};
+use crate::platform::geometry::{GeometryId, VertexFormat};
+use crate::platform::shader::{ShaderId, ShaderUniformType};
+use crate::platform::texture::{Texture, TextureFormat, TextureRegion};
+use crate::platform::draw_list::DrawItem;
+use crate::platform::ContrastSample;
 use crate::platform::Cx;
+use crate::draw::cx_2d::Cx2d;
+use crate::draw::shader::ShaderBuilder;
 use crate::draw::color::Color;
 use crate::draw::rect::Rect;
+use crate::draw::quad::create_quad_geometry;
+use crate::draw::font_collection::{FontCollection, FontWeight, FontStyle, face_family_name, face_style};
 use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use ab_glyph_rasterizer::{Rasterizer, Point, point};
 use unicode_segmentation::UnicodeSegmentation;
 
-#[derive(Clone, Debug)]
+// Glyphs are rasterized once at this size and scaled when drawn
+const ATLAS_FONT_SIZE: f32 = 32.0;
+const ATLAS_SIZE: usize = 1024;
+// Empty pixels around each glyph so bilinear sampling doesn't bleed
+const ATLAS_PADDING: usize = 1;
+
+#[derive(Clone)]
 pub struct Font {
     pub name: String,
-    pub data: Vec<u8>,
-    pub font_info: Option<ttf_parser::Face<'static>>,
+    pub index: u32,
+    pub weight: FontWeight,
+    pub style: FontStyle,
     pub texture: Option<Texture>,
     pub glyphs: HashMap<char, GlyphInfo>,
+    // Where the next glyph goes in the atlas: x, y and the current row height
+    atlas_cursor: (usize, usize, usize),
+    // Parsed once and borrowing from `data`, see `from_shared`
+    face: Option<ttf_parser::Face<'static>>,
+    // The whole font file, shared with the other faces of a collection
+    data: Arc<[u8]>,
 }
 
 impl Default for Font {
     fn default() -> Self {
         Self {
             name: "default".to_string(),
-            data: Vec::new(),
-            font_info: None,
+            index: 0,
+            weight: FontWeight::NORMAL,
+            style: FontStyle::Normal,
             texture: None,
             glyphs: HashMap::new(),
+            atlas_cursor: (0, 0, 0),
+            face: None,
+            data: Arc::from(Vec::new()),
+        }
+    }
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Font")
+            .field("name", &self.name)
+            .field("index", &self.index)
+            .field("weight", &self.weight)
+            .field("style", &self.style)
+            .field("glyphs", &self.glyphs.len())
+            .finish()
+    }
+}
+
+impl Font {
+    pub fn from_data(data: Vec<u8>, index: u32) -> Option<Self> {
+        Self::from_shared(Arc::from(data), index)
+    }
+
+    // For fonts embedded with `include_bytes!`
+    pub fn from_static(data: &'static [u8], index: u32) -> Option<Self> {
+        Self::from_shared(Arc::from(data), index)
+    }
+
+    pub fn from_file(path: &Path, index: u32) -> Option<Self> {
+        let data = std::fs::read(path).ok()?;
+        Self::from_data(data, index)
+    }
+
+    // Face `index` of a font file, keeping a reference to the file so faces
+    // of one collection share it
+    pub fn from_shared(data: Arc<[u8]>, index: u32) -> Option<Self> {
+        let face = ttf_parser::Face::parse(&data, index).ok()?;
+        // SAFETY: the face borrows from the Arc's heap allocation, which
+        // doesn't move and lives as long as `data` in this Font or any clone
+        // of it. Both fields are private, so the face can't outlive it.
+        let face = unsafe { std::mem::transmute::<ttf_parser::Face<'_>, ttf_parser::Face<'static>>(face) };
+
+        Some(Self {
+            name: face_family_name(face.names())?,
+            index,
+            weight: FontWeight(face.weight().to_number()),
+            style: face_style(face.style(), face.italic_angle()),
+            face: Some(face),
+            data,
+            ..Default::default()
+        })
+    }
+
+    fn face(&self) -> Option<&ttf_parser::Face<'_>> {
+        self.face.as_ref()
+    }
+
+    // The font file this face was parsed from
+    pub fn data(&self) -> &Arc<[u8]> {
+        &self.data
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        if self.glyphs.contains_key(&c) {
+            return true;
+        }
+        self.face()
+            .and_then(|face| face.glyph_index(c))
+            .is_some_and(|glyph_id| glyph_id.0 != 0)
+    }
+
+    // Distance from the baseline to the top of the em box, scaled to font_size
+    pub fn ascender(&self, font_size: f32) -> f32 {
+        match self.face() {
+            Some(face) => face.ascender() as f32 * font_size / face.units_per_em() as f32,
+            None => font_size * 0.8,
+        }
+    }
+
+    // Distance from the baseline to the bottom of the em box, as a positive value
+    pub fn descender(&self, font_size: f32) -> f32 {
+        match self.face() {
+            Some(face) => -(face.descender() as f32) * font_size / face.units_per_em() as f32,
+            None => font_size * 0.2,
+        }
+    }
+
+    // Glyph metrics in the 32px space the atlas and layout code assume
+    pub fn glyph_info(&self, c: char) -> Option<GlyphInfo> {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return Some(glyph.clone());
+        }
+
+        let face = self.face()?;
+        let glyph_id = face.glyph_index(c).filter(|id| id.0 != 0)?;
+        let scale = ATLAS_FONT_SIZE / face.units_per_em() as f32;
+        let advance = face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32 * scale;
+        let (bearing, size) = match face.glyph_bounding_box(glyph_id) {
+            Some(bbox) => (
+                Vec2::new(bbox.x_min as f32 * scale, bbox.y_max as f32 * scale),
+                Vec2::new(bbox.width() as f32 * scale, bbox.height() as f32 * scale),
+            ),
+            None => (Vec2::zero(), Vec2::zero()),
+        };
+
+        Some(GlyphInfo {
+            code_point: c,
+            advance,
+            bearing,
+            size,
+            uv_rect: Rect::zero(),
+        })
+    }
+
+    // Puts the glyph into the font's atlas the first time it's needed.
+    // Returns false for glyphs with nothing to draw: whitespace, bitmap-only
+    // glyphs like color emoji, or ones that no longer fit in the atlas.
+    pub fn rasterize_glyph(&mut self, cx: &mut Cx, c: char) -> bool {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return glyph.uv_rect.width() > 0.0;
+        }
+        let Some(mut glyph) = self.glyph_info(c) else {
+            return false;
+        };
+
+        if let Some((width, height, coverage)) = self.rasterize_outline(c, &glyph) {
+            if let Some(region) = self.allocate_atlas(cx, width, height) {
+                let texture_id = self.texture.as_ref().map(|texture| texture.texture_id);
+                let uploaded = texture_id.is_some_and(|id| cx.update_texture_region(id, region, &coverage).is_ok());
+                if uploaded {
+                    // The bitmap is rounded up to whole pixels
+                    glyph.size = Vec2::new(width as f32, height as f32);
+                    glyph.uv_rect = Rect::new(
+                        region.x as f32 / ATLAS_SIZE as f32,
+                        region.y as f32 / ATLAS_SIZE as f32,
+                        width as f32 / ATLAS_SIZE as f32,
+                        height as f32 / ATLAS_SIZE as f32,
+                    );
+                }
+            }
+        }
+
+        let drawable = glyph.uv_rect.width() > 0.0;
+        self.glyphs.insert(c, glyph);
+        drawable
+    }
+
+    // Coverage bitmap of the glyph outline at the atlas size, with the
+    // glyph's top-left bearing at the origin
+    fn rasterize_outline(&self, c: char, glyph: &GlyphInfo) -> Option<(usize, usize, Vec<u8>)> {
+        let width = glyph.size.x.ceil() as usize;
+        let height = glyph.size.y.ceil() as usize;
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let face = self.face()?;
+        let glyph_id = face.glyph_index(c)?;
+        let mut outline = OutlineRasterizer {
+            rasterizer: Rasterizer::new(width, height),
+            scale: ATLAS_FONT_SIZE / face.units_per_em() as f32,
+            origin: glyph.bearing,
+            start: point(0.0, 0.0),
+            last: point(0.0, 0.0),
+        };
+        face.outline_glyph(glyph_id, &mut outline)?;
+
+        let mut coverage = vec![0u8; width * height];
+        outline.rasterizer.for_each_pixel(|index, alpha| {
+            coverage[index] = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+        });
+        Some((width, height, coverage))
+    }
+
+    // Packs glyphs in rows, creating the atlas texture on first use
+    fn allocate_atlas(&mut self, cx: &mut Cx, width: usize, height: usize) -> Option<TextureRegion> {
+        if self.texture.is_none() {
+            self.texture = Some(cx.create_texture(ATLAS_SIZE, ATLAS_SIZE, TextureFormat::R8));
         }
+
+        let (mut x, mut y, mut row_height) = self.atlas_cursor;
+        if x + width + ATLAS_PADDING > ATLAS_SIZE {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        if x + width + ATLAS_PADDING > ATLAS_SIZE || y + height + ATLAS_PADDING > ATLAS_SIZE {
+            return None;
+        }
+
+        self.atlas_cursor = (x + width + ATLAS_PADDING, y, row_height.max(height + ATLAS_PADDING));
+        Some(TextureRegion::new(x + ATLAS_PADDING, y + ATLAS_PADDING, width, height))
+    }
+}
+
+// Feeds a glyph outline in font units to the rasterizer in bitmap pixels
+struct OutlineRasterizer {
+    rasterizer: Rasterizer,
+    scale: f32,
+    origin: Vec2,
+    start: Point,
+    last: Point,
+}
+
+impl OutlineRasterizer {
+    fn to_pixels(&self, x: f32, y: f32) -> Point {
+        point(x * self.scale - self.origin.x, self.origin.y - y * self.scale)
+    }
+}
+
+impl ttf_parser::OutlineBuilder for OutlineRasterizer {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.to_pixels(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.to_pixels(x, y);
+        self.rasterizer.draw_line(self.last, p);
+        self.last = p;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p1, p) = (self.to_pixels(x1, y1), self.to_pixels(x, y));
+        self.rasterizer.draw_quad(self.last, p1, p);
+        self.last = p;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p1, p2, p) = (self.to_pixels(x1, y1), self.to_pixels(x2, y2), self.to_pixels(x, y));
+        self.rasterizer.draw_cubic(self.last, p1, p2, p);
+        self.last = p;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.rasterizer.draw_line(self.last, self.start);
+        }
+        self.last = self.start;
     }
 }
 
@@ -50,6 +312,8 @@ pub enum TextAlign {
 pub struct TextStyle {
     pub font_size: f32,
     pub font_name: String,
+    pub font_weight: FontWeight,
+    pub font_style: FontStyle,
     pub color: Color,
     pub align: TextAlign,
     pub line_height: f32,
@@ -60,6 +324,8 @@ impl Default for TextStyle {
         Self {
             font_size: 16.0,
             font_name: "default".to_string(),
+            font_weight: FontWeight::NORMAL,
+            font_style: FontStyle::Normal,
             color: Color::black(),
             align: TextAlign::Left,
             line_height: 1.2,
@@ -67,6 +333,21 @@ impl Default for TextStyle {
     }
 }
 
+// WCAG 2.1 AA minimum contrast ratios
+pub const MIN_TEXT_CONTRAST: f32 = 4.5;
+pub const MIN_LARGE_TEXT_CONTRAST: f32 = 3.0;
+
+impl TextStyle {
+    // Large text is 18pt (24px), or 14pt (18.66px) when bold
+    pub fn is_large_text(&self) -> bool {
+        self.font_size >= 24.0 || (self.font_size >= 18.66 && self.font_weight >= FontWeight::BOLD)
+    }
+
+    pub fn min_contrast(&self) -> f32 {
+        if self.is_large_text() { MIN_LARGE_TEXT_CONTRAST } else { MIN_TEXT_CONTRAST }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DrawText {
     pub text: String,
@@ -111,6 +392,16 @@ impl DrawText {
         self
     }
 
+    pub fn with_font_weight(mut self, font_weight: FontWeight) -> Self {
+        self.style.font_weight = font_weight;
+        self
+    }
+
+    pub fn with_font_style(mut self, font_style: FontStyle) -> Self {
+        self.style.font_style = font_style;
+        self
+    }
+
     pub fn with_color(mut self, color: Color) -> Self {
         self.style.color = color;
         self
@@ -122,65 +413,26 @@ impl DrawText {
     }
 
     pub fn init(&mut self, cx: &mut Cx) {
-        if self.shader_id.is_none() {
-            let shader_id = cx.create_shader();
-            let mut shader = Shader::new(shader_id);
-
-            shader.set_vertex_shader(TEXT_VERTEX_SHADER);
-            shader.set_fragment_shader(TEXT_FRAGMENT_SHADER);
-
-            shader.add_uniform("color", crate::platform::shader::ShaderUniformType::Vec4);
-            shader.add_uniform("font_size", crate::platform::shader::ShaderUniformType::Float);
-
-            cx.shaders.insert(shader_id, shader);
-            self.shader_id = Some(shader_id);
+        if self.geometry_id.is_none() {
+            self.geometry_id = Some(create_quad_geometry(cx));
         }
 
-        if self.geometry_id.is_none() {
-            let geometry_id = cx.create_geometry();
-            let mut geometry = Geometry::new(geometry_id);
-
-            // Define quad vertices for text rendering
-            let vertices: Vec<f32> = vec![
-                0.0, 0.0,  // Position
-                0.0, 0.0,  // UV
-
-                1.0, 0.0,  // Position
-                1.0, 0.0,  // UV
-
-                1.0, 1.0,  // Position
-                1.0, 1.0,  // UV
-
-                0.0, 1.0,  // Position
-                0.0, 1.0,  // UV
-            ];
-
-            // Convert to bytes
-            let vertices_bytes: Vec<u8> = unsafe {
-                std::slice::from_raw_parts(
-                    vertices.as_ptr() as *const u8,
-                    vertices.len() * std::mem::size_of::<f32>(),
-                ).to_vec()
-            };
-
-            // Define indices
-            let indices: Vec<u16> = vec![
-                0, 1, 2,  // First triangle
-                0, 2, 3,  // Second triangle
-            ];
-
-            geometry.add_vertex_attribute("position", 0, VertexFormat::Float2);
-            geometry.add_vertex_attribute("uv", 8, VertexFormat::Float2);
-
-            geometry.set_vertices(vertices_bytes);
-            geometry.set_indices(indices);
-
-            cx.geometries.insert(geometry_id, geometry);
-            self.geometry_id = Some(geometry_id);
+        if self.shader_id.is_none() {
+            let shader_id = ShaderBuilder::new()
+                .vertex_shader(TEXT_VERTEX_SHADER)
+                .fragment_shader(TEXT_FRAGMENT_SHADER)
+                .uniform("font_texture", ShaderUniformType::Texture2D)
+                .instance("glyph_rect", VertexFormat::Float4)
+                .instance("uv_rect", VertexFormat::Float4)
+                .instance("color", VertexFormat::Float4)
+                .geometry(self.geometry_id.unwrap())
+                .build(cx)
+                .expect("DrawText shader failed validation");
+            self.shader_id = Some(shader_id);
         }
     }
 
-    pub fn measure_text(&self, font: &Font) -> Vec2 {
+    pub fn measure_text(&self, fonts: &FontCollection) -> Vec2 {
         let mut width: f32 = 0.0;
         let mut height = self.style.font_size * self.style.line_height;
         let scale_factor = self.style.font_size / 32.0; // Assuming font metrics are based on 32px
@@ -196,10 +448,10 @@ impl DrawText {
             }
 
             let c = grapheme.chars().next().unwrap_or(' ');
-            if let Some(glyph) = font.glyphs.get(&c) {
+            if let Some((_, glyph)) = self.resolve_glyph(fonts, c) {
                 line_width += glyph.advance * scale_factor;
             } else {
-                // Use a default advance for unknown characters
+                // Use a default advance for characters no font in the chain covers
                 line_width += self.style.font_size * 0.5;
             }
         }
@@ -209,21 +461,41 @@ impl DrawText {
         Vec2::new(width, height)
     }
 
-    pub fn draw(&mut self, cx: &mut Cx, draw_list_id: crate::platform::draw_list::DrawListId, rect: &Rect, font: &Font) {
+    // Walks the style's fallback chain for the first font that has the glyph
+    fn resolve_glyph<'f>(&self, fonts: &'f FontCollection, c: char) -> Option<(&'f Font, GlyphInfo)> {
+        let font = fonts.font_for_char(&self.style, c)?;
+        font.glyph_info(c).map(|glyph| (font, glyph))
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx2d, draw_list_id: crate::platform::draw_list::DrawListId, rect: &Rect, fonts: &mut FontCollection) {
         if self.shader_id.is_none() || self.geometry_id.is_none() {
             self.init(cx);
         }
 
-        if font.texture.is_none() {
-            return;
+        fonts.prepare_glyphs(cx, &self.style, &self.text);
+        let fonts = &*fonts;
+
+        if cx.debug.contrast_overlay {
+            let window_rect = cx.transformed_rect(rect);
+            cx.debug.record_contrast_sample(draw_list_id, ContrastSample::Text {
+                rect: (window_rect.x(), window_rect.y(), window_rect.width(), window_rect.height()),
+                color: self.style.color.to_array(),
+                min_contrast: self.style.min_contrast(),
+            });
         }
 
         let scale_factor = self.style.font_size / 32.0; // Assuming font metrics are based on 32px
         let mut x = rect.pos.x;
-        let mut y = rect.pos.y;
+
+        // Glyphs hang off the baseline, so the first one sits an ascent below the rect top
+        let ascent = match fonts.primary_font(&self.style) {
+            Some(font) => font.ascender(self.style.font_size),
+            None => self.style.font_size * 0.8,
+        };
+        let mut y = rect.pos.y + ascent;
 
         // Adjust starting position based on text alignment
-        let text_size = self.measure_text(font);
+        let text_size = self.measure_text(fonts);
         match self.style.align {
             TextAlign::Left => {},
             TextAlign::Center => {
@@ -237,7 +509,8 @@ impl DrawText {
         let mut _line_width: f32 = 0.0;
         let line_start_x = x;
 
-        for grapheme in self.text.graphemes(true) {
+        let text = self.text.clone();
+        for grapheme in text.graphemes(true) {
             if grapheme == "\n" {
                 // Move to the next line
                 y += self.style.font_size * self.style.line_height;
@@ -261,41 +534,74 @@ impl DrawText {
             }
 
             let c = grapheme.chars().next().unwrap_or(' ');
-            if let Some(glyph) = font.glyphs.get(&c) {
-                // Calculate glyph position and size
-                let _glyph_pos = Vec2::new(
-                    x + glyph.bearing.x * scale_factor,
-                    y - glyph.bearing.y * scale_factor
-                );
-                let _glyph_size = Vec2::new(
-                    glyph.size.x * scale_factor,
-                    glyph.size.y * scale_factor
-                );
-
-                let draw_item = DrawItem {
-                    shader_id: self.shader_id.unwrap(),
-                    geometry_id: self.geometry_id.unwrap(),
-                    uniforms: vec![
-                        DrawUniform::Vec4(self.style.color.to_array()),
-                        DrawUniform::Float(self.style.font_size),
-                    ],
-                    textures: vec![font.texture.clone().unwrap()],
-                    instance_count: 1,
-                };
-
-                if let Some(draw_list) = cx.draw_lists.get_mut(&draw_list_id) {
-                    draw_list.add_draw_item(draw_item);
+            if let Some((font, glyph)) = self.resolve_glyph(fonts, c) {
+                // Glyphs that aren't in an atlas still take up space
+                if let (Some(texture), true) = (&font.texture, glyph.uv_rect.width() > 0.0) {
+                    // Calculate glyph position and size
+                    let glyph_pos = Vec2::new(
+                        x + glyph.bearing.x * scale_factor,
+                        y - glyph.bearing.y * scale_factor
+                    );
+                    let glyph_size = Vec2::new(
+                        glyph.size.x * scale_factor,
+                        glyph.size.y * scale_factor
+                    );
+
+                    let color = self.style.color;
+                    self.draw_glyph(cx, draw_list_id, glyph_pos, glyph_size, &glyph.uv_rect, color, texture);
                 }
 
                 x += glyph.advance * scale_factor;
                 _line_width += glyph.advance * scale_factor;
             } else {
-                // Skip unknown characters
+                // Skip characters no font in the chain covers
                 x += self.style.font_size * 0.5;
                 _line_width += self.style.font_size * 0.5;
             }
         }
     }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw_glyph(
+        &mut self,
+        cx: &mut Cx2d,
+        draw_list_id: crate::platform::draw_list::DrawListId,
+        glyph_pos: Vec2,
+        glyph_size: Vec2,
+        uv_rect: &Rect,
+        color: Color,
+        texture: &Texture,
+    ) {
+        if self.shader_id.is_none() || self.geometry_id.is_none() {
+            self.init(cx);
+        }
+
+        if let Some(clip_rect) = cx.clip_rect() {
+            if !clip_rect.intersects(&cx.transformed_rect(&Rect::from_pos_size(glyph_pos, glyph_size))) {
+                return;
+            }
+        }
+
+        let draw_item = DrawItem {
+            shader_id: self.shader_id.unwrap(),
+            geometry_id: self.geometry_id.unwrap(),
+            uniforms: Vec::new(),
+            textures: vec![texture.clone()],
+            instances: vec![
+                glyph_pos.x, glyph_pos.y, glyph_size.x, glyph_size.y,
+                uv_rect.x(), uv_rect.y(), uv_rect.width(), uv_rect.height(),
+                color.r, color.g, color.b, color.a,
+            ],
+            instance_count: 1,
+            clip: cx.current_clip(),
+            transform: cx.current_transform(),
+            blend: cx.current_blend(),
+            sub_list: None,
+        };
+
+        // Consecutive glyphs sharing an atlas collapse into one instanced item
+        cx.append_draw_item(draw_list_id, draw_item);
+    }
 }
 
 const TEXT_VERTEX_SHADER: &str = r#"
@@ -305,14 +611,25 @@ precision highp float;
 in vec2 position;
 in vec2 uv;
 
-uniform float font_size;
+// Per instance
+in vec4 glyph_rect;
+in vec4 uv_rect;
+in vec4 color;
+
 uniform mat4 view_transform;
+uniform mat4 model_transform;
 
 out vec2 v_uv;
+out vec2 v_world;
+flat out vec4 v_color;
 
 void main() {
-    v_uv = uv;
-    gl_Position = view_transform * vec4(position * font_size, 0.0, 1.0);
+    v_uv = uv_rect.xy + uv * uv_rect.zw;
+    v_color = color;
+    vec2 pos = glyph_rect.xy + position * glyph_rect.zw;
+    vec4 world = model_transform * vec4(pos, 0.0, 1.0);
+    v_world = world.xy;
+    gl_Position = view_transform * world;
 }
 "#;
 
@@ -321,17 +638,79 @@ const TEXT_FRAGMENT_SHADER: &str = r#"
 precision highp float;
 
 in vec2 v_uv;
+in vec2 v_world;
+flat in vec4 v_color;
 
-uniform vec4 color;
 uniform sampler2D font_texture;
 
+// Supplied by the backend from DrawItem::clip
+uniform vec4 clip_rect;
+uniform float clip_radius;
+
 out vec4 frag_color;
 
+float rounded_box(vec2 p, vec2 b, float r) {
+    return length(max(abs(p) - b + r, 0.0)) - r;
+}
+
 void main() {
+    vec2 clip_half = clip_rect.zw * 0.5;
+    if (clip_radius > 0.0 && rounded_box(v_world - clip_rect.xy - clip_half, clip_half, clip_radius) > 0.0) {
+        discard;
+    }
+
     float alpha = texture(font_texture, v_uv).r;
-    frag_color = vec4(color.rgb, color.a * alpha);
+    frag_color = vec4(v_color.rgb, v_color.a * alpha);
 }
 "#;
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::math::Affine2;
+    use crate::draw::layout::Layout;
+
+    // A font with one pre-rasterized glyph and no face, so the ascent falls
+    // back to 0.8 of the font size
+    fn test_fonts(cx: &mut Cx) -> FontCollection {
+        let mut font = Font {
+            name: "Test".to_string(),
+            texture: Some(cx.create_texture(64, 64, TextureFormat::R8)),
+            ..Default::default()
+        };
+        font.glyphs.insert('A', GlyphInfo {
+            code_point: 'A',
+            advance: 20.0,
+            bearing: Vec2::new(2.0, 24.0),
+            size: Vec2::new(16.0, 24.0),
+            uv_rect: Rect::new(0.0, 0.0, 0.25, 0.375),
+        });
+
+        let mut fonts = FontCollection::new();
+        fonts.add_font(font);
+        fonts
+    }
 
-
+    #[test]
+    fn glyph_instance_sits_on_the_first_baseline_of_the_turtle_rect() {
+        let mut cx = Cx::new();
+        let draw_list_id = cx.create_draw_list();
+        let mut fonts = test_fonts(&mut cx);
+        let mut cx2d = Cx2d::new(&mut cx);
+        let mut text = DrawText::new().with_text("A").with_font_name("Test").with_font_size(16.0);
+
+        cx2d.push_transform(Affine2::scale(2.0, 2.0));
+        cx2d.begin_sized_turtle(Vec2::new(200.0, 100.0), Layout::vertical().with_padding(Vec2::new(10.0, 5.0)));
+        let rect = cx2d.add_turtle_item(Vec2::new(100.0, 20.0)).unwrap();
+        text.draw(&mut cx2d, draw_list_id, &rect, &mut fonts);
+        cx2d.end_turtle();
+        drop(cx2d);
+
+        // Scaled from the 32px atlas space to 16px, below an ascent of 12.8
+        let item = &cx.draw_lists[&draw_list_id].draw_items[0];
+        let instance = item.instance(0);
+        assert_eq!(&instance[0..4], &[10.0 + 1.0, 5.0 + 12.8 - 12.0, 8.0, 12.0]);
+        assert_eq!(&instance[4..8], &[0.0, 0.0, 0.25, 0.375]);
+        assert_eq!(item.transform, Affine2::scale(2.0, 2.0));
+    }
+}
@@ -1,5 +1,9 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use crate::platform::math::Vec2;
-use crate::draw::layout::{Layout, LayoutDirection, LayoutAlign};
+use crate::platform::area::Area;
+use crate::platform::draw_list::DrawListId;
+use crate::draw::layout::{Layout, LayoutDirection, LayoutAlign, LayoutSize};
 use crate::draw::rect::Rect;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -25,17 +29,38 @@ impl Walk {
     }
 }
 
+// The draw list open when a turtle tree's root turtle began, usually its
+// window's, and the turtle's position among those begun under that list this
+// frame. Stable across frames and distinct between windows.
+pub type LayoutId = (Option<DrawListId>, usize);
+
 #[derive(Clone, Debug)]
 pub struct Turtle {
     pub rect: Rect,
     pub layout: Layout,
     pub items: Vec<TurtleItem>,
+    // Keys the rects remembered from the last frame
+    pub layout_id: LayoutId,
+    // The item this turtle lays out inside, in the enclosing turtle
+    pub parent_item: Option<usize>,
 }
 
 #[derive(Clone, Debug)]
 pub struct TurtleItem {
+    // Where the item was placed when added; `end_turtle` moves it to its
+    // final rect
     pub rect: Rect,
     pub align_self: Option<LayoutAlign>,
+    pub width: LayoutSize,
+    pub height: LayoutSize,
+    pub content_size: Vec2,
+    // What the rect was resolved from, see `Turtle::layout_key`
+    pub layout_key: u64,
+    // Areas and draw lists updated with the item's rect, moved with it
+    pub bindings: Vec<(Area, DrawListId)>,
+    // Those of the items of a turtle nested in this one, which move with it
+    // but keep their own rects
+    pub nested: Vec<(Area, DrawListId)>,
 }
 
 impl Turtle {
@@ -44,55 +69,156 @@ impl Turtle {
             rect,
             layout,
             items: Vec::new(),
+            layout_id: (None, 0),
+            parent_item: None,
         }
     }
 
+    // An item sized to its content
     pub fn add_item(&mut self, size: Vec2, align_self: Option<LayoutAlign>) -> Rect {
-        let rect: Rect;
+        self.add_sized_item(LayoutSize::Fit, LayoutSize::Fit, size, align_self, None)
+    }
 
-        match self.layout.direction {
-            LayoutDirection::Horizontal => {
-                let x = if self.items.is_empty() {
-                    self.rect.pos.x + self.layout.padding.x
-                } else {
-                    let last_item = self.items.last().unwrap();
-                    last_item.rect.right() + self.layout.spacing
-                };
-
-                let align = align_self.unwrap_or(self.layout.align_items);
-                let y = self.rect.pos.y + self.layout.padding.y + align.resolve(
-                    self.rect.height() - self.layout.padding.y * 2.0,
-                    size.y
-                );
-
-                rect = Rect::new(x, y, size.x, size.y);
-            },
-            LayoutDirection::Vertical => {
-                let y = if self.items.is_empty() {
-                    self.rect.pos.y + self.layout.padding.y
-                } else {
-                    let last_item = self.items.last().unwrap();
-                    last_item.rect.bottom() + self.layout.spacing
-                };
-
-                let align = align_self.unwrap_or(self.layout.align_items);
-                let x = self.rect.pos.x + self.layout.padding.x + align.resolve(
-                    self.rect.width() - self.layout.padding.x * 2.0,
-                    size.x
-                );
-
-                rect = Rect::new(x, y, size.x, size.y);
-            },
-        }
+    // Places the item after the previous ones, or at `previous`, its final
+    // rect last frame. Flex sizes and `justify_content` depend on the items
+    // still to come, so the rect is provisional until `layout_items`.
+    pub fn add_sized_item(
+        &mut self,
+        width: LayoutSize,
+        height: LayoutSize,
+        content_size: Vec2,
+        align_self: Option<LayoutAlign>,
+        previous: Option<Rect>,
+    ) -> Rect {
+        let rect = previous.unwrap_or_else(|| {
+            let size = Vec2::new(
+                width.resolve(self.inner_size().x, content_size.x),
+                height.resolve(self.inner_size().y, content_size.y),
+            );
+            let align = align_self.unwrap_or(self.layout.align_items);
+            match self.layout.direction {
+                LayoutDirection::Horizontal => {
+                    let x = match self.items.last() {
+                        Some(last) => last.rect.right() + self.layout.spacing,
+                        None => self.rect.pos.x + self.layout.padding.x,
+                    };
+                    let y = self.rect.pos.y + self.layout.padding.y + align.resolve(self.inner_size().y, size.y);
+                    Rect::new(x, y, size.x, size.y)
+                },
+                LayoutDirection::Vertical => {
+                    let y = match self.items.last() {
+                        Some(last) => last.rect.bottom() + self.layout.spacing,
+                        None => self.rect.pos.y + self.layout.padding.y,
+                    };
+                    let x = self.rect.pos.x + self.layout.padding.x + align.resolve(self.inner_size().x, size.x);
+                    Rect::new(x, y, size.x, size.y)
+                },
+            }
+        });
 
         self.items.push(TurtleItem {
             rect,
             align_self,
+            width,
+            height,
+            content_size,
+            layout_key: self.layout_key(width, height, content_size),
+            bindings: Vec::new(),
+            nested: Vec::new(),
         });
 
         rect
     }
 
+    // Identifies the inputs an item's size is resolved from, so a rect
+    // remembered from another frame is only reused while they hold
+    pub fn layout_key(&self, width: LayoutSize, height: LayoutSize, content_size: Vec2) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for size in [width, height] {
+            let (kind, value) = match size {
+                LayoutSize::Fixed(value) => (0u8, value),
+                LayoutSize::Flex(factor) => (1, factor),
+                LayoutSize::Fill => (2, 0.0),
+                LayoutSize::Fit => (3, 0.0),
+            };
+            kind.hash(&mut hasher);
+            value.to_bits().hash(&mut hasher);
+        }
+        let inner = self.inner_size();
+        for value in [content_size.x, content_size.y, inner.x, inner.y] {
+            value.to_bits().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    pub fn inner_size(&self) -> Vec2 {
+        Vec2::new(
+            (self.rect.width() - self.layout.padding.x * 2.0).max(0.0),
+            (self.rect.height() - self.layout.padding.y * 2.0).max(0.0),
+        )
+    }
+
+    // The flexbox pass: fixed and fit items are measured first, the space
+    // left along the direction is split between Flex and Fill items by
+    // factor, then `justify_content` places them and `align_items` places
+    // or stretches them across. A turtle without a size of its own along an
+    // axis has no space to share, so items keep their content size there.
+    pub fn layout_items(&self) -> Vec<Rect> {
+        let horizontal = self.layout.direction == LayoutDirection::Horizontal;
+        let inner = self.inner_size();
+        let (inner_main, inner_cross) = if horizontal { (inner.x, inner.y) } else { (inner.y, inner.x) };
+        let main_spec = |item: &TurtleItem| if horizontal { item.width } else { item.height };
+        let cross_spec = |item: &TurtleItem| if horizontal { item.height } else { item.width };
+        let main_content = |item: &TurtleItem| if horizontal { item.content_size.x } else { item.content_size.y };
+        let cross_content = |item: &TurtleItem| if horizontal { item.content_size.y } else { item.content_size.x };
+
+        let count = self.items.len();
+        let spacing = self.layout.spacing * count.saturating_sub(1) as f32;
+        let mut sizes: Vec<f32> = self.items.iter().map(|item| match main_spec(item) {
+            LayoutSize::Fixed(size) => size,
+            _ => main_content(item),
+        }).collect();
+
+        let total_factor: f32 = self.items.iter().filter_map(|item| main_spec(item).flex_factor()).sum();
+        if inner_main > 0.0 && total_factor > 0.0 {
+            let measured: f32 = self.items.iter().zip(&sizes)
+                .filter(|(item, _)| main_spec(item).flex_factor().is_none())
+                .map(|(_, size)| size)
+                .sum();
+            let remaining = (inner_main - measured - spacing).max(0.0);
+            for (item, size) in self.items.iter().zip(sizes.iter_mut()) {
+                if let Some(factor) = main_spec(item).flex_factor() {
+                    *size = remaining * factor / total_factor;
+                }
+            }
+        }
+
+        let used: f32 = sizes.iter().sum::<f32>() + spacing;
+        let free = if inner_main > 0.0 { inner_main - used } else { 0.0 };
+        let (offset, extra_gap) = self.layout.justify_content.resolve(free, count);
+
+        let origin = self.rect.pos + self.layout.padding;
+        let mut main = offset;
+        self.items.iter().zip(sizes).map(|(item, main_size)| {
+            let align = item.align_self.unwrap_or(self.layout.align_items);
+            let cross_size = match cross_spec(item) {
+                LayoutSize::Fit if align == LayoutAlign::Stretch && inner_cross > 0.0 => inner_cross,
+                spec if inner_cross > 0.0 => spec.resolve(inner_cross, cross_content(item)),
+                LayoutSize::Fixed(size) => size,
+                _ => cross_content(item),
+            };
+            let cross = if inner_cross > 0.0 { align.resolve(inner_cross, cross_size) } else { 0.0 };
+
+            let rect = if horizontal {
+                Rect::new(origin.x + main, origin.y + cross, main_size, cross_size)
+            } else {
+                Rect::new(origin.x + cross, origin.y + main, cross_size, main_size)
+            };
+            main += main_size + self.layout.spacing + extra_gap;
+            rect
+        }).collect()
+    }
+
     pub fn compute_content_size(&self) -> Vec2 {
         if self.items.is_empty() {
             return Vec2::zero();
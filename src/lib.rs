@@ -3,14 +3,18 @@
 
 // Re-export platform modules
 pub mod platform;
-pub use platform::{Cx, Event, EventHandler, Area, PassId, Pass, PassClearColor, PassClearDepth,
-                   DrawListId, DrawList, Texture, TextureFormat, Geometry, GeometryId,
-                   math::{Vec2, Vec3, Vec4, Mat4}};
+pub use platform::{Cx, Event, EventHandler, Area, PassId, Pass, PassParent, PassGraph, PassError, PassClearColor, PassClearDepth,
+                   DrawListId, DrawList, BlendMode, Shader, ShaderId, ShaderError, Texture, TextureFormat, TextureRegion, TextureError, Image, Geometry, GeometryId,
+                   math::{Vec2, Vec3, Vec4, Mat4, Affine2}};
 
 // Re-export draw modules
 pub mod draw;
-pub use draw::{Cx2d, DrawList2d, color::Color, layout::{Layout, LayoutAlign, LayoutDirection},
-               text::{TextAlign, Font, DrawText}, turtle::{Turtle, Walk}, rect::Rect, quad::DrawQuad};
+pub use draw::{Cx2d, DrawList2d, color::Color, layout::{Layout, LayoutAlign, LayoutDirection, LayoutSize, JustifyContent},
+               text::{TextAlign, Font, DrawText}, turtle::{Turtle, Walk}, rect::Rect, quad::{DrawQuad, CornerRadii, BorderWidths, BorderStyle},
+               gradient::{Gradient, GradientKind, GradientStop}, shadow::DrawShadow, backdrop_blur::DrawBackdropBlur,
+               path::{Path, DrawPath}, svg::{SvgScene, DrawSvg}, image::{DrawImage, ImageFit, NineSlice}, shader::DrawShader, tessellator::{FillRule, LineJoin, LineCap, StrokeStyle},
+               font_collection::{FontCollection, FontWeight, FontStyle},
+               rich_text::{RichText, TextSpan, SpanStyle}};
 
 // Re-export widgets modules
 pub mod widgets;
@@ -20,7 +24,7 @@ pub use widgets::button::Button;
 pub use widgets::label::Label;
 pub use widgets::window::Window;
 pub use widgets::app::{AppMain, App};
-pub use widgets::theme::Theme;
+pub use widgets::theme::{Theme, ContrastViolation};
 
 // Re-export the app_main macro
 #[macro_export]
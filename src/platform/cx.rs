@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::platform::window::{WindowId, WindowHandle};
 use crate::platform::area::Area;
-use crate::platform::pass::{PassId, Pass};
-use crate::platform::draw_list::{DrawListId, DrawList};
-use crate::platform::texture::{TextureId, Texture, TextureFormat};
+use crate::platform::pass::{PassId, Pass, PassClearColor, PassGraph, PassError, GroupTarget};
+use crate::platform::draw_list::{DrawListId, DrawList, DrawItem, DrawOrderItem};
+use crate::platform::dirty_regions::DirtyRegions;
+use crate::platform::texture::{TextureId, Texture, TextureFormat, TextureRegion, TextureError};
+use crate::platform::image::Image;
 use crate::platform::geometry::{GeometryId, Geometry};
 use crate::platform::shader::{ShaderId, Shader};
 use crate::platform::gpu_info::GpuInfo;
@@ -11,14 +13,25 @@ use crate::platform::debug::Debug;
 use crate::platform::performance_stats::PerformanceStats;
 use crate::platform::event::Event;
 use crate::platform::os::OsBackend;
+use crate::platform::math::{Vec2, Affine2};
 
 #[cfg(target_os = "windows")]
 use crate::platform::os::windows::WindowsBackend;
 
+// Turtle item rects by turtle (see `turtle::LayoutId`) and item index, each
+// with a key of what it was laid out from
+pub(crate) type LayoutRects = HashMap<((Option<DrawListId>, usize), usize), ((f32, f32, f32, f32), u64)>;
+
 pub struct Cx {
     pub windows: HashMap<WindowId, WindowHandle>,
     pub passes: HashMap<PassId, Pass>,
+    // Order the backend renders passes in, refreshed by `render`
+    pub pass_order: Vec<PassId>,
+    // Why `pass_order` couldn't be refreshed, reported once when it appears
+    pub pass_error: Option<PassError>,
     pub draw_lists: HashMap<DrawListId, DrawList>,
+    // Damage since the last frame; backends repaint only these rects
+    pub dirty_regions: DirtyRegions,
     pub textures: HashMap<TextureId, Texture>,
     pub geometries: HashMap<GeometryId, Geometry>,
     pub shaders: HashMap<ShaderId, Shader>,
@@ -34,6 +47,22 @@ pub struct Cx {
     next_geometry_id: usize,
     next_shader_id: usize,
     next_area_id: usize,
+    // Shaders whose draw items already failed validation, reported once
+    invalid_shaders: HashSet<ShaderId>,
+    // Content hashes of registered shaders and geometries, so drawables that
+    // build identical ones share a single instance
+    shader_cache: HashMap<u64, Vec<ShaderId>>,
+    geometry_cache: HashMap<u64, Vec<GeometryId>>,
+    group_targets: Vec<GroupTarget>,
+    // Opacity groups drawn since the last render; `render` releases the rest
+    pub(crate) group_targets_used: usize,
+    // What `time` returns until the next render
+    frame_time: f64,
+    // Final turtle item rects from the last frame
+    pub(crate) layout_rects: LayoutRects,
+    // The same for this frame; `render` swaps them, dropping items that
+    // weren't laid out again
+    pub(crate) next_layout_rects: LayoutRects,
 
     #[cfg(target_os = "windows")]
     os_backend: WindowsBackend,
@@ -42,6 +71,7 @@ pub struct Cx {
 pub struct AreaData {
     pub rect: (f32, f32, f32, f32), // x, y, width, height
     pub draw_list_id: Option<DrawListId>,
+    pub transform: Affine2, // local to window space
 }
 
 impl Cx {
@@ -49,7 +79,10 @@ impl Cx {
         Self {
             windows: HashMap::new(),
             passes: HashMap::new(),
+            pass_order: Vec::new(),
+            pass_error: None,
             draw_lists: HashMap::new(),
+            dirty_regions: DirtyRegions::new(),
             textures: HashMap::new(),
             geometries: HashMap::new(),
             shaders: HashMap::new(),
@@ -65,6 +98,14 @@ impl Cx {
             next_geometry_id: 1,
             next_shader_id: 1,
             next_area_id: 1,
+            invalid_shaders: HashSet::new(),
+            shader_cache: HashMap::new(),
+            geometry_cache: HashMap::new(),
+            group_targets: Vec::new(),
+            group_targets_used: 0,
+            frame_time: 0.0,
+            layout_rects: HashMap::new(),
+            next_layout_rects: HashMap::new(),
 
             #[cfg(target_os = "windows")]
             os_backend: WindowsBackend::new(),
@@ -111,6 +152,118 @@ impl Cx {
         draw_list_id
     }
 
+    // Appends to the draw list, batching with the previous item where it can.
+    // Debug builds check the item's uniforms against its shader first.
+    pub fn append_draw_item(&mut self, draw_list_id: DrawListId, draw_item: DrawItem) {
+        if cfg!(debug_assertions) {
+            self.check_draw_item(&draw_item);
+        }
+        if let Some(draw_list) = self.draw_lists.get_mut(&draw_list_id) {
+            draw_list.append_draw_item(draw_item);
+        }
+    }
+
+    // Nests `child` in `parent`, drawn after what the parent holds so far
+    pub fn append_sub_list(&mut self, parent: DrawListId, child: DrawListId) {
+        if let Some(draw_list) = self.draw_lists.get_mut(&parent) {
+            draw_list.append_draw_item(DrawItem::sub_list(child));
+        }
+    }
+
+    // The offscreen target for the `index`th opacity group of a frame, with a
+    // cleared content list and a transparent texture of at least the size
+    pub fn group_target(&mut self, index: usize, width: usize, height: usize) -> GroupTarget {
+        while self.group_targets.len() <= index {
+            let pass_id = self.create_pass();
+            let target = GroupTarget {
+                pass_id,
+                content_list: self.create_draw_list(),
+                composite_list: self.create_draw_list(),
+                opacity: 1.0,
+            };
+            if let Some(pass) = self.passes.get_mut(&pass_id) {
+                pass.set_clear_color(PassClearColor::new(0.0, 0.0, 0.0, 0.0));
+            }
+            self.group_targets.push(target);
+        }
+        let target = self.group_targets[index].clone();
+
+        let current = self.passes.get(&target.pass_id).and_then(|pass| pass.color_texture.as_ref());
+        let stale = current.map(|texture| (texture.width, texture.height)) != Some((width, height));
+        if stale {
+            if let Some(old) = current.map(|texture| texture.texture_id) {
+                self.textures.remove(&old);
+            }
+            let texture = self.create_texture(width, height, TextureFormat::Rgba8);
+            if let Some(pass) = self.passes.get_mut(&target.pass_id) {
+                pass.set_color_texture(texture);
+            }
+        }
+        if let Some(pass) = self.passes.get_mut(&target.pass_id) {
+            pass.set_main_draw_list(target.content_list);
+        }
+        if let Some(draw_list) = self.draw_lists.get_mut(&target.content_list) {
+            draw_list.clear();
+        }
+        target
+    }
+
+    // Records the opacity a group was drawn with, returning the previous one
+    pub fn set_group_opacity(&mut self, index: usize, opacity: f32) -> f32 {
+        match self.group_targets.get_mut(index) {
+            Some(target) => std::mem::replace(&mut target.opacity, opacity),
+            None => opacity,
+        }
+    }
+
+    // Stops rendering the offscreen passes of groups from `first` on, which
+    // this frame didn't use
+    pub fn release_group_targets(&mut self, first: usize) {
+        for target in self.group_targets.iter().skip(first) {
+            if let Some(pass) = self.passes.get_mut(&target.pass_id) {
+                pass.main_draw_list_id = None;
+            }
+            for id in [target.content_list, target.composite_list] {
+                if let Some(draw_list) = self.draw_lists.get_mut(&id) {
+                    draw_list.clear();
+                }
+            }
+        }
+    }
+
+    // Every item the pass draws, in the order the backend should draw them
+    pub fn draw_order(&self, pass_id: PassId) -> Vec<DrawOrderItem> {
+        match self.passes.get(&pass_id) {
+            Some(Pass { main_draw_list_id: Some(root), zbias_step, .. }) => {
+                crate::platform::draw_list::draw_order(*root, &self.draw_lists, *zbias_step)
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    // Reports mismatched uniforms or geometry through `Debug::report`, once
+    // per shader so a broken drawable doesn't flood it every frame
+    fn check_draw_item(&mut self, draw_item: &DrawItem) {
+        if draw_item.sub_list.is_some() {
+            return;
+        }
+        let Some(shader) = self.shaders.get(&draw_item.shader_id) else {
+            return;
+        };
+        let result = shader.validate_draw_item(draw_item).map_err(|err| err.to_string()).and_then(|_| {
+            match self.geometries.get(&draw_item.geometry_id) {
+                Some(geometry) => shader.validate_geometry(geometry).map_err(|err| err.to_string()),
+                None => Ok(()),
+            }
+        });
+        if let Err(err) = result {
+            if !self.invalid_shaders.contains(&draw_item.shader_id) {
+                self.debug.report(&format!("shader {}: {}", draw_item.shader_id.0, err));
+                self.invalid_shaders.insert(draw_item.shader_id);
+            }
+        }
+    }
+
     pub fn create_texture(&mut self, width: usize, height: usize, format: TextureFormat) -> Texture {
         let texture_id = TextureId(self.next_texture_id);
         self.next_texture_id += 1;
@@ -121,6 +274,42 @@ impl Cx {
         texture
     }
 
+    pub fn create_texture_with_data(&mut self, width: usize, height: usize, format: TextureFormat, data: Vec<u8>) -> Result<Texture, TextureError> {
+        let mut texture = Texture::new(TextureId(self.next_texture_id), width, height, format);
+        texture.set_data(data)?;
+        self.next_texture_id += 1;
+        self.textures.insert(texture.texture_id, texture.clone());
+
+        Ok(texture)
+    }
+
+    // Decodes a PNG or JPEG (with the `png`/`jpeg` features) into an Rgba8 texture
+    pub fn load_texture(&mut self, bytes: &[u8]) -> Result<Texture, TextureError> {
+        let image = Image::decode(bytes)?;
+        self.create_texture_with_data(image.width, image.height, TextureFormat::Rgba8, image.data)
+    }
+
+    pub fn update_texture(&mut self, texture_id: TextureId, data: Vec<u8>) -> Result<(), TextureError> {
+        self.textures
+            .get_mut(&texture_id)
+            .ok_or(TextureError::UnknownTexture(texture_id))?
+            .set_data(data)
+    }
+
+    pub fn update_texture_region(&mut self, texture_id: TextureId, region: TextureRegion, data: &[u8]) -> Result<(), TextureError> {
+        self.textures
+            .get_mut(&texture_id)
+            .ok_or(TextureError::UnknownTexture(texture_id))?
+            .update_region(region, data)
+    }
+
+    pub fn generate_texture_mipmaps(&mut self, texture_id: TextureId) -> Result<(), TextureError> {
+        self.textures
+            .get_mut(&texture_id)
+            .ok_or(TextureError::UnknownTexture(texture_id))?
+            .generate_mipmaps()
+    }
+
     pub fn create_geometry(&mut self) -> GeometryId {
         let geometry_id = GeometryId(self.next_geometry_id);
         self.next_geometry_id += 1;
@@ -141,6 +330,42 @@ impl Cx {
         shader_id
     }
 
+    // Returns the id of an identical registered shader, or registers this one.
+    // The shader's own id is ignored and replaced.
+    pub fn register_shader(&mut self, mut shader: Shader) -> ShaderId {
+        let hash = shader.content_hash();
+        let existing = self.shader_cache.get(&hash).and_then(|ids| {
+            ids.iter().find(|id| self.shaders.get(id).is_some_and(|s| s.content_eq(&shader))).copied()
+        });
+        if let Some(shader_id) = existing {
+            return shader_id;
+        }
+
+        let shader_id = self.create_shader();
+        shader.shader_id = shader_id;
+        self.shaders.insert(shader_id, shader);
+        self.shader_cache.entry(hash).or_default().push(shader_id);
+        shader_id
+    }
+
+    // Like `register_shader`. Shared geometries must not be modified; use
+    // `create_geometry` for per-drawable data such as tessellated paths.
+    pub fn register_geometry(&mut self, mut geometry: Geometry) -> GeometryId {
+        let hash = geometry.content_hash();
+        let existing = self.geometry_cache.get(&hash).and_then(|ids| {
+            ids.iter().find(|id| self.geometries.get(id).is_some_and(|g| g.content_eq(&geometry))).copied()
+        });
+        if let Some(geometry_id) = existing {
+            return geometry_id;
+        }
+
+        let geometry_id = self.create_geometry();
+        geometry.geometry_id = geometry_id;
+        self.geometries.insert(geometry_id, geometry);
+        self.geometry_cache.entry(hash).or_default().push(geometry_id);
+        geometry_id
+    }
+
     pub fn create_area(&mut self) -> Area {
         let area = Area(self.next_area_id);
         self.next_area_id += 1;
@@ -148,6 +373,7 @@ impl Cx {
         let area_data = AreaData {
             rect: (0.0, 0.0, 0.0, 0.0),
             draw_list_id: None,
+            transform: Affine2::identity(),
         };
 
         self.areas.insert(area, area_data);
@@ -167,6 +393,29 @@ impl Cx {
         }
     }
 
+    pub fn set_area_transform(&mut self, area: Area, transform: Affine2) {
+        if let Some(area_data) = self.areas.get_mut(&area) {
+            area_data.transform = transform;
+        }
+    }
+
+    // Maps a window-space point into the area's local space
+    pub fn area_local_point(&self, area: Area, point: Vec2) -> Option<Vec2> {
+        let area_data = self.areas.get(&area)?;
+        let inverse = area_data.transform.inverse()?;
+        Some(inverse.transform_point(point))
+    }
+
+    pub fn area_contains(&self, area: Area, point: Vec2) -> bool {
+        match (self.areas.get(&area), self.area_local_point(area, point)) {
+            (Some(area_data), Some(local)) => {
+                let (ax, ay, aw, ah) = area_data.rect;
+                local.x >= ax && local.x <= ax + aw && local.y >= ay && local.y <= ay + ah
+            },
+            _ => false,
+        }
+    }
+
     pub fn process_events(&mut self) -> Vec<Event> {
         #[cfg(target_os = "windows")]
         return self.os_backend.process_events();
@@ -175,11 +424,46 @@ impl Cx {
         return Vec::new();
     }
 
+    // Seconds since the Cx was created, for animated shaders. Sampled once
+    // per frame by `render`, so every draw of a frame sees the same time and
+    // items that bind it still batch.
+    pub fn time(&self) -> f64 {
+        self.frame_time
+    }
+
+    pub fn pass_graph(&self) -> PassGraph {
+        PassGraph::build(&self.passes, &self.draw_lists)
+    }
+
+    // Sorts passes so offscreen passes render into their textures before the
+    // passes that composite them. On a cycle the previous order is kept.
+    pub fn update_pass_order(&mut self) -> Result<(), PassError> {
+        self.pass_order = self.pass_graph().sort()?;
+        Ok(())
+    }
+
     pub fn render(&mut self) {
         self.performance_stats.update();
 
+        self.release_group_targets(self.group_targets_used);
+        self.group_targets_used = 0;
+        self.layout_rects = std::mem::take(&mut self.next_layout_rects);
+
+        match self.update_pass_order() {
+            Ok(()) => self.pass_error = None,
+            Err(err) => {
+                if self.pass_error.as_ref() != Some(&err) {
+                    self.debug.report(&err.to_string());
+                }
+                self.pass_error = Some(err);
+            },
+        }
+
         #[cfg(target_os = "windows")]
         self.os_backend.render();
+
+        self.dirty_regions.clear();
+        self.frame_time = self.performance_stats.start_time.elapsed().as_secs_f64();
     }
 
     pub fn shutdown(&mut self) {
@@ -197,6 +481,7 @@ impl Cx {
         event_handler(self, Event::Init);
 
         // Initial draw
+        self.dirty_regions.mark_all();
         event_handler(self, Event::Draw);
         self.render();
 
@@ -215,6 +500,7 @@ impl Cx {
                     },
                     Event::WindowResize { .. } => {
                         needs_redraw = true;
+                        self.dirty_regions.mark_all();
                         event_handler(self, event);
                     },
                     _ => {
@@ -240,5 +526,53 @@ impl Cx {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn area(cx: &mut Cx, transform: Affine2) -> Area {
+        let area = cx.create_area();
+        cx.set_area_rect(area, 0.0, 0.0, 100.0, 20.0);
+        cx.set_area_transform(area, transform);
+        area
+    }
 
+    #[test]
+    fn area_hits_follow_rotation_and_scale() {
+        let mut cx = Cx::new();
+
+        // A quarter turn about the center stands the rect upright: x 40..60, y -40..60
+        let turned = area(&mut cx, Affine2::around(Vec2::new(50.0, 10.0), Affine2::rotation(std::f32::consts::FRAC_PI_2)));
+        assert!(cx.area_contains(turned, Vec2::new(50.0, 50.0)));
+        assert!(cx.area_contains(turned, Vec2::new(45.0, -35.0)));
+        assert!(!cx.area_contains(turned, Vec2::new(90.0, 10.0)));
+        assert!(!cx.area_contains(turned, Vec2::new(35.0, 10.0)));
+
+        // Doubled and moved: x 10..210, y 10..50
+        let scaled = area(&mut cx, Affine2::translation(10.0, 10.0).multiply(&Affine2::scale(2.0, 2.0)));
+        assert!(cx.area_contains(scaled, Vec2::new(200.0, 45.0)));
+        assert!(!cx.area_contains(scaled, Vec2::new(5.0, 20.0)));
+        assert!(!cx.area_contains(scaled, Vec2::new(100.0, 55.0)));
+        let local = cx.area_local_point(scaled, Vec2::new(110.0, 30.0)).unwrap();
+        assert_eq!((local.x, local.y), (50.0, 10.0));
+
+        // Mirrored: x -100..0
+        let mirrored = area(&mut cx, Affine2::scale(-1.0, 1.0));
+        assert!(cx.area_contains(mirrored, Vec2::new(-50.0, 10.0)));
+        assert!(!cx.area_contains(mirrored, Vec2::new(50.0, 10.0)));
+    }
+
+    #[test]
+    fn areas_with_a_collapsed_transform_are_never_hit() {
+        let mut cx = Cx::new();
+        for transform in [Affine2::scale(0.0, 1.0), Affine2::scale(1.0, 0.0), Affine2::new(1.0, 2.0, 2.0, 4.0, 0.0, 0.0)] {
+            let collapsed = area(&mut cx, transform);
+            assert_eq!(cx.area_local_point(collapsed, Vec2::new(0.0, 0.0)), None);
+            assert!(!cx.area_contains(collapsed, Vec2::new(0.0, 0.0)));
+            assert!(!cx.area_contains(collapsed, Vec2::new(10.0, 5.0)));
+        }
+
+        // Unknown areas aren't hit either
+        assert!(!cx.area_contains(Area(999), Vec2::new(0.0, 0.0)));
+    }
+}
@@ -1,12 +1,40 @@
+use std::collections::HashMap;
+use crate::platform::draw_list::DrawListId;
+
 #[derive(Clone, Debug)]
 pub struct Debug {
     pub enabled: bool,
+    // Flag low-contrast text on screen, see `ContrastOverlay`
+    pub contrast_overlay: bool,
+    // What each draw list drew while `contrast_overlay` was on, replaced
+    // whenever the list is rebuilt
+    pub contrast_samples: HashMap<DrawListId, Vec<ContrastSample>>,
+    // Everything passed to `report`, for tools and tests to check
+    pub errors: Vec<String>,
+}
+
+// Window-space rects and sRGB colors, enough to check text against what is
+// drawn behind it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContrastSample {
+    Background {
+        rect: (f32, f32, f32, f32),
+        color: [f32; 4],
+    },
+    Text {
+        rect: (f32, f32, f32, f32),
+        color: [f32; 4],
+        min_contrast: f32,
+    },
 }
 
 impl Debug {
     pub fn new() -> Self {
         Self {
             enabled: false,
+            contrast_overlay: false,
+            contrast_samples: HashMap::new(),
+            errors: Vec::new(),
         }
     }
     
@@ -21,6 +49,29 @@ impl Debug {
             eprintln!("[ERROR] {}", message);
         }
     }
+
+    // For problems that break drawing: printed whether or not `enabled` is
+    // set, and kept in `errors`. Callers report each problem once.
+    pub fn report(&mut self, message: &str) {
+        eprintln!("[ERROR] {}", message);
+        self.errors.push(message.to_string());
+    }
+
+    pub fn record_contrast_sample(&mut self, draw_list_id: DrawListId, sample: ContrastSample) {
+        if self.contrast_overlay {
+            self.contrast_samples.entry(draw_list_id).or_default().push(sample);
+        }
+    }
+
+    // Called when a draw list is rebuilt. While the overlay is on, an empty
+    // entry marks the list as sampled even if it draws nothing.
+    pub fn reset_contrast_samples(&mut self, draw_list_id: DrawListId) {
+        if self.contrast_overlay {
+            self.contrast_samples.insert(draw_list_id, Vec::new());
+        } else {
+            self.contrast_samples.remove(&draw_list_id);
+        }
+    }
 }
 
 
@@ -0,0 +1,126 @@
+// Past this many separate rects the backend is better off repainting their
+// bounding box than scissoring each one
+const MAX_DIRTY_RECTS: usize = 16;
+
+// Window-space rects whose pixels changed since the backend last presented.
+// Overlapping rects are merged as they are added.
+#[derive(Clone, Debug, Default)]
+pub struct DirtyRegions {
+    rects: Vec<(f32, f32, f32, f32)>, // x, y, width, height
+    full: bool,
+}
+
+impl DirtyRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, rect: (f32, f32, f32, f32)) {
+        if self.full || rect.2 <= 0.0 || rect.3 <= 0.0 {
+            return;
+        }
+
+        // A merged rect can now overlap rects it missed before, so keep
+        // merging until it stands alone
+        let mut rect = rect;
+        while let Some(index) = self.rects.iter().position(|r| overlaps(*r, rect)) {
+            rect = union(self.rects.swap_remove(index), rect);
+        }
+        self.rects.push(rect);
+
+        if self.rects.len() > MAX_DIRTY_RECTS {
+            let bounds = self.rects.drain(..).reduce(union).unwrap_or(rect);
+            self.rects.push(bounds);
+        }
+    }
+
+    // The whole surface needs repainting, e.g. after a resize
+    pub fn mark_all(&mut self) {
+        self.full = true;
+        self.rects.clear();
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.full && self.rects.is_empty()
+    }
+
+    // Empty when `is_full`; the backend repaints everything then
+    pub fn rects(&self) -> &[(f32, f32, f32, f32)] {
+        &self.rects
+    }
+
+    pub fn bounds(&self) -> Option<(f32, f32, f32, f32)> {
+        self.rects.iter().copied().reduce(union)
+    }
+
+    pub fn clear(&mut self) {
+        self.full = false;
+        self.rects.clear();
+    }
+}
+
+fn overlaps(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> bool {
+    a.0 <= b.0 + b.2 && b.0 <= a.0 + a.2 && a.1 <= b.1 + b.3 && b.1 <= a.1 + a.3
+}
+
+fn union(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+    let x = a.0.min(b.0);
+    let y = a.1.min(b.1);
+    (x, y, (a.0 + a.2).max(b.0 + b.2) - x, (a.1 + a.3).max(b.1 + b.3) - y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_rects_merge() {
+        let mut dirty = DirtyRegions::new();
+        dirty.add((0.0, 0.0, 10.0, 10.0));
+        dirty.add((5.0, 5.0, 10.0, 10.0));
+        dirty.add((50.0, 0.0, 10.0, 10.0));
+        assert_eq!(dirty.rects(), &[(0.0, 0.0, 15.0, 15.0), (50.0, 0.0, 10.0, 10.0)]);
+
+        // Bridging the two merges all three
+        dirty.add((10.0, 0.0, 45.0, 5.0));
+        assert_eq!(dirty.rects(), &[(0.0, 0.0, 60.0, 15.0)]);
+    }
+
+    #[test]
+    fn empty_rects_are_ignored() {
+        let mut dirty = DirtyRegions::new();
+        dirty.add((0.0, 0.0, 0.0, 10.0));
+        dirty.add((0.0, 0.0, 10.0, -1.0));
+        assert!(dirty.is_empty());
+        assert_eq!(dirty.bounds(), None);
+    }
+
+    #[test]
+    fn too_many_rects_collapse_to_their_bounds() {
+        let mut dirty = DirtyRegions::new();
+        for i in 0..MAX_DIRTY_RECTS {
+            dirty.add((i as f32 * 20.0, 0.0, 10.0, 10.0));
+        }
+        assert_eq!(dirty.rects().len(), MAX_DIRTY_RECTS);
+
+        dirty.add((0.0, 100.0, 10.0, 10.0));
+        assert_eq!(dirty.rects(), &[(0.0, 0.0, MAX_DIRTY_RECTS as f32 * 20.0 - 10.0, 110.0)]);
+    }
+
+    #[test]
+    fn mark_all_covers_everything_until_cleared() {
+        let mut dirty = DirtyRegions::new();
+        dirty.add((0.0, 0.0, 10.0, 10.0));
+        dirty.mark_all();
+        dirty.add((20.0, 20.0, 10.0, 10.0));
+        assert!(dirty.is_full() && !dirty.is_empty());
+        assert!(dirty.rects().is_empty());
+
+        dirty.clear();
+        assert!(dirty.is_empty() && !dirty.is_full());
+    }
+}
@@ -1,5 +1,6 @@
-use crate::platform::math::Mat4;
-use crate::platform::shader::ShaderId;
+use std::collections::HashMap;
+use crate::platform::math::{Mat4, Affine2, srgb_to_linear, linear_to_srgb};
+use crate::platform::shader::{ShaderId, ShaderUniformType};
 use crate::platform::geometry::GeometryId;
 use crate::platform::texture::Texture;
 
@@ -10,18 +11,193 @@ pub struct DrawListId(pub usize);
 pub struct DrawItem {
     pub shader_id: ShaderId,
     pub geometry_id: GeometryId,
-    pub uniforms: Vec<DrawUniform>,
+    // Matched to the shader's uniforms by name
+    pub uniforms: Vec<UniformBinding>,
+    // One per Texture2D uniform, in declaration order
     pub textures: Vec<Texture>,
+    pub instances: Vec<f32>,
     pub instance_count: u32,
+    pub clip: Option<DrawClip>,
+    pub transform: Affine2,
+    pub blend: BlendMode,
+    // When set the item draws nothing itself and stands for the whole child
+    // list at this point in the parent's order
+    pub sub_list: Option<DrawListId>,
 }
 
-#[derive(Clone, Debug)]
+// Region the item is clipped to. Backends scissor to `rect` and, when
+// `corner_radius` is set, mask the corners in the fragment shader.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawClip {
+    pub rect: (f32, f32, f32, f32), // x, y, width, height
+    pub corner_radius: f32,
+}
+
+impl DrawItem {
+    pub fn sub_list(draw_list_id: DrawListId) -> Self {
+        Self {
+            shader_id: ShaderId(0),
+            geometry_id: GeometryId(0),
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+            instances: Vec::new(),
+            instance_count: 0,
+            clip: None,
+            transform: Affine2::identity(),
+            blend: BlendMode::Normal,
+            sub_list: Some(draw_list_id),
+        }
+    }
+
+    // Items can share one instanced draw when only their instance data differs
+    pub fn can_batch_with(&self, other: &DrawItem) -> bool {
+        self.sub_list.is_none()
+            && other.sub_list.is_none()
+            && self.shader_id == other.shader_id
+            && self.geometry_id == other.geometry_id
+            && self.uniforms == other.uniforms
+            && self.clip == other.clip
+            && self.transform == other.transform
+            && self.blend == other.blend
+            && self.textures.len() == other.textures.len()
+            && self.textures.iter().zip(&other.textures).all(|(a, b)| a.texture_id == b.texture_id)
+    }
+
+    // The raw attribute values of one instance
+    pub fn instance(&self, index: usize) -> &[f32] {
+        if self.instance_count == 0 {
+            return &[];
+        }
+        let stride = self.instances.len() / self.instance_count as usize;
+        &self.instances[index * stride..(index + 1) * stride]
+    }
+}
+
+// How an item's fragments combine with what is already in the target.
+// Shaders output straight alpha; `Premultiplied` is for sources that are
+// already premultiplied, such as offscreen pass textures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Premultiplied,
+    Additive,
+    Multiply,
+    Screen,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstColor,
+    OneMinusSrcColor,
+}
+
+// Fixed-function blending with an add equation:
+// result = src * src_factor + dst * dst_factor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlendState {
+    pub color_src: BlendFactor,
+    pub color_dst: BlendFactor,
+    pub alpha_src: BlendFactor,
+    pub alpha_dst: BlendFactor,
+}
+
+impl BlendMode {
+    pub fn blend_state(self) -> BlendState {
+        use BlendFactor::*;
+        let (color_src, color_dst, alpha_src, alpha_dst) = match self {
+            BlendMode::Normal => (SrcAlpha, OneMinusSrcAlpha, One, OneMinusSrcAlpha),
+            BlendMode::Premultiplied => (One, OneMinusSrcAlpha, One, OneMinusSrcAlpha),
+            BlendMode::Additive => (SrcAlpha, One, One, One),
+            BlendMode::Multiply => (DstColor, OneMinusSrcAlpha, One, OneMinusSrcAlpha),
+            BlendMode::Screen => (One, OneMinusSrcColor, One, OneMinusSrcAlpha),
+        };
+        BlendState { color_src, color_dst, alpha_src, alpha_dst }
+    }
+
+    // Multiply and Screen only come out right for translucent fragments when
+    // the color is premultiplied, so backends multiply the shader's output
+    // color by its alpha before blending
+    pub fn premultiplies_output(self) -> bool {
+        matches!(self, BlendMode::Multiply | BlendMode::Screen)
+    }
+
+    // Reference blend of one straight-alpha source fragment over a
+    // premultiplied target pixel, as a backend would do it
+    pub fn blend(self, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+        let src = if self.premultiplies_output() {
+            [src[0] * src[3], src[1] * src[3], src[2] * src[3], src[3]]
+        } else {
+            src
+        };
+        let state = self.blend_state();
+        let factor = |factor: BlendFactor, channel: usize| match factor {
+            BlendFactor::Zero => 0.0,
+            BlendFactor::One => 1.0,
+            BlendFactor::SrcAlpha => src[3],
+            BlendFactor::OneMinusSrcAlpha => 1.0 - src[3],
+            BlendFactor::DstColor => dst[channel],
+            BlendFactor::OneMinusSrcColor => 1.0 - src[channel],
+        };
+        let mut out = [0.0; 4];
+        for channel in 0..3 {
+            out[channel] = src[channel] * factor(state.color_src, channel) + dst[channel] * factor(state.color_dst, channel);
+        }
+        out[3] = src[3] * factor(state.alpha_src, 3) + dst[3] * factor(state.alpha_dst, 3);
+        out.map(|value| value.clamp(0.0, 1.0))
+    }
+
+    // `blend` for sRGB-encoded colors: the mix happens on linear light and
+    // the result is encoded again, which is how a software rasterizer should
+    // composite. Alpha is never encoded.
+    pub fn blend_srgb(self, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+        let decode = |c: [f32; 4]| [srgb_to_linear(c[0]), srgb_to_linear(c[1]), srgb_to_linear(c[2]), c[3]];
+        let out = self.blend(decode(src), decode(dst));
+        [linear_to_srgb(out[0]), linear_to_srgb(out[1]), linear_to_srgb(out[2]), out[3]]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum DrawUniform {
     Float(f32),
     Vec2([f32; 2]),
     Vec3([f32; 3]),
     Vec4([f32; 4]),
     Mat4([f32; 16]),
+    Vec4Array(Vec<[f32; 4]>),
+}
+
+impl DrawUniform {
+    pub fn uniform_type(&self) -> ShaderUniformType {
+        match self {
+            DrawUniform::Float(_) => ShaderUniformType::Float,
+            DrawUniform::Vec2(_) => ShaderUniformType::Vec2,
+            DrawUniform::Vec3(_) => ShaderUniformType::Vec3,
+            DrawUniform::Vec4(_) => ShaderUniformType::Vec4,
+            DrawUniform::Mat4(_) => ShaderUniformType::Mat4,
+            DrawUniform::Vec4Array(values) => ShaderUniformType::Vec4Array(values.len()),
+        }
+    }
+}
+
+// A uniform value for the shader uniform called `name`
+#[derive(Clone, Debug, PartialEq)]
+pub struct UniformBinding {
+    pub name: String,
+    pub value: DrawUniform,
+}
+
+impl UniformBinding {
+    pub fn new(name: &str, value: DrawUniform) -> Self {
+        Self {
+            name: name.to_string(),
+            value,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -29,6 +205,9 @@ pub struct DrawList {
     pub draw_list_id: DrawListId,
     pub draw_items: Vec<DrawItem>,
     pub view_transform: Mat4,
+    // Lists in a higher layer draw after every list in a lower one, wherever
+    // they sit in the tree. None keeps the parent's layer.
+    pub layer: Option<i32>,
 }
 
 impl DrawList {
@@ -37,12 +216,26 @@ impl DrawList {
             draw_list_id,
             draw_items: Vec::new(),
             view_transform: Mat4::identity(),
+            layer: None,
         }
     }
     
     pub fn add_draw_item(&mut self, draw_item: DrawItem) {
         self.draw_items.push(draw_item);
     }
+
+    // Appends the item's instances to the last item when they can be drawn
+    // together, so runs of quads or glyphs become one instanced draw
+    pub fn append_draw_item(&mut self, draw_item: DrawItem) {
+        if let Some(last) = self.draw_items.last_mut() {
+            if last.can_batch_with(&draw_item) {
+                last.instances.extend_from_slice(&draw_item.instances);
+                last.instance_count += draw_item.instance_count;
+                return;
+            }
+        }
+        self.draw_items.push(draw_item);
+    }
     
     pub fn clear(&mut self) {
         self.draw_items.clear();
@@ -51,6 +244,114 @@ impl DrawList {
     pub fn set_view_transform(&mut self, view_transform: Mat4) {
         self.view_transform = view_transform;
     }
+
+    pub fn set_layer(&mut self, layer: Option<i32>) {
+        self.layer = layer;
+    }
+
+    // Whether any item draws something itself rather than through a sub-list
+    pub fn has_own_items(&self) -> bool {
+        self.draw_items.iter().any(|item| item.sub_list.is_none())
+    }
+
+    pub fn sub_lists(&self) -> Vec<DrawListId> {
+        self.draw_items.iter().filter_map(|item| item.sub_list).collect()
+    }
+
+    // Points the sub-list items at `sub_lists` in order, leaving the list's
+    // own items where they are. Surplus links are dropped and missing ones
+    // appended.
+    pub fn replace_sub_lists(&mut self, sub_lists: &[DrawListId]) {
+        let mut next = sub_lists.iter();
+        self.draw_items.retain_mut(|item| match item.sub_list {
+            Some(_) => match next.next() {
+                Some(id) => {
+                    item.sub_list = Some(*id);
+                    true
+                },
+                None => false,
+            },
+            None => true,
+        });
+        for id in next {
+            self.draw_items.push(DrawItem::sub_list(*id));
+        }
+    }
+}
+
+// One item in a pass's flattened draw order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawOrderItem {
+    pub draw_list_id: DrawListId,
+    pub item_index: usize,
+    // Nesting depth below the pass's main draw list
+    pub depth: usize,
+    pub layer: i32,
+    // `depth` times the pass's zbias step, added to the item's z so deeper
+    // lists win the depth test
+    pub zbias: f32,
+}
+
+// Flattens the tree of lists below `root` into the order the backend draws
+// in: by layer, then in tree order, sub-list items replaced by their lists'
+// contents. A list that contains itself further down is drawn once.
+pub fn draw_order(root: DrawListId, draw_lists: &HashMap<DrawListId, DrawList>, zbias_step: f32) -> Vec<DrawOrderItem> {
+    let mut order = Vec::new();
+    let mut path = Vec::new();
+    collect_draw_order(root, 0, 0, draw_lists, zbias_step, &mut path, &mut order);
+    // Stable, so tree order holds within a layer
+    order.sort_by_key(|item| item.layer);
+    order
+}
+
+// Every list in the tree below `root`, parents before their sub-lists
+pub fn list_order(root: DrawListId, draw_lists: &HashMap<DrawListId, DrawList>) -> Vec<DrawListId> {
+    let mut order = Vec::new();
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        if order.contains(&id) {
+            continue;
+        }
+        order.push(id);
+        if let Some(draw_list) = draw_lists.get(&id) {
+            stack.extend(draw_list.sub_lists().into_iter().rev());
+        }
+    }
+    order
+}
+
+fn collect_draw_order(
+    draw_list_id: DrawListId,
+    depth: usize,
+    parent_layer: i32,
+    draw_lists: &HashMap<DrawListId, DrawList>,
+    zbias_step: f32,
+    path: &mut Vec<DrawListId>,
+    order: &mut Vec<DrawOrderItem>,
+) {
+    let Some(draw_list) = draw_lists.get(&draw_list_id) else {
+        return;
+    };
+    if path.contains(&draw_list_id) {
+        return;
+    }
+    path.push(draw_list_id);
+
+    let layer = draw_list.layer.unwrap_or(parent_layer);
+    for (item_index, item) in draw_list.draw_items.iter().enumerate() {
+        match item.sub_list {
+            Some(child) => collect_draw_order(child, depth + 1, layer, draw_lists, zbias_step, path, order),
+            None => order.push(DrawOrderItem {
+                draw_list_id,
+                item_index,
+                depth,
+                layer,
+                zbias: depth as f32 * zbias_step,
+            }),
+        }
+    }
+
+    path.pop();
 }
 
 
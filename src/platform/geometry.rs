@@ -1,3 +1,6 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct GeometryId(pub usize);
 
@@ -5,18 +8,19 @@ pub struct GeometryId(pub usize);
 pub struct Geometry {
     pub geometry_id: GeometryId,
     pub vertices: Vec<u8>,
-    pub indices: Vec<u16>,
+    // 32 bit so large tessellated paths fit in one geometry
+    pub indices: Vec<u32>,
     pub vertex_attributes: Vec<VertexAttribute>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct VertexAttribute {
     pub name: String,
     pub offset: usize,
     pub format: VertexFormat,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum VertexFormat {
     Float1,
     Float2,
@@ -69,10 +73,64 @@ impl Geometry {
         self.vertices = vertices;
     }
     
-    pub fn set_indices(&mut self, indices: Vec<u16>) {
+    pub fn set_indices(&mut self, indices: Vec<u32>) {
         self.indices = indices;
     }
+
+    // Identity for Cx's geometry cache: everything but the id
+    pub fn content_eq(&self, other: &Geometry) -> bool {
+        self.vertices == other.vertices
+            && self.indices == other.indices
+            && self.vertex_attributes == other.vertex_attributes
+    }
+
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.vertices.hash(&mut hasher);
+        self.indices.hash(&mut hasher);
+        self.vertex_attributes.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Cx;
+
+    fn triangle(vertices: Vec<u8>) -> Geometry {
+        let mut geometry = Geometry::new(GeometryId(0));
+        geometry.add_vertex_attribute("position", 0, VertexFormat::Float2);
+        geometry.set_vertices(vertices);
+        geometry.set_indices(vec![0, 1, 2]);
+        geometry
+    }
+
+    #[test]
+    fn identical_geometries_share_an_id() {
+        let mut cx = Cx::new();
+        let vertices: Vec<u8> = [0.0f32, 0.0, 1.0, 0.0, 0.0, 1.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let a = cx.register_geometry(triangle(vertices.clone()));
+        // The id passed in doesn't count
+        let mut copy = triangle(vertices.clone());
+        copy.geometry_id = GeometryId(42);
+        assert_eq!(cx.register_geometry(copy), a);
+        assert_eq!(cx.geometries[&a].geometry_id, a);
 
+        let mut other_data = vertices.clone();
+        other_data[0] = 1;
+        let mut other_indices = triangle(vertices.clone());
+        other_indices.set_indices(vec![2, 1, 0]);
+        let mut other_layout = triangle(vertices.clone());
+        other_layout.vertex_attributes[0].format = VertexFormat::Short4;
 
+        let ids = [
+            cx.register_geometry(triangle(other_data)),
+            cx.register_geometry(other_indices),
+            cx.register_geometry(other_layout),
+        ];
+        assert!(ids.iter().all(|id| *id != a));
+        assert!(ids[0] != ids[1] && ids[1] != ids[2] && ids[0] != ids[2]);
+        assert_eq!(cx.geometries.len(), 4);
+    }
+}
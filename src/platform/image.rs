@@ -0,0 +1,156 @@
+use crate::platform::texture::TextureError;
+
+// Decoded pixels, always TextureFormat::Rgba8 with straight alpha
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    // Picks the decoder from the file signature. Each format needs its cargo
+    // feature (`png`, `jpeg`); disabled formats report a decode error.
+    pub fn decode(bytes: &[u8]) -> Result<Image, TextureError> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            return decode_png(bytes);
+        }
+        if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            return decode_jpeg(bytes);
+        }
+        Err(TextureError::Decode("unrecognized image format".to_string()))
+    }
+
+    pub fn from_file(path: &std::path::Path) -> Result<Image, TextureError> {
+        let bytes = std::fs::read(path).map_err(|err| TextureError::Decode(err.to_string()))?;
+        Self::decode(&bytes)
+    }
+}
+
+// Expands 1 to 4 channel 8-bit pixels to RGBA
+#[cfg_attr(not(any(feature = "png", feature = "jpeg")), allow(dead_code))]
+fn expand_to_rgba(pixels: &[u8], channels: usize) -> Vec<u8> {
+    if channels == 4 {
+        return pixels.to_vec();
+    }
+    let mut rgba = Vec::with_capacity(pixels.len() / channels * 4);
+    for p in pixels.chunks_exact(channels) {
+        match channels {
+            1 => rgba.extend_from_slice(&[p[0], p[0], p[0], 255]),
+            2 => rgba.extend_from_slice(&[p[0], p[0], p[0], p[1]]),
+            _ => rgba.extend_from_slice(&[p[0], p[1], p[2], 255]),
+        }
+    }
+    rgba
+}
+
+#[cfg(feature = "png")]
+fn decode_png(bytes: &[u8]) -> Result<Image, TextureError> {
+    let error = |err: png::DecodingError| TextureError::Decode(err.to_string());
+
+    let mut decoder = png::Decoder::new(bytes);
+    // Palettes, low bit depths and 16-bit channels all come out as 8-bit
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(error)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(error)?;
+    buffer.truncate(info.buffer_size());
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err(TextureError::Decode("unexpanded palette".to_string())),
+    };
+
+    Ok(Image {
+        width: info.width as usize,
+        height: info.height as usize,
+        data: expand_to_rgba(&buffer, channels),
+    })
+}
+
+#[cfg(not(feature = "png"))]
+fn decode_png(_bytes: &[u8]) -> Result<Image, TextureError> {
+    Err(TextureError::Decode("PNG support needs the `png` feature".to_string()))
+}
+
+#[cfg(feature = "jpeg")]
+fn decode_jpeg(bytes: &[u8]) -> Result<Image, TextureError> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let pixels = decoder.decode().map_err(|err| TextureError::Decode(err.to_string()))?;
+    let info = decoder.info().ok_or_else(|| TextureError::Decode("missing JPEG header".to_string()))?;
+
+    let data = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => expand_to_rgba(&pixels, 1),
+        // Big-endian 16-bit luma; keep the high byte
+        jpeg_decoder::PixelFormat::L16 => {
+            let luma: Vec<u8> = pixels.chunks_exact(2).map(|p| p[0]).collect();
+            expand_to_rgba(&luma, 1)
+        },
+        jpeg_decoder::PixelFormat::RGB24 => expand_to_rgba(&pixels, 3),
+        // Adobe CMYK JPEGs store inverted values
+        jpeg_decoder::PixelFormat::CMYK32 => pixels
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let k = p[3] as u32;
+                [(p[0] as u32 * k / 255) as u8, (p[1] as u32 * k / 255) as u8, (p[2] as u32 * k / 255) as u8, 255]
+            })
+            .collect(),
+    };
+
+    Ok(Image {
+        width: info.width as usize,
+        height: info.height as usize,
+        data,
+    })
+}
+
+#[cfg(not(feature = "jpeg"))]
+fn decode_jpeg(_bytes: &[u8]) -> Result<Image, TextureError> {
+    Err(TextureError::Decode("JPEG support needs the `jpeg` feature".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2x2 RGB: red, green / blue, white
+    #[cfg_attr(not(feature = "png"), allow(dead_code))]
+    const TINY_PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+        0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x02, 0x00, 0x00, 0x00, 0xfd, 0xd4, 0x9a,
+        0x73, 0x00, 0x00, 0x00, 0x12, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8, 0xcf, 0xc0, 0xc0,
+        0x00, 0xc2, 0x0c, 0xff, 0x81, 0x00, 0x00, 0x1f, 0xee, 0x05, 0xfb, 0x0b, 0xd9, 0x68, 0x8b, 0x00,
+        0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_decodes_to_rgba() {
+        let image = Image::decode(TINY_PNG).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.data, [
+            255, 0, 0, 255, 0, 255, 0, 255,
+            0, 0, 255, 255, 255, 255, 255, 255,
+        ]);
+
+        // Cut off inside the pixel data
+        assert!(matches!(Image::decode(&TINY_PNG[..50]), Err(TextureError::Decode(_))));
+    }
+
+    #[test]
+    fn unknown_signatures_are_rejected() {
+        let err = Image::decode(b"GIF89a").unwrap_err();
+        assert_eq!(err.to_string(), "failed to decode image: unrecognized image format");
+    }
+
+    #[test]
+    fn fewer_channels_expand_to_rgba() {
+        assert_eq!(expand_to_rgba(&[10, 20], 1), [10, 10, 10, 255, 20, 20, 20, 255]);
+        assert_eq!(expand_to_rgba(&[10, 128], 2), [10, 10, 10, 128]);
+        assert_eq!(expand_to_rgba(&[1, 2, 3], 3), [1, 2, 3, 255]);
+        assert_eq!(expand_to_rgba(&[1, 2, 3, 4], 4), [1, 2, 3, 4]);
+    }
+}
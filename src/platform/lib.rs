@@ -4,9 +4,14 @@ pub mod window;
 pub mod area;
 pub mod pass;
 pub mod draw_list;
+pub mod dirty_regions;
 pub mod texture;
+pub mod image;
 pub mod geometry;
 pub mod shader;
+pub mod shader_reflect;
+#[cfg(feature = "naga")]
+pub mod shader_naga;
 pub mod gpu_info;
 pub mod os;
 pub mod math;
@@ -18,12 +23,18 @@ pub use crate::platform::cx::Cx;
 pub use crate::platform::event::{Event, EventHandler};
 pub use crate::platform::window::{WindowId, WindowHandle, WindowGeom};
 pub use crate::platform::area::Area;
-pub use crate::platform::pass::{PassId, Pass, PassClearColor, PassClearDepth};
-pub use crate::platform::draw_list::{DrawListId, DrawList};
-pub use crate::platform::texture::{Texture, TextureFormat};
+pub use crate::platform::pass::{PassId, Pass, PassParent, PassClearColor, PassClearDepth, PassGraph, PassError, GroupTarget};
+pub use crate::platform::draw_list::{DrawListId, DrawList, DrawOrderItem, BlendMode, BlendFactor, BlendState};
+pub use crate::platform::dirty_regions::DirtyRegions;
+pub use crate::platform::texture::{Texture, TextureFormat, TextureRegion, TextureError};
+pub use crate::platform::image::Image;
 pub use crate::platform::geometry::{Geometry, GeometryId};
-pub use crate::platform::shader::{Shader, ShaderId};
-pub use crate::platform::math::{Vec2, Vec3, Vec4, Mat4};
+pub use crate::platform::shader::{Shader, ShaderId, UniformError};
+pub use crate::platform::shader_reflect::{ShaderError, ShaderStage, ShaderReflection};
+#[cfg(feature = "naga")]
+pub use crate::platform::shader_naga::{ShaderTarget, CompiledShader};
+pub use crate::platform::debug::ContrastSample;
+pub use crate::platform::math::{Vec2, Vec3, Vec4, Mat4, Affine2};
 
 
 
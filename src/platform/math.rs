@@ -1,5 +1,7 @@
 // Basic math types for mix
 
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec2 {
     pub x: f32,
@@ -18,6 +20,52 @@ impl Vec2 {
     pub fn one() -> Self {
         Self { x: 1.0, y: 1.0 }
     }
+    
+    pub fn dot(&self, other: Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+    
+    // z component of the 3D cross product; positive when `other` is counter-clockwise
+    pub fn cross(&self, other: Vec2) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+    
+    pub fn perp(&self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
+    }
+    
+    pub fn length_squared(&self) -> f32 {
+        self.dot(*self)
+    }
+    
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+    
+    pub fn distance(&self, other: Vec2) -> f32 {
+        (other - *self).length()
+    }
+    
+    pub fn normalize(&self) -> Vec2 {
+        let length = self.length();
+        if length > 0.0 { *self / length } else { Vec2::zero() }
+    }
+    
+    pub fn lerp(&self, other: Vec2, t: f32) -> Vec2 {
+        *self + (other - *self) * t
+    }
+    
+    pub fn min(&self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x.min(other.x), self.y.min(other.y))
+    }
+    
+    pub fn max(&self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x.max(other.x), self.y.max(other.y))
+    }
+    
+    pub fn abs(&self) -> Vec2 {
+        Vec2::new(self.x.abs(), self.y.abs())
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -39,6 +87,51 @@ impl Vec3 {
     pub fn one() -> Self {
         Self { x: 1.0, y: 1.0, z: 1.0 }
     }
+    
+    pub fn dot(&self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+    
+    pub fn cross(&self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+    
+    pub fn length_squared(&self) -> f32 {
+        self.dot(*self)
+    }
+    
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+    
+    pub fn distance(&self, other: Vec3) -> f32 {
+        (other - *self).length()
+    }
+    
+    pub fn normalize(&self) -> Vec3 {
+        let length = self.length();
+        if length > 0.0 { *self / length } else { Vec3::zero() }
+    }
+    
+    pub fn lerp(&self, other: Vec3, t: f32) -> Vec3 {
+        *self + (other - *self) * t
+    }
+    
+    pub fn min(&self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+    
+    pub fn max(&self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+    
+    pub fn extend(&self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -61,8 +154,136 @@ impl Vec4 {
     pub fn one() -> Self {
         Self { x: 1.0, y: 1.0, z: 1.0, w: 1.0 }
     }
+    
+    pub fn dot(&self, other: Vec4) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+    
+    pub fn length_squared(&self) -> f32 {
+        self.dot(*self)
+    }
+    
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+    
+    pub fn normalize(&self) -> Vec4 {
+        let length = self.length();
+        if length > 0.0 { *self / length } else { Vec4::zero() }
+    }
+    
+    pub fn lerp(&self, other: Vec4, t: f32) -> Vec4 {
+        *self + (other - *self) * t
+    }
+    
+    pub fn min(&self, other: Vec4) -> Vec4 {
+        Vec4::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z), self.w.min(other.w))
+    }
+    
+    pub fn max(&self, other: Vec4) -> Vec4 {
+        Vec4::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z), self.w.max(other.w))
+    }
+    
+    pub fn truncate(&self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
 }
 
+// Component-wise operators, plus scaling by an f32
+macro_rules! impl_vec_ops {
+    ($ty:ident { $($field:ident),+ }) => {
+        impl Add for $ty {
+            type Output = $ty;
+            fn add(self, rhs: $ty) -> $ty {
+                $ty { $($field: self.$field + rhs.$field),+ }
+            }
+        }
+        
+        impl Sub for $ty {
+            type Output = $ty;
+            fn sub(self, rhs: $ty) -> $ty {
+                $ty { $($field: self.$field - rhs.$field),+ }
+            }
+        }
+        
+        impl Mul for $ty {
+            type Output = $ty;
+            fn mul(self, rhs: $ty) -> $ty {
+                $ty { $($field: self.$field * rhs.$field),+ }
+            }
+        }
+        
+        impl Div for $ty {
+            type Output = $ty;
+            fn div(self, rhs: $ty) -> $ty {
+                $ty { $($field: self.$field / rhs.$field),+ }
+            }
+        }
+        
+        impl Mul<f32> for $ty {
+            type Output = $ty;
+            fn mul(self, rhs: f32) -> $ty {
+                $ty { $($field: self.$field * rhs),+ }
+            }
+        }
+        
+        impl Mul<$ty> for f32 {
+            type Output = $ty;
+            fn mul(self, rhs: $ty) -> $ty {
+                $ty { $($field: self * rhs.$field),+ }
+            }
+        }
+        
+        impl Div<f32> for $ty {
+            type Output = $ty;
+            fn div(self, rhs: f32) -> $ty {
+                $ty { $($field: self.$field / rhs),+ }
+            }
+        }
+        
+        impl Neg for $ty {
+            type Output = $ty;
+            fn neg(self) -> $ty {
+                $ty { $($field: -self.$field),+ }
+            }
+        }
+        
+        impl AddAssign for $ty {
+            fn add_assign(&mut self, rhs: $ty) {
+                $(self.$field += rhs.$field;)+
+            }
+        }
+        
+        impl SubAssign for $ty {
+            fn sub_assign(&mut self, rhs: $ty) {
+                $(self.$field -= rhs.$field;)+
+            }
+        }
+        
+        impl MulAssign<f32> for $ty {
+            fn mul_assign(&mut self, rhs: f32) {
+                $(self.$field *= rhs;)+
+            }
+        }
+        
+        impl DivAssign<f32> for $ty {
+            fn div_assign(&mut self, rhs: f32) {
+                $(self.$field /= rhs;)+
+            }
+        }
+        
+        impl Default for $ty {
+            fn default() -> Self {
+                $ty::zero()
+            }
+        }
+    };
+}
+
+impl_vec_ops!(Vec2 { x, y });
+impl_vec_ops!(Vec3 { x, y, z });
+impl_vec_ops!(Vec4 { x, y, z, w });
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Mat4 {
     pub m: [f32; 16],
@@ -83,7 +304,465 @@ impl Mat4 {
             ],
         }
     }
+    
+    // Column-major, matching GLSL: element (row, col) is m[col * 4 + row]
+    pub fn get(&self, row: usize, col: usize) -> f32 {
+        self.m[col * 4 + row]
+    }
+    
+    pub fn from_cols(c0: Vec4, c1: Vec4, c2: Vec4, c3: Vec4) -> Self {
+        Self {
+            m: [
+                c0.x, c0.y, c0.z, c0.w,
+                c1.x, c1.y, c1.z, c1.w,
+                c2.x, c2.y, c2.z, c2.w,
+                c3.x, c3.y, c3.z, c3.w,
+            ],
+        }
+    }
+    
+    pub fn translation(x: f32, y: f32, z: f32) -> Self {
+        let mut m = Self::identity();
+        m.m[12] = x;
+        m.m[13] = y;
+        m.m[14] = z;
+        m
+    }
+    
+    pub fn scale(x: f32, y: f32, z: f32) -> Self {
+        let mut m = Self::identity();
+        m.m[0] = x;
+        m.m[5] = y;
+        m.m[10] = z;
+        m
+    }
+    
+    // Right-handed rotation of `radians` around `axis`
+    pub fn rotation(axis: Vec3, radians: f32) -> Self {
+        let axis = axis.normalize();
+        let (sin, cos) = radians.sin_cos();
+        let t = 1.0 - cos;
+        let (x, y, z) = (axis.x, axis.y, axis.z);
+        Self {
+            m: [
+                t * x * x + cos,     t * x * y + sin * z, t * x * z - sin * y, 0.0,
+                t * x * y - sin * z, t * y * y + cos,     t * y * z + sin * x, 0.0,
+                t * x * z + sin * y, t * y * z - sin * x, t * z * z + cos,     0.0,
+                0.0,                 0.0,                 0.0,                 1.0,
+            ],
+        }
+    }
+    
+    pub fn rotation_x(radians: f32) -> Self {
+        Self::rotation(Vec3::new(1.0, 0.0, 0.0), radians)
+    }
+    
+    pub fn rotation_y(radians: f32) -> Self {
+        Self::rotation(Vec3::new(0.0, 1.0, 0.0), radians)
+    }
+    
+    pub fn rotation_z(radians: f32) -> Self {
+        Self::rotation(Vec3::new(0.0, 0.0, 1.0), radians)
+    }
+    
+    // OpenGL-style clip space (z in -1..1)
+    pub fn ortho(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        let rl = right - left;
+        let tb = top - bottom;
+        let fun = far - near;
+        Self {
+            m: [
+                2.0 / rl, 0.0, 0.0, 0.0,
+                0.0, 2.0 / tb, 0.0, 0.0,
+                0.0, 0.0, -2.0 / fun, 0.0,
+                -(right + left) / rl, -(top + bottom) / tb, -(far + near) / fun, 1.0,
+            ],
+        }
+    }
+    
+    // `fov_y` in radians, OpenGL-style clip space
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let f = 1.0 / (fov_y * 0.5).tan();
+        let nf = near - far;
+        Self {
+            m: [
+                f / aspect, 0.0, 0.0, 0.0,
+                0.0, f, 0.0, 0.0,
+                0.0, 0.0, (far + near) / nf, -1.0,
+                0.0, 0.0, 2.0 * far * near / nf, 0.0,
+            ],
+        }
+    }
+    
+    // Right-handed view matrix looking from `eye` towards `target`
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        let forward = (target - eye).normalize();
+        let side = forward.cross(up).normalize();
+        let up = side.cross(forward);
+        Self {
+            m: [
+                side.x, up.x, -forward.x, 0.0,
+                side.y, up.y, -forward.y, 0.0,
+                side.z, up.z, -forward.z, 0.0,
+                -side.dot(eye), -up.dot(eye), forward.dot(eye), 1.0,
+            ],
+        }
+    }
+    
+    pub fn transpose(&self) -> Self {
+        let mut m = [0.0; 16];
+        for row in 0..4 {
+            for col in 0..4 {
+                m[row * 4 + col] = self.m[col * 4 + row];
+            }
+        }
+        Self { m }
+    }
+    
+    pub fn determinant(&self) -> f32 {
+        let c = self.cofactors();
+        let m = &self.m;
+        m[0] * c[0] + m[1] * c[4] + m[2] * c[8] + m[3] * c[12]
+    }
+    
+    pub fn inverse(&self) -> Option<Self> {
+        let inv = self.cofactors();
+        let m = &self.m;
+        let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+        // |det| is at most the product of the row lengths, and only gets
+        // close to zero relative to it when the rows are nearly dependent
+        let row_lengths: f32 = (0..4)
+            .map(|row| (0..4).map(|col| m[col * 4 + row].powi(2)).sum::<f32>().sqrt())
+            .product();
+        if !is_invertible(det, row_lengths) {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        Some(Self { m: inv.map(|v| v * inv_det) })
+    }
+    
+    // Adjugate matrix (transposed cofactors), as used by `inverse`
+    fn cofactors(&self) -> [f32; 16] {
+        let m = &self.m;
+        let mut inv = [0.0; 16];
+        inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+            + m[9] * m[7] * m[14] + m[13] * m[6] * m[11] - m[13] * m[7] * m[10];
+        inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+            - m[8] * m[7] * m[14] - m[12] * m[6] * m[11] + m[12] * m[7] * m[10];
+        inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+            + m[8] * m[7] * m[13] + m[12] * m[5] * m[11] - m[12] * m[7] * m[9];
+        inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+            - m[8] * m[6] * m[13] - m[12] * m[5] * m[10] + m[12] * m[6] * m[9];
+        inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+            - m[9] * m[3] * m[14] - m[13] * m[2] * m[11] + m[13] * m[3] * m[10];
+        inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+            + m[8] * m[3] * m[14] + m[12] * m[2] * m[11] - m[12] * m[3] * m[10];
+        inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+            - m[8] * m[3] * m[13] - m[12] * m[1] * m[11] + m[12] * m[3] * m[9];
+        inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+            + m[8] * m[2] * m[13] + m[12] * m[1] * m[10] - m[12] * m[2] * m[9];
+        inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+            + m[5] * m[3] * m[14] + m[13] * m[2] * m[7] - m[13] * m[3] * m[6];
+        inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+            - m[4] * m[3] * m[14] - m[12] * m[2] * m[7] + m[12] * m[3] * m[6];
+        inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+            + m[4] * m[3] * m[13] + m[12] * m[1] * m[7] - m[12] * m[3] * m[5];
+        inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+            - m[4] * m[2] * m[13] - m[12] * m[1] * m[6] + m[12] * m[2] * m[5];
+        inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+            - m[5] * m[3] * m[10] - m[9] * m[2] * m[7] + m[9] * m[3] * m[6];
+        inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+            + m[4] * m[3] * m[10] + m[8] * m[2] * m[7] - m[8] * m[3] * m[6];
+        inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+            - m[4] * m[3] * m[9] - m[8] * m[1] * m[7] + m[8] * m[3] * m[5];
+        inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+            + m[4] * m[2] * m[9] + m[8] * m[1] * m[6] - m[8] * m[2] * m[5];
+        inv
+    }
+    
+    // Transforms a point, dividing by w for projective matrices
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        let v = *self * point.extend(1.0);
+        if v.w != 0.0 && v.w != 1.0 {
+            v.truncate() / v.w
+        } else {
+            v.truncate()
+        }
+    }
+    
+    // Ignores the translation
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        (*self * vector.extend(0.0)).truncate()
+    }
+}
+
+
+
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut m = [0.0; 16];
+        for col in 0..4 {
+            for row in 0..4 {
+                m[col * 4 + row] = (0..4).map(|k| self.m[k * 4 + row] * rhs.m[col * 4 + k]).sum();
+            }
+        }
+        Mat4 { m }
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+    fn mul(self, v: Vec4) -> Vec4 {
+        let m = &self.m;
+        Vec4::new(
+            m[0] * v.x + m[4] * v.y + m[8] * v.z + m[12] * v.w,
+            m[1] * v.x + m[5] * v.y + m[9] * v.z + m[13] * v.w,
+            m[2] * v.x + m[6] * v.y + m[10] * v.z + m[14] * v.w,
+            m[3] * v.x + m[7] * v.y + m[11] * v.z + m[15] * v.w,
+        )
+    }
+}
+
+impl MulAssign for Mat4 {
+    fn mul_assign(&mut self, rhs: Mat4) {
+        *self = *self * rhs;
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::identity()
+    }
+}
+
+// 2D affine transform, column-major like Mat4:
+// x' = a * x + c * y + tx
+// y' = b * x + d * y + ty
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine2 {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub tx: f32,
+    pub ty: f32,
+}
+
+impl Affine2 {
+    pub fn new(a: f32, b: f32, c: f32, d: f32, tx: f32, ty: f32) -> Self {
+        Self { a, b, c, d, tx, ty }
+    }
+    
+    pub fn identity() -> Self {
+        Self { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: 0.0, ty: 0.0 }
+    }
+    
+    pub fn translation(x: f32, y: f32) -> Self {
+        Self { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: x, ty: y }
+    }
+    
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Self { a: sx, b: 0.0, c: 0.0, d: sy, tx: 0.0, ty: 0.0 }
+    }
+    
+    // Counter-clockwise in a y-up space, clockwise on screen where y points down
+    pub fn rotation(radians: f32) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Self { a: cos, b: sin, c: -sin, d: cos, tx: 0.0, ty: 0.0 }
+    }
+    
+    // Rotates or scales around `pivot` instead of the origin
+    pub fn around(pivot: Vec2, transform: Affine2) -> Self {
+        Affine2::translation(pivot.x, pivot.y)
+            .multiply(&transform)
+            .multiply(&Affine2::translation(-pivot.x, -pivot.y))
+    }
+    
+    // `self * other`: applies `other` first, then `self`
+    pub fn multiply(&self, other: &Affine2) -> Affine2 {
+        Affine2 {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            tx: self.a * other.tx + self.c * other.ty + self.tx,
+            ty: self.b * other.tx + self.d * other.ty + self.ty,
+        }
+    }
+    
+    pub fn determinant(&self) -> f32 {
+        self.a * self.d - self.b * self.c
+    }
+    
+    pub fn inverse(&self) -> Option<Affine2> {
+        let det = self.determinant();
+        let column_lengths = self.a.hypot(self.b) * self.c.hypot(self.d);
+        if !is_invertible(det, column_lengths) {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        Some(Affine2 {
+            a: self.d * inv_det,
+            b: -self.b * inv_det,
+            c: -self.c * inv_det,
+            d: self.a * inv_det,
+            tx: (self.c * self.ty - self.d * self.tx) * inv_det,
+            ty: (self.b * self.tx - self.a * self.ty) * inv_det,
+        })
+    }
+    
+    pub fn transform_point(&self, point: Vec2) -> Vec2 {
+        Vec2::new(
+            self.a * point.x + self.c * point.y + self.tx,
+            self.b * point.x + self.d * point.y + self.ty,
+        )
+    }
+    
+    // Ignores the translation
+    pub fn transform_vector(&self, vector: Vec2) -> Vec2 {
+        Vec2::new(
+            self.a * vector.x + self.c * vector.y,
+            self.b * vector.x + self.d * vector.y,
+        )
+    }
+    
+    pub fn is_identity(&self) -> bool {
+        *self == Affine2::identity()
+    }
+    
+    pub fn to_mat4(&self) -> Mat4 {
+        Mat4::new([
+            self.a, self.b, 0.0, 0.0,
+            self.c, self.d, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            self.tx, self.ty, 0.0, 1.0,
+        ])
+    }
+}
+
+impl Mul for Affine2 {
+    type Output = Affine2;
+    fn mul(self, rhs: Affine2) -> Affine2 {
+        self.multiply(&rhs)
+    }
+}
+
+impl MulAssign for Affine2 {
+    fn mul_assign(&mut self, rhs: Affine2) {
+        *self = self.multiply(&rhs);
+    }
+}
+
+impl Mul<Vec2> for Affine2 {
+    type Output = Vec2;
+    fn mul(self, point: Vec2) -> Vec2 {
+        self.transform_point(point)
+    }
+}
+
+impl Default for Affine2 {
+    fn default() -> Self {
+        Affine2::identity()
+    }
+}
+
+// Compares the determinant against the largest value it could have for
+// vectors of the same lengths, so the scale of a transform doesn't matter
+fn is_invertible(det: f32, length_product: f32) -> bool {
+    det != 0.0 && det.is_finite() && det.abs() > length_product * f32::EPSILON
+}
+
+// sRGB transfer function, for colors stored gamma-encoded
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Interop with other math crates through mint
+#[cfg(feature = "mint")]
+mod mint_interop {
+    use super::{Vec2, Vec3, Vec4, Mat4};
+    
+    impl From<mint::Vector2<f32>> for Vec2 {
+        fn from(v: mint::Vector2<f32>) -> Self {
+            Vec2::new(v.x, v.y)
+        }
+    }
+    
+    impl From<Vec2> for mint::Vector2<f32> {
+        fn from(v: Vec2) -> Self {
+            mint::Vector2 { x: v.x, y: v.y }
+        }
+    }
+    
+    impl From<mint::Vector3<f32>> for Vec3 {
+        fn from(v: mint::Vector3<f32>) -> Self {
+            Vec3::new(v.x, v.y, v.z)
+        }
+    }
+    
+    impl From<Vec3> for mint::Vector3<f32> {
+        fn from(v: Vec3) -> Self {
+            mint::Vector3 { x: v.x, y: v.y, z: v.z }
+        }
+    }
+    
+    impl From<mint::Vector4<f32>> for Vec4 {
+        fn from(v: mint::Vector4<f32>) -> Self {
+            Vec4::new(v.x, v.y, v.z, v.w)
+        }
+    }
+    
+    impl From<Vec4> for mint::Vector4<f32> {
+        fn from(v: Vec4) -> Self {
+            mint::Vector4 { x: v.x, y: v.y, z: v.z, w: v.w }
+        }
+    }
+    
+    impl From<mint::ColumnMatrix4<f32>> for Mat4 {
+        fn from(m: mint::ColumnMatrix4<f32>) -> Self {
+            Mat4::new(m.into())
+        }
+    }
+    
+    impl From<Mat4> for mint::ColumnMatrix4<f32> {
+        fn from(m: Mat4) -> Self {
+            m.m.into()
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    #[test]
+    fn small_uniform_scales_invert() {
+        let inverse = Affine2::scale(1e-4, 1e-4).inverse().unwrap();
+        assert!((inverse.a - 1e4).abs() < 1e-1);
 
+        let inverse = Mat4::scale(1e-2, 1e-2, 1e-2).inverse().unwrap();
+        assert!((inverse.m[0] - 1e2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn nearly_singular_transforms_do_not_invert() {
+        // Large, but the columns are parallel to within rounding
+        assert!(Affine2::new(1e4, 1e4, 1e4, 1e4 + 1e-3, 0.0, 0.0).inverse().is_none());
+        assert!(Affine2::scale(0.0, 1.0).inverse().is_none());
+        assert!(Mat4::scale(1e3, 1e3, 0.0).inverse().is_none());
+    }
+}
@@ -1,7 +1,9 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use crate::platform::math::Vec4;
 use crate::platform::window::WindowId;
-use crate::platform::draw_list::DrawListId;
-use crate::platform::texture::Texture;
+use crate::platform::draw_list::{self, DrawListId, DrawList};
+use crate::platform::texture::{Texture, TextureId};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PassId(pub usize);
@@ -103,5 +105,228 @@ impl Pass {
     }
 }
 
+// Offscreen pass behind one `Cx2d::push_opacity` group. Cx keeps these and
+// hands the same one to the group at the same position next frame.
+#[derive(Clone, Debug)]
+pub struct GroupTarget {
+    pub pass_id: PassId,
+    // What the group draws, the main list of the offscreen pass
+    pub content_list: DrawListId,
+    // The pass's texture drawn back into the parent
+    pub composite_list: DrawListId,
+    pub opacity: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PassError {
+    // Passes that depend on each other's output, in dependency order. A pass
+    // sampling its own render target is a cycle of one.
+    Cycle(Vec<PassId>),
+}
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PassError::Cycle(cycle) => {
+                let ids: Vec<String> = cycle.iter().map(|id| id.0.to_string()).collect();
+                write!(f, "pass dependency cycle: {}", ids.join(" -> "))
+            },
+        }
+    }
+}
+
+impl std::error::Error for PassError {}
+
+// Which passes must render before which. A pass depends on its child passes
+// (those with `PassParent::Pass` pointing at it) and on every pass whose
+// color or depth texture its draw list samples.
+#[derive(Clone, Debug, Default)]
+pub struct PassGraph {
+    dependencies: HashMap<PassId, Vec<PassId>>,
+}
+
+impl PassGraph {
+    pub fn build(passes: &HashMap<PassId, Pass>, draw_lists: &HashMap<DrawListId, DrawList>) -> Self {
+        let mut targets: HashMap<TextureId, PassId> = HashMap::new();
+        for pass in passes.values() {
+            for texture in pass.color_texture.iter().chain(&pass.depth_texture) {
+                targets.insert(texture.texture_id, pass.pass_id);
+            }
+        }
+
+        let mut dependencies: HashMap<PassId, Vec<PassId>> = passes.keys().map(|id| (*id, Vec::new())).collect();
+        for pass in passes.values() {
+            if let PassParent::Pass(parent) = pass.parent {
+                if let Some(deps) = dependencies.get_mut(&parent) {
+                    deps.push(pass.pass_id);
+                }
+            }
+
+            let Some(root) = pass.main_draw_list_id else {
+                continue;
+            };
+            for entry in draw_list::draw_order(root, draw_lists, pass.zbias_step) {
+                let item = &draw_lists[&entry.draw_list_id].draw_items[entry.item_index];
+                for texture in &item.textures {
+                    if let Some(source) = targets.get(&texture.texture_id) {
+                        dependencies.get_mut(&pass.pass_id).unwrap().push(*source);
+                    }
+                }
+            }
+        }
+
+        for deps in dependencies.values_mut() {
+            deps.sort_by_key(|id| id.0);
+            deps.dedup();
+        }
+        Self { dependencies }
+    }
+
+    // Passes that have to render before `pass_id`
+    pub fn dependencies(&self, pass_id: PassId) -> &[PassId] {
+        self.dependencies.get(&pass_id).map(|deps| deps.as_slice()).unwrap_or(&[])
+    }
 
+    // Every pass after the passes it depends on, ties broken by id so the
+    // order is stable between frames
+    pub fn sort(&self) -> Result<Vec<PassId>, PassError> {
+        let mut ids: Vec<PassId> = self.dependencies.keys().copied().collect();
+        ids.sort_by_key(|id| id.0);
 
+        let mut order = Vec::with_capacity(ids.len());
+        let mut done = HashSet::new();
+        while order.len() < ids.len() {
+            let ready: Vec<PassId> = ids
+                .iter()
+                .filter(|id| !done.contains(*id) && self.dependencies(**id).iter().all(|dep| done.contains(dep)))
+                .copied()
+                .collect();
+            if ready.is_empty() {
+                return Err(PassError::Cycle(self.find_cycle(&done)));
+            }
+            done.extend(ready.iter().copied());
+            order.extend(ready);
+        }
+        Ok(order)
+    }
+
+    // Follows unsorted dependencies from the lowest unsorted pass until one
+    // repeats. Every unsorted pass has an unsorted dependency, so this ends.
+    fn find_cycle(&self, done: &HashSet<PassId>) -> Vec<PassId> {
+        let mut path: Vec<PassId> = Vec::new();
+        let mut current = self
+            .dependencies
+            .keys()
+            .filter(|id| !done.contains(*id))
+            .min_by_key(|id| id.0)
+            .copied();
+        while let Some(pass_id) = current {
+            if let Some(start) = path.iter().position(|id| *id == pass_id) {
+                let mut cycle = path.split_off(start);
+                cycle.reverse();
+                return cycle;
+            }
+            path.push(pass_id);
+            current = self.dependencies(pass_id).iter().find(|dep| !done.contains(*dep)).copied();
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Cx;
+    use crate::platform::draw_list::{DrawItem, BlendMode};
+    use crate::platform::shader::ShaderId;
+    use crate::platform::geometry::GeometryId;
+    use crate::platform::math::Affine2;
+    use crate::platform::texture::TextureFormat;
+
+    // Makes `pass` draw an item sampling the color texture of `source`
+    fn sample(cx: &mut Cx, pass: PassId, source: PassId) {
+        let texture = match &cx.passes[&source].color_texture {
+            Some(texture) => texture.clone(),
+            None => {
+                let texture = cx.create_texture(4, 4, TextureFormat::Rgba8);
+                cx.passes.get_mut(&source).unwrap().set_color_texture(texture.clone());
+                texture
+            },
+        };
+        let draw_list_id = match cx.passes[&pass].main_draw_list_id {
+            Some(draw_list_id) => draw_list_id,
+            None => {
+                let draw_list_id = cx.create_draw_list();
+                cx.passes.get_mut(&pass).unwrap().set_main_draw_list(draw_list_id);
+                draw_list_id
+            },
+        };
+        let item = DrawItem {
+            shader_id: ShaderId(1),
+            geometry_id: GeometryId(1),
+            uniforms: Vec::new(),
+            textures: vec![texture],
+            instances: Vec::new(),
+            instance_count: 0,
+            clip: None,
+            transform: Affine2::identity(),
+            blend: BlendMode::Normal,
+            sub_list: None,
+        };
+        cx.draw_lists.get_mut(&draw_list_id).unwrap().append_draw_item(item);
+    }
+
+    #[test]
+    fn passes_sort_after_the_passes_they_sample_and_their_children() {
+        let mut cx = Cx::new();
+        let [window, group, nested, child] = [(); 4].map(|_| cx.create_pass());
+        sample(&mut cx, window, group);
+        sample(&mut cx, group, nested);
+        cx.passes.get_mut(&child).unwrap().set_pass_parent(window);
+
+        let graph = cx.pass_graph();
+        assert_eq!(graph.dependencies(window), &[group, child]);
+        assert_eq!(graph.sort(), Ok(vec![nested, child, group, window]));
+    }
+
+    #[test]
+    fn independent_passes_keep_id_order() {
+        let mut cx = Cx::new();
+        let passes = [(); 3].map(|_| cx.create_pass());
+        assert_eq!(cx.pass_graph().sort(), Ok(passes.to_vec()));
+    }
+
+    #[test]
+    fn cycles_report_their_path() {
+        let mut cx = Cx::new();
+        let [a, b, c, d] = [(); 4].map(|_| cx.create_pass());
+        // d sorts fine; a, b and c each need the next
+        sample(&mut cx, a, b);
+        sample(&mut cx, b, c);
+        sample(&mut cx, c, a);
+        sample(&mut cx, a, d);
+        assert_eq!(cx.pass_graph().sort(), Err(PassError::Cycle(vec![c, b, a])));
+
+        // A pass drawing its own target
+        let mut cx = Cx::new();
+        let pass = cx.create_pass();
+        sample(&mut cx, pass, pass);
+        assert_eq!(cx.pass_graph().sort(), Err(PassError::Cycle(vec![pass])));
+    }
+
+    #[test]
+    fn render_reports_a_cycle_once_and_keeps_the_last_order() {
+        let mut cx = Cx::new();
+        let [a, b] = [(); 2].map(|_| cx.create_pass());
+        cx.render();
+        assert_eq!(cx.pass_order, vec![a, b]);
+
+        sample(&mut cx, a, b);
+        sample(&mut cx, b, a);
+        cx.render();
+        cx.render();
+        assert_eq!(cx.pass_order, vec![a, b]);
+        assert_eq!(cx.pass_error, Some(PassError::Cycle(vec![b, a])));
+        assert_eq!(cx.debug.errors, vec![format!("pass dependency cycle: {} -> {}", b.0, a.0)]);
+    }
+}
@@ -7,6 +7,7 @@ pub struct PerformanceStats {
     pub frame_times: Vec<Duration>,
     pub last_frame_time: Instant,
     pub fps: f32,
+    pub start_time: Instant,
 }
 
 impl PerformanceStats {
@@ -17,6 +18,7 @@ impl PerformanceStats {
             frame_times: Vec::with_capacity(60),
             last_frame_time: Instant::now(),
             fps: 0.0,
+            start_time: Instant::now(),
         }
     }
     
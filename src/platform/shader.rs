@@ -1,3 +1,10 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use crate::platform::geometry::{Geometry, VertexAttribute, VertexFormat};
+use crate::platform::draw_list::DrawItem;
+use crate::platform::shader_reflect::{ShaderError, ShaderStage, ShaderVariable};
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct ShaderId(pub usize);
 
@@ -7,24 +14,70 @@ pub struct Shader {
     pub vertex_shader: String,
     pub fragment_shader: String,
     pub uniforms: Vec<ShaderUniform>,
+    pub instance_attributes: Vec<VertexAttribute>,
+    // Vertex shader inputs reflected by ShaderBuilder; empty for shaders
+    // assembled by hand, which skips the geometry check
+    pub inputs: Vec<ShaderVariable>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ShaderUniform {
     pub name: String,
     pub uniform_type: ShaderUniformType,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ShaderUniformType {
     Float,
     Vec2,
     Vec3,
     Vec4,
     Mat4,
+    // `vec4 name[len]`
+    Vec4Array(usize),
     Texture2D,
 }
 
+// Everything wrong with one draw item's uniforms, so a single report covers
+// the whole mismatch
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UniformError {
+    pub missing: Vec<String>,
+    // Undeclared, or bound more than once
+    pub extra: Vec<String>,
+    // (name, declared, bound)
+    pub mistyped: Vec<(String, ShaderUniformType, ShaderUniformType)>,
+    // (Texture2D uniforms, bound textures) when they differ
+    pub texture_count: Option<(usize, usize)>,
+}
+
+impl UniformError {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mistyped.is_empty() && self.texture_count.is_none()
+    }
+}
+
+impl fmt::Display for UniformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut problems = Vec::new();
+        if !self.missing.is_empty() {
+            problems.push(format!("missing uniforms: {}", self.missing.join(", ")));
+        }
+        if !self.extra.is_empty() {
+            problems.push(format!("extra uniforms: {}", self.extra.join(", ")));
+        }
+        for (name, declared, bound) in &self.mistyped {
+            problems.push(format!("uniform {} is declared {:?} but bound as {:?}", name, declared, bound));
+        }
+        if let Some((declared, bound)) = self.texture_count {
+            problems.push(format!("{} texture uniforms but {} textures bound", declared, bound));
+        }
+        write!(f, "{}", problems.join("; "))
+    }
+}
+
+impl std::error::Error for UniformError {}
+
 impl Shader {
     pub fn new(shader_id: ShaderId) -> Self {
         Self {
@@ -32,6 +85,8 @@ impl Shader {
             vertex_shader: String::new(),
             fragment_shader: String::new(),
             uniforms: Vec::new(),
+            instance_attributes: Vec::new(),
+            inputs: Vec::new(),
         }
     }
     
@@ -49,7 +104,231 @@ impl Shader {
             uniform_type,
         });
     }
+    
+    // Instance attributes are packed back to back in declaration order
+    pub fn add_instance_attribute(&mut self, name: &str, format: VertexFormat) {
+        let offset = self.instance_stride();
+        self.instance_attributes.push(VertexAttribute {
+            name: name.to_string(),
+            offset,
+            format,
+        });
+    }
+    
+    // Size of one instance in bytes
+    pub fn instance_stride(&self) -> usize {
+        self.instance_attributes
+            .iter()
+            .map(|a| a.offset + a.format.size())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Identity for Cx's shader cache: sources and layout, not the id.
+    // Reflected inputs follow from the vertex source.
+    pub fn content_eq(&self, other: &Shader) -> bool {
+        self.vertex_shader == other.vertex_shader
+            && self.fragment_shader == other.fragment_shader
+            && self.uniforms == other.uniforms
+            && self.instance_attributes == other.instance_attributes
+    }
+
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.vertex_shader.hash(&mut hasher);
+        self.fragment_shader.hash(&mut hasher);
+        self.uniforms.hash(&mut hasher);
+        self.instance_attributes.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Checks a draw item's bindings against the declared uniforms. Every
+    // non-texture uniform must be bound exactly once with its declared type,
+    // and each Texture2D uniform takes one texture slot.
+    pub fn validate_draw_item(&self, draw_item: &DrawItem) -> Result<(), UniformError> {
+        let mut error = UniformError::default();
+
+        for uniform in self.uniforms.iter().filter(|u| u.uniform_type != ShaderUniformType::Texture2D) {
+            match draw_item.uniforms.iter().find(|b| b.name == uniform.name) {
+                Some(binding) => {
+                    let bound = binding.value.uniform_type();
+                    if bound != uniform.uniform_type {
+                        error.mistyped.push((uniform.name.clone(), uniform.uniform_type, bound));
+                    }
+                },
+                None => error.missing.push(uniform.name.clone()),
+            }
+        }
+
+        for (i, binding) in draw_item.uniforms.iter().enumerate() {
+            let declared = self
+                .uniforms
+                .iter()
+                .any(|u| u.name == binding.name && u.uniform_type != ShaderUniformType::Texture2D);
+            let duplicate = draw_item.uniforms[..i].iter().any(|b| b.name == binding.name);
+            if !declared || duplicate {
+                error.extra.push(binding.name.clone());
+            }
+        }
+
+        let texture_uniforms = self.uniforms.iter().filter(|u| u.uniform_type == ShaderUniformType::Texture2D).count();
+        if texture_uniforms != draw_item.textures.len() {
+            error.texture_count = Some((texture_uniforms, draw_item.textures.len()));
+        }
+
+        if error.is_empty() {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    // Every vertex shader input must come from the instance layout or the
+    // geometry, in a format its type can read
+    pub fn validate_geometry(&self, geometry: &Geometry) -> Result<(), ShaderError> {
+        for input in &self.inputs {
+            let attribute = self
+                .instance_attributes
+                .iter()
+                .chain(&geometry.vertex_attributes)
+                .find(|a| a.name == input.name);
+            match attribute {
+                Some(attribute) if !input.accepts_format(attribute.format) => {
+                    return Err(ShaderError::at(
+                        ShaderStage::Vertex,
+                        input.line,
+                        format!("`{}` is {} but its attribute is {:?}", input.name, input.glsl_type, attribute.format),
+                    ));
+                },
+                Some(_) => {},
+                None => {
+                    return Err(ShaderError::at(
+                        ShaderStage::Vertex,
+                        input.line,
+                        format!("`{}` is neither an instance attribute nor in geometry {}", input.name, geometry.geometry_id.0),
+                    ));
+                },
+            }
+        }
+        Ok(())
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::draw_list::{BlendMode, DrawUniform, UniformBinding};
+    use crate::platform::geometry::GeometryId;
+    use crate::platform::math::Affine2;
+    use crate::platform::texture::{Texture, TextureId, TextureFormat};
+    use crate::platform::Cx;
 
+    fn shader() -> Shader {
+        let mut shader = Shader::new(ShaderId(1));
+        shader.add_uniform("color", ShaderUniformType::Vec4);
+        shader.add_uniform("radius", ShaderUniformType::Float);
+        shader.add_uniform("image", ShaderUniformType::Texture2D);
+        shader
+    }
 
+    fn draw_item(uniforms: Vec<UniformBinding>, textures: usize) -> DrawItem {
+        DrawItem {
+            shader_id: ShaderId(1),
+            geometry_id: GeometryId(1),
+            uniforms,
+            textures: (0..textures).map(|i| Texture::new(TextureId(i), 1, 1, TextureFormat::Rgba8)).collect(),
+            instances: Vec::new(),
+            instance_count: 0,
+            clip: None,
+            transform: Affine2::identity(),
+            blend: BlendMode::Normal,
+            sub_list: None,
+        }
+    }
+
+    fn color() -> UniformBinding {
+        UniformBinding::new("color", DrawUniform::Vec4([1.0; 4]))
+    }
+
+    fn radius() -> UniformBinding {
+        UniformBinding::new("radius", DrawUniform::Float(2.0))
+    }
+
+    #[test]
+    fn matching_bindings_validate() {
+        assert_eq!(shader().validate_draw_item(&draw_item(vec![radius(), color()], 1)), Ok(()));
+    }
+
+    #[test]
+    fn unbound_uniforms_are_missing() {
+        let error = shader().validate_draw_item(&draw_item(vec![color()], 1)).unwrap_err();
+        assert_eq!(error, UniformError { missing: vec!["radius".to_string()], ..Default::default() });
+    }
+
+    #[test]
+    fn undeclared_and_repeated_bindings_are_extra() {
+        let glow = UniformBinding::new("glow", DrawUniform::Float(1.0));
+        let error = shader().validate_draw_item(&draw_item(vec![color(), radius(), glow, radius()], 1)).unwrap_err();
+        assert_eq!(error, UniformError { extra: vec!["glow".to_string(), "radius".to_string()], ..Default::default() });
+    }
+
+    #[test]
+    fn bindings_of_another_type_are_mistyped() {
+        let radius = UniformBinding::new("radius", DrawUniform::Vec2([1.0, 2.0]));
+        let error = shader().validate_draw_item(&draw_item(vec![color(), radius], 1)).unwrap_err();
+        assert_eq!(error, UniformError {
+            mistyped: vec![("radius".to_string(), ShaderUniformType::Float, ShaderUniformType::Vec2)],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn each_texture_uniform_takes_one_texture() {
+        let error = shader().validate_draw_item(&draw_item(vec![color(), radius()], 2)).unwrap_err();
+        assert_eq!(error, UniformError { texture_count: Some((1, 2)), ..Default::default() });
+    }
+
+    #[test]
+    fn cx_reports_a_broken_item_once_with_debug_logging_off() {
+        let mut cx = Cx::new();
+        let draw_list_id = cx.create_draw_list();
+        let shader_id = cx.register_shader(shader());
+        let mut item = draw_item(vec![color()], 1);
+        item.shader_id = shader_id;
+
+        cx.append_draw_item(draw_list_id, item.clone());
+        cx.append_draw_item(draw_list_id, item);
+        assert_eq!(cx.debug.errors, vec![format!("shader {}: missing uniforms: radius", shader_id.0)]);
+    }
+
+    fn source_shader(fragment: &str) -> Shader {
+        let mut shader = Shader::new(ShaderId(0));
+        shader.set_vertex_shader("#version 300 es\nvoid main() {}\n");
+        shader.set_fragment_shader(fragment);
+        shader.add_uniform("color", ShaderUniformType::Vec4);
+        shader.add_instance_attribute("rect", VertexFormat::Float4);
+        shader
+    }
+
+    #[test]
+    fn identical_shaders_share_an_id() {
+        let mut cx = Cx::new();
+        let fragment = "#version 300 es\nvoid main() {}\n";
+        let a = cx.register_shader(source_shader(fragment));
+        assert_eq!(cx.register_shader(source_shader(fragment)), a);
+        assert!(source_shader(fragment).content_eq(&cx.shaders[&a]));
+        assert_eq!(source_shader(fragment).content_hash(), cx.shaders[&a].content_hash());
+
+        let mut other_uniform = source_shader(fragment);
+        other_uniform.uniforms[0].uniform_type = ShaderUniformType::Vec2;
+        let mut other_instances = source_shader(fragment);
+        other_instances.add_instance_attribute("tint", VertexFormat::Float4);
+        let ids = [
+            cx.register_shader(source_shader("#version 300 es\nvoid main() { }\n")),
+            cx.register_shader(other_uniform),
+            cx.register_shader(other_instances),
+        ];
+        assert!(ids.iter().all(|id| *id != a));
+        assert!(ids[0] != ids[1] && ids[1] != ids[2] && ids[0] != ids[2]);
+    }
+}
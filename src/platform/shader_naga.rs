@@ -0,0 +1,204 @@
+use naga::back::{hlsl, spv, wgsl};
+use naga::front::glsl;
+use naga::valid::{Capabilities, ModuleInfo, ValidationFlags, Validator};
+use crate::platform::shader::Shader;
+use crate::platform::shader_reflect::{ShaderError, ShaderReflection, ShaderStage, ShaderVariable};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderTarget {
+    Wgsl,
+    Hlsl,
+    SpirV,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompiledShader {
+    Text(String),
+    SpirV(Vec<u32>),
+}
+
+impl Shader {
+    // Translates one stage for another backend. Bindings follow
+    // `to_vulkan_glsl`: plain uniforms in group 0, textures and samplers in
+    // group 1.
+    pub fn cross_compile(&self, stage: ShaderStage, target: ShaderTarget) -> Result<CompiledShader, ShaderError> {
+        let vertex = ShaderReflection::parse(&self.vertex_shader, ShaderStage::Vertex)?;
+        let fragment = ShaderReflection::parse(&self.fragment_shader, ShaderStage::Fragment)?;
+        let source = match stage {
+            ShaderStage::Vertex => &self.vertex_shader,
+            ShaderStage::Fragment => &self.fragment_shader,
+        };
+        let (module, info) = compile(source, stage, &vertex, &fragment)?;
+        let backend_error = |err: &dyn std::fmt::Display| ShaderError::in_stage(stage, format!("{:?} output failed: {}", target, err));
+
+        match target {
+            ShaderTarget::Wgsl => wgsl::write_string(&module, &info, wgsl::WriterFlags::empty())
+                .map(CompiledShader::Text)
+                .map_err(|err| backend_error(&err)),
+            ShaderTarget::Hlsl => {
+                let mut out = String::new();
+                let options = hlsl::Options::default();
+                let pipeline_options = hlsl::PipelineOptions::default();
+                hlsl::Writer::new(&mut out, &options, &pipeline_options)
+                    .write(&module, &info, None)
+                    .map_err(|err| backend_error(&err))?;
+                Ok(CompiledShader::Text(out))
+            },
+            ShaderTarget::SpirV => {
+                let pipeline_options = spv::PipelineOptions {
+                    shader_stage: naga_stage(stage),
+                    entry_point: "main".to_string(),
+                };
+                spv::write_vec(&module, &info, &spv::Options::default(), Some(&pipeline_options))
+                    .map(CompiledShader::SpirV)
+                    .map_err(|err| backend_error(&err))
+            },
+        }
+    }
+}
+
+// Parses and validates one stage with naga, reporting the first error at its
+// line in the original source
+pub(crate) fn compile(
+    source: &str,
+    stage: ShaderStage,
+    vertex: &ShaderReflection,
+    fragment: &ShaderReflection,
+) -> Result<(naga::Module, ModuleInfo), ShaderError> {
+    let translated = to_vulkan_glsl(source, stage, vertex, fragment);
+
+    let module = glsl::Frontend::default()
+        .parse(&glsl::Options::from(naga_stage(stage)), &translated)
+        .map_err(|errors| match errors.errors.first() {
+            Some(err) => ShaderError::at(stage, err.meta.location(&translated).line_number as usize, err.kind.to_string()),
+            None => ShaderError::in_stage(stage, "failed to parse"),
+        })?;
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|err| {
+            let message = err.as_inner().to_string();
+            match err.location(&translated) {
+                Some(location) => ShaderError::at(stage, location.line_number as usize, message),
+                None => ShaderError::in_stage(stage, message),
+            }
+        })?;
+
+    Ok((module, info))
+}
+
+// naga reads Vulkan-flavoured GLSL 4.50, so the ES source is rewritten in
+// place: explicit locations on inputs and outputs, each plain uniform in its
+// own block, and sampler2D split into a texture and a sampler. Declarations
+// keep their lines so naga's line numbers match the original source.
+fn to_vulkan_glsl(source: &str, stage: ShaderStage, vertex: &ShaderReflection, fragment: &ShaderReflection) -> String {
+    let reflection = match stage {
+        ShaderStage::Vertex => vertex,
+        ShaderStage::Fragment => fragment,
+    };
+
+    // Uniforms shared between stages need the same binding in both
+    let mut buffers: Vec<&str> = Vec::new();
+    let mut samplers: Vec<&str> = Vec::new();
+    for uniform in vertex.uniforms.iter().chain(&fragment.uniforms) {
+        let list = if uniform.glsl_type == "sampler2D" { &mut samplers } else { &mut buffers };
+        if !list.contains(&uniform.name.as_str()) {
+            list.push(&uniform.name);
+        }
+    }
+
+    let location = |variable: &ShaderVariable, list: &[ShaderVariable]| list.iter().position(|v| v.name == variable.name).unwrap_or(0);
+    let mut declarations: Vec<((usize, usize), String)> = Vec::new();
+    let mut declare = |span: (usize, usize), text: String| match declarations.iter_mut().find(|(s, _)| *s == span) {
+        Some((_, existing)) => {
+            existing.push(' ');
+            existing.push_str(&text);
+        },
+        None => declarations.push((span, text)),
+    };
+
+    for input in &reflection.inputs {
+        // Varyings are matched by name, so both stages use the vertex output's location
+        let index = match stage {
+            ShaderStage::Vertex => location(input, &reflection.inputs),
+            ShaderStage::Fragment => location(input, &vertex.outputs),
+        };
+        declare(input.span, format!("layout(location = {}) {}in {};", index, flat(input), declaration(input)));
+    }
+    for output in &reflection.outputs {
+        let index = location(output, &reflection.outputs);
+        declare(output.span, format!("layout(location = {}) {}out {};", index, flat(output), declaration(output)));
+    }
+    for uniform in &reflection.uniforms {
+        if uniform.glsl_type == "sampler2D" {
+            let binding = samplers.iter().position(|s| *s == uniform.name).unwrap_or(0) * 2;
+            declare(uniform.span, format!(
+                "layout(set = 1, binding = {}) uniform texture2D {}_texture; layout(set = 1, binding = {}) uniform sampler {}_sampler;",
+                binding, uniform.name, binding + 1, uniform.name
+            ));
+        } else {
+            let binding = buffers.iter().position(|b| *b == uniform.name).unwrap_or(0);
+            declare(uniform.span, format!(
+                "layout(set = 0, binding = {}) uniform Uniform_{} {{ {}; }};",
+                binding, uniform.name, declaration(uniform)
+            ));
+        }
+    }
+    declarations.sort_by_key(|(span, _)| span.0);
+
+    let sampler_names: Vec<&str> = reflection.uniforms.iter().filter(|u| u.glsl_type == "sampler2D").map(|u| u.name.as_str()).collect();
+    let mut out = String::with_capacity(source.len() * 2);
+    let mut cursor = 0;
+    for ((start, end), text) in declarations {
+        out.push_str(&replace_samplers(&source[cursor..start], &sampler_names));
+        out.push_str(&text);
+        out.push_str(&"\n".repeat(source[start..end].matches('\n').count()));
+        cursor = end;
+    }
+    out.push_str(&replace_samplers(&source[cursor..], &sampler_names));
+
+    out.replacen("#version 300 es", "#version 450", 1)
+}
+
+fn declaration(variable: &ShaderVariable) -> String {
+    match variable.array_len {
+        Some(len) => format!("{} {}[{}]", variable.glsl_type, variable.name, len),
+        None => format!("{} {}", variable.glsl_type, variable.name),
+    }
+}
+
+fn flat(variable: &ShaderVariable) -> &'static str {
+    if variable.flat { "flat " } else { "" }
+}
+
+// Uses of a sampler2D become a combined sampler built from its two halves
+fn replace_samplers(code: &str, names: &[&str]) -> String {
+    if names.is_empty() {
+        return code.to_string();
+    }
+    let mut out = String::with_capacity(code.len());
+    let mut identifier = String::new();
+    for c in code.chars().chain(std::iter::once('\0')) {
+        if c.is_alphanumeric() || c == '_' {
+            identifier.push(c);
+            continue;
+        }
+        if names.contains(&identifier.as_str()) {
+            out.push_str(&format!("sampler2D({0}_texture, {0}_sampler)", identifier));
+        } else {
+            out.push_str(&identifier);
+        }
+        identifier.clear();
+        if c != '\0' {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn naga_stage(stage: ShaderStage) -> naga::ShaderStage {
+    match stage {
+        ShaderStage::Vertex => naga::ShaderStage::Vertex,
+        ShaderStage::Fragment => naga::ShaderStage::Fragment,
+    }
+}
@@ -0,0 +1,533 @@
+use std::fmt;
+use crate::platform::geometry::VertexFormat;
+use crate::platform::shader::{ShaderUniform, ShaderUniformType};
+
+// Set by the backend for every draw item, so shaders declare them in source
+// but drawables never bind them
+pub const FRAMEWORK_UNIFORMS: &[&str] = &["view_transform", "model_transform", "clip_rect", "clip_radius"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex shader"),
+            ShaderStage::Fragment => write!(f, "fragment shader"),
+        }
+    }
+}
+
+// Line numbers are 1-based and count from the start of the stage's source
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderError {
+    pub stage: Option<ShaderStage>,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ShaderError {
+    pub fn at(stage: ShaderStage, line: usize, message: impl Into<String>) -> Self {
+        Self { stage: Some(stage), line: Some(line), message: message.into() }
+    }
+
+    pub fn in_stage(stage: ShaderStage, message: impl Into<String>) -> Self {
+        Self { stage: Some(stage), line: None, message: message.into() }
+    }
+
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { stage: None, line: None, message: message.into() }
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.stage, self.line) {
+            (Some(stage), Some(line)) => write!(f, "{}, line {}: {}", stage, line, self.message),
+            (Some(stage), None) => write!(f, "{}: {}", stage, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+// A global `in`, `out` or `uniform` declaration
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderVariable {
+    pub name: String,
+    pub glsl_type: String,
+    pub array_len: Option<usize>,
+    pub flat: bool,
+    pub line: usize,
+    // Byte range of the declaring statement, up to and including the `;`
+    pub(crate) span: (usize, usize),
+}
+
+impl ShaderVariable {
+    pub fn uniform_type(&self) -> Option<ShaderUniformType> {
+        match (self.glsl_type.as_str(), self.array_len) {
+            ("float", None) => Some(ShaderUniformType::Float),
+            ("vec2", None) => Some(ShaderUniformType::Vec2),
+            ("vec3", None) => Some(ShaderUniformType::Vec3),
+            ("vec4", None) => Some(ShaderUniformType::Vec4),
+            ("mat4", None) => Some(ShaderUniformType::Mat4),
+            ("vec4", Some(len)) => Some(ShaderUniformType::Vec4Array(len)),
+            ("sampler2D", None) => Some(ShaderUniformType::Texture2D),
+            _ => None,
+        }
+    }
+
+    // Whether a vertex attribute in `format` can feed this input. Integer
+    // formats are read normalized, so they land in float vectors.
+    pub fn accepts_format(&self, format: VertexFormat) -> bool {
+        if self.array_len.is_some() {
+            return false;
+        }
+        match self.glsl_type.as_str() {
+            "float" => format == VertexFormat::Float1,
+            "vec2" => matches!(format, VertexFormat::Float2 | VertexFormat::Short2 | VertexFormat::UShort2),
+            "vec3" => format == VertexFormat::Float3,
+            "vec4" => matches!(
+                format,
+                VertexFormat::Float4 | VertexFormat::Byte4 | VertexFormat::UByte4 | VertexFormat::Short4 | VertexFormat::UShort4
+            ),
+            _ => false,
+        }
+    }
+
+    fn type_name(&self) -> String {
+        match self.array_len {
+            Some(len) => format!("{}[{}]", self.glsl_type, len),
+            None => self.glsl_type.clone(),
+        }
+    }
+}
+
+// The interface of one GLSL ES 3.0 stage, read from its global declarations.
+// This is not a compiler: function bodies are only checked for balanced
+// brackets. The `naga` feature adds full validation on top.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShaderReflection {
+    pub inputs: Vec<ShaderVariable>,
+    pub outputs: Vec<ShaderVariable>,
+    pub uniforms: Vec<ShaderVariable>,
+}
+
+impl ShaderReflection {
+    pub fn parse(source: &str, stage: ShaderStage) -> Result<Self, ShaderError> {
+        let code = strip_directives(&strip_comments(source), stage)?;
+        let bytes = code.as_bytes();
+
+        let mut reflection = Self::default();
+        let mut has_main = false;
+        let mut braces: Vec<usize> = Vec::new();
+        let mut parens: Vec<usize> = Vec::new();
+        let mut statement_start = 0;
+        let mut line = 1;
+
+        for (i, c) in bytes.iter().enumerate() {
+            match c {
+                b'\n' => line += 1,
+                b'(' => parens.push(line),
+                b')' => {
+                    parens.pop().ok_or_else(|| ShaderError::at(stage, line, "unmatched ')'"))?;
+                },
+                b'{' => {
+                    if braces.is_empty() {
+                        let header = &code[statement_start..i];
+                        let compact: String = header.split_whitespace().collect();
+                        if compact == "voidmain()" || compact == "voidmain(void)" {
+                            has_main = true;
+                        }
+                        match header.split_whitespace().next() {
+                            Some("uniform") => {
+                                let start_line = line_at(&code, statement_start);
+                                return Err(ShaderError::at(stage, start_line, "uniform blocks are not supported"));
+                            },
+                            // A declaration missing its `;` runs into the
+                            // function after it
+                            Some("in" | "out") => {
+                                return Err(ShaderError::at(stage, line_at(&code, statement_start), "expected ';'"));
+                            },
+                            _ => {},
+                        }
+                    }
+                    braces.push(line);
+                },
+                b'}' => {
+                    if braces.pop().is_none() {
+                        return Err(ShaderError::at(stage, line, "unmatched '}'"));
+                    }
+                    if braces.is_empty() {
+                        statement_start = i + 1;
+                    }
+                },
+                b';' if braces.is_empty() && parens.is_empty() => {
+                    reflection.parse_declaration(&code, (statement_start, i + 1), stage)?;
+                    statement_start = i + 1;
+                },
+                _ => {},
+            }
+        }
+
+        if let Some(open) = braces.last() {
+            return Err(ShaderError::at(stage, *open, "unclosed '{'"));
+        }
+        if let Some(open) = parens.last() {
+            return Err(ShaderError::at(stage, *open, "unclosed '('"));
+        }
+        if !code[statement_start..].trim().is_empty() {
+            return Err(ShaderError::at(stage, line_at(&code, statement_start), "expected ';'"));
+        }
+        if !has_main {
+            return Err(ShaderError::in_stage(stage, "missing `void main()`"));
+        }
+
+        reflection.check_declarations(stage)?;
+        Ok(reflection)
+    }
+
+    // Every varying the fragment shader reads must be written by the vertex
+    // shader with the same type and interpolation
+    pub fn link(vertex: &ShaderReflection, fragment: &ShaderReflection) -> Result<(), ShaderError> {
+        for input in &fragment.inputs {
+            let Some(output) = vertex.outputs.iter().find(|o| o.name == input.name) else {
+                return Err(ShaderError::at(
+                    ShaderStage::Fragment,
+                    input.line,
+                    format!("`{}` is not an output of the vertex shader", input.name),
+                ));
+            };
+            if output.glsl_type != input.glsl_type || output.array_len != input.array_len {
+                return Err(ShaderError::at(
+                    ShaderStage::Fragment,
+                    input.line,
+                    format!("`{}` is {} here but {} in the vertex shader", input.name, input.type_name(), output.type_name()),
+                ));
+            }
+            if output.flat != input.flat {
+                return Err(ShaderError::at(
+                    ShaderStage::Fragment,
+                    input.line,
+                    format!("`{}` must be `flat` in both stages or neither", input.name),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Uniforms drawables bind, from both stages in declaration order, vertex
+    // first. Texture slots follow the order of the Texture2D entries.
+    pub fn shader_uniforms(vertex: &ShaderReflection, fragment: &ShaderReflection) -> Result<Vec<ShaderUniform>, ShaderError> {
+        let mut uniforms: Vec<ShaderUniform> = Vec::new();
+        let declared = vertex.uniforms.iter().map(|u| (ShaderStage::Vertex, u))
+            .chain(fragment.uniforms.iter().map(|u| (ShaderStage::Fragment, u)));
+
+        for (stage, variable) in declared {
+            if FRAMEWORK_UNIFORMS.contains(&variable.name.as_str()) {
+                continue;
+            }
+            // Types were checked when the stage was parsed
+            let uniform_type = variable.uniform_type().unwrap();
+            match uniforms.iter().find(|u| u.name == variable.name) {
+                Some(existing) if existing.uniform_type != uniform_type => {
+                    return Err(ShaderError::at(
+                        stage,
+                        variable.line,
+                        format!("uniform `{}` is declared with a different type in the vertex shader", variable.name),
+                    ));
+                },
+                Some(_) => {},
+                None => uniforms.push(ShaderUniform { name: variable.name.clone(), uniform_type }),
+            }
+        }
+        Ok(uniforms)
+    }
+
+    pub fn input(&self, name: &str) -> Option<&ShaderVariable> {
+        self.inputs.iter().find(|v| v.name == name)
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&ShaderVariable> {
+        self.uniforms.iter().find(|v| v.name == name)
+    }
+
+    // `[qualifiers] storage [qualifiers] type name[N], ...;` Statements
+    // without in/out/uniform storage (precision, const, prototypes) are skipped.
+    fn parse_declaration(&mut self, code: &str, span: (usize, usize), stage: ShaderStage) -> Result<(), ShaderError> {
+        let text = &code[span.0..span.1 - 1];
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        let line = line_at(code, span.0);
+        let tokens = tokenize(text);
+
+        let mut storage = None;
+        let mut flat = false;
+        let mut i = 0;
+        while i < tokens.len() {
+            match tokens[i].as_str() {
+                "in" | "out" | "uniform" => storage = Some(tokens[i].clone()),
+                "flat" => flat = true,
+                "smooth" | "centroid" | "invariant" | "highp" | "mediump" | "lowp" => {},
+                "precision" | "const" | "struct" => return Ok(()),
+                "layout" => {
+                    // Skip the parenthesized layout qualifiers
+                    while i < tokens.len() && tokens[i] != ")" {
+                        i += 1;
+                    }
+                },
+                _ => break,
+            }
+            i += 1;
+        }
+        let Some(storage) = storage else {
+            return Ok(());
+        };
+
+        let Some(glsl_type) = tokens.get(i).filter(|t| is_identifier(t)).cloned() else {
+            return Err(ShaderError::at(stage, line, format!("expected a type after `{}`", storage)));
+        };
+        i += 1;
+
+        loop {
+            let Some(name) = tokens.get(i).filter(|t| is_identifier(t)).cloned() else {
+                return Err(ShaderError::at(stage, line, format!("expected a variable name after `{}`", glsl_type)));
+            };
+            i += 1;
+
+            let mut array_len = None;
+            if tokens.get(i).map(|t| t.as_str()) == Some("[") {
+                let len = tokens.get(i + 1).and_then(|t| t.parse::<usize>().ok());
+                if len.is_none() || tokens.get(i + 2).map(|t| t.as_str()) != Some("]") {
+                    return Err(ShaderError::at(stage, line, format!("`{}` needs a constant array size", name)));
+                }
+                array_len = len;
+                i += 3;
+            }
+
+            let variable = ShaderVariable { name, glsl_type: glsl_type.clone(), array_len, flat, line, span };
+            match storage.as_str() {
+                "in" => self.inputs.push(variable),
+                "out" => self.outputs.push(variable),
+                _ => self.uniforms.push(variable),
+            }
+
+            match tokens.get(i).map(|t| t.as_str()) {
+                None => return Ok(()),
+                Some(",") => i += 1,
+                Some(token) => return Err(ShaderError::at(stage, line, format!("unexpected `{}` in declaration", token))),
+            }
+        }
+    }
+
+    fn check_declarations(&self, stage: ShaderStage) -> Result<(), ShaderError> {
+        for uniform in &self.uniforms {
+            if uniform.uniform_type().is_none() {
+                return Err(ShaderError::at(
+                    stage,
+                    uniform.line,
+                    format!("unsupported uniform type `{}` for `{}`", uniform.type_name(), uniform.name),
+                ));
+            }
+        }
+
+        let all: Vec<&ShaderVariable> = self.inputs.iter().chain(&self.outputs).chain(&self.uniforms).collect();
+        for (i, variable) in all.iter().enumerate() {
+            if all[..i].iter().any(|v| v.name == variable.name) {
+                return Err(ShaderError::at(stage, variable.line, format!("`{}` is declared twice", variable.name)));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Replaces comments with spaces, keeping newlines so offsets and line
+// numbers still match the original source
+fn strip_comments(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut out = bytes.to_vec();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"//") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                out[i] = b' ';
+                i += 1;
+            }
+        } else if bytes[i..].starts_with(b"/*") {
+            let end = source[i + 2..].find("*/").map(|e| i + 2 + e + 2).unwrap_or(bytes.len());
+            for byte in &mut out[i..end] {
+                if *byte != b'\n' {
+                    *byte = b' ';
+                }
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    // Only whole multi-byte characters were replaced, by ASCII spaces
+    String::from_utf8(out).unwrap_or_default()
+}
+
+// Checks that `#version 300 es` comes first and blanks out directive lines
+fn strip_directives(code: &str, stage: ShaderStage) -> Result<String, ShaderError> {
+    let mut out = String::with_capacity(code.len());
+    let mut seen_version = false;
+    for (index, line) in code.split('\n').enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            out.push_str(line);
+            continue;
+        }
+        let directive: Vec<&str> = trimmed.strip_prefix('#').map(|d| d.split_whitespace().collect()).unwrap_or_default();
+        if !seen_version {
+            if directive != ["version", "300", "es"] {
+                return Err(ShaderError::at(stage, index + 1, "shaders must start with `#version 300 es`"));
+            }
+            seen_version = true;
+        }
+        if trimmed.starts_with('#') {
+            out.push_str(&" ".repeat(line.len()));
+        } else {
+            out.push_str(line);
+        }
+    }
+    if !seen_version {
+        return Err(ShaderError::in_stage(stage, "shaders must start with `#version 300 es`"));
+    }
+    Ok(out)
+}
+
+fn line_at(code: &str, offset: usize) -> usize {
+    let start = code[offset..].find(|c: char| !c.is_whitespace()).map(|s| offset + s).unwrap_or(offset);
+    code[..start].matches('\n').count() + 1
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            current.push(c);
+            continue;
+        }
+        if !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+        if !c.is_whitespace() {
+            tokens.push(c.to_string());
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn is_identifier(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERTEX: &str = "#version 300 es
+in vec2 position;
+out vec2 uv;
+flat out vec4 tint;
+void main() {
+    uv = position;
+    tint = vec4(1.0);
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+";
+
+    fn fragment(declarations: &str) -> String {
+        format!("#version 300 es\nprecision highp float;\n{}\nout vec4 frag_color;\nvoid main() {{ frag_color = vec4(0.0); }}\n", declarations)
+    }
+
+    fn link(declarations: &str) -> Result<(), ShaderError> {
+        let vertex = ShaderReflection::parse(VERTEX, ShaderStage::Vertex).unwrap();
+        let fragment = ShaderReflection::parse(&fragment(declarations), ShaderStage::Fragment).unwrap();
+        ShaderReflection::link(&vertex, &fragment)
+    }
+
+    #[test]
+    fn parse_errors_carry_the_source_line() {
+        let source = "#version 300 es\n// comment\nin vec2 position\nvoid main() {}\n";
+        let err = ShaderReflection::parse(source, ShaderStage::Vertex).unwrap_err();
+        assert_eq!((err.stage, err.line), (Some(ShaderStage::Vertex), Some(3)));
+        assert_eq!(err.to_string(), "vertex shader, line 3: expected ';'");
+
+        let source = "#version 300 es\nvoid main() {\n    if (true) {\n}\n";
+        let err = ShaderReflection::parse(source, ShaderStage::Fragment).unwrap_err();
+        assert_eq!((err.line, err.message.as_str()), (Some(2), "unclosed '{'"));
+
+        let source = "\nprecision highp float;\n#version 300 es\nvoid main() {}\n";
+        assert_eq!(ShaderReflection::parse(source, ShaderStage::Vertex).unwrap_err().line, Some(2));
+        let source = "// first\n#version 300 es\nuniform float values[n];\nvoid main() {}\n";
+        assert_eq!(ShaderReflection::parse(source, ShaderStage::Vertex).unwrap_err().line, Some(3));
+    }
+
+    #[test]
+    fn linking_checks_names_types_and_interpolation() {
+        assert_eq!(link("in vec2 uv;\nflat in vec4 tint;"), Ok(()));
+
+        let err = link("in vec2 coord;").unwrap_err();
+        assert_eq!(err.line, Some(3));
+        assert_eq!(err.message, "`coord` is not an output of the vertex shader");
+
+        let err = link("in vec2 uv;\nflat in vec3 tint;").unwrap_err();
+        assert_eq!(err.line, Some(4));
+        assert_eq!(err.message, "`tint` is vec3 here but vec4 in the vertex shader");
+
+        let err = link("in vec2 uv;\nin vec4 tint;").unwrap_err();
+        assert_eq!(err.message, "`tint` must be `flat` in both stages or neither");
+    }
+
+    #[test]
+    fn declarations_read_arrays_qualifiers_and_skip_comments() {
+        let source = "#version 300 es
+precision mediump float;
+uniform highp vec4 stops[8], /* inline */ tint;
+layout(location = 0) in vec2 position; // trailing
+flat in /* split
+   across lines */ vec4 color;
+uniform sampler2D image;
+void main() {}
+";
+        let reflection = ShaderReflection::parse(source, ShaderStage::Vertex).unwrap();
+
+        let stops = reflection.uniform("stops").unwrap();
+        assert_eq!((stops.glsl_type.as_str(), stops.array_len, stops.line), ("vec4", Some(8), 3));
+        assert_eq!(stops.uniform_type(), Some(ShaderUniformType::Vec4Array(8)));
+        assert_eq!(reflection.uniform("tint").unwrap().uniform_type(), Some(ShaderUniformType::Vec4));
+        assert_eq!(reflection.uniform("image").unwrap().uniform_type(), Some(ShaderUniformType::Texture2D));
+
+        let position = reflection.input("position").unwrap();
+        assert_eq!((position.glsl_type.as_str(), position.flat), ("vec2", false));
+        let color = reflection.input("color").unwrap();
+        assert_eq!((color.glsl_type.as_str(), color.flat, color.line), ("vec4", true, 5));
+        assert_eq!(reflection.outputs, Vec::new());
+    }
+
+    #[test]
+    fn unsupported_and_repeated_declarations_are_rejected() {
+        let source = "#version 300 es\nuniform vec3 offsets[4];\nvoid main() {}\n";
+        let err = ShaderReflection::parse(source, ShaderStage::Vertex).unwrap_err();
+        assert_eq!((err.line, err.message.as_str()), (Some(2), "unsupported uniform type `vec3[4]` for `offsets`"));
+
+        let source = "#version 300 es\nin vec2 position;\nuniform float position;\nvoid main() {}\n";
+        let err = ShaderReflection::parse(source, ShaderStage::Vertex).unwrap_err();
+        assert_eq!((err.line, err.message.as_str()), (Some(3), "`position` is declared twice"));
+    }
+}
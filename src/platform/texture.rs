@@ -1,3 +1,5 @@
+use std::fmt;
+use std::sync::Arc;
 use crate::platform::math::Vec2;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -13,12 +15,87 @@ pub enum TextureFormat {
     Depth32,
 }
 
+impl TextureFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Bgra8 => 4,
+            TextureFormat::Rgb8 | TextureFormat::Bgr8 => 3,
+            TextureFormat::R8 => 1,
+            TextureFormat::Depth32 => 4,
+        }
+    }
+}
+
+// Pixel rectangle within a texture level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl TextureRegion {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self { x, y, width, height }
+    }
+
+    fn union(&self, other: &TextureRegion) -> TextureRegion {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        TextureRegion::new(x, y, right - x, bottom - y)
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    UnknownTexture(TextureId),
+    SizeMismatch { expected: usize, actual: usize },
+    OutOfBounds(TextureRegion),
+    // Mipmaps can't be averaged for depth textures
+    UnsupportedFormat(TextureFormat),
+    Decode(String),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::UnknownTexture(id) => write!(f, "no texture with id {}", id.0),
+            TextureError::SizeMismatch { expected, actual } => {
+                write!(f, "expected {} bytes of pixel data, got {}", expected, actual)
+            },
+            TextureError::OutOfBounds(region) => write!(
+                f,
+                "region {}x{} at ({}, {}) is outside the texture",
+                region.width, region.height, region.x, region.y
+            ),
+            TextureError::UnsupportedFormat(format) => write!(f, "unsupported texture format {:?}", format),
+            TextureError::Decode(err) => write!(f, "failed to decode image: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+// Textures are handles: clones share pixel data, and the copy in
+// `Cx::textures` is the one backends upload from. Changes go through the Cx
+// so that copy stays current.
 #[derive(Clone, Debug)]
 pub struct Texture {
     pub texture_id: TextureId,
     pub width: usize,
     pub height: usize,
     pub format: TextureFormat,
+    // Level 0 first, tightly packed rows. Empty until data is uploaded;
+    // render targets never get CPU-side data.
+    pub levels: Vec<Arc<Vec<u8>>>,
+    // Regenerate the mip chain whenever level 0 changes
+    pub mipmaps: bool,
+    // Part of level 0 changed since the backend last uploaded, or the whole
+    // texture when the mip chain changed too
+    pub dirty: Option<TextureRegion>,
 }
 
 impl Texture {
@@ -28,13 +105,226 @@ impl Texture {
             width,
             height,
             format,
+            levels: Vec::new(),
+            mipmaps: false,
+            dirty: None,
         }
     }
-    
+
     pub fn size(&self) -> Vec2 {
         Vec2::new(self.width as f32, self.height as f32)
     }
+
+    pub fn byte_len(&self) -> usize {
+        self.width * self.height * self.format.bytes_per_pixel()
+    }
+
+    pub fn has_data(&self) -> bool {
+        !self.levels.is_empty()
+    }
+
+    pub fn data(&self) -> Option<&[u8]> {
+        self.levels.first().map(|level| level.as_slice())
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn level_size(&self, level: usize) -> (usize, usize) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<&[u8]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let bpp = self.format.bytes_per_pixel();
+        let start = (y * self.width + x) * bpp;
+        self.data().map(|data| &data[start..start + bpp])
+    }
+
+    // Replaces all pixels
+    pub fn set_data(&mut self, data: Vec<u8>) -> Result<(), TextureError> {
+        if data.len() != self.byte_len() {
+            return Err(TextureError::SizeMismatch { expected: self.byte_len(), actual: data.len() });
+        }
+        self.levels = vec![Arc::new(data)];
+        self.mark_dirty(TextureRegion::new(0, 0, self.width, self.height));
+        if self.mipmaps {
+            self.generate_mipmaps()?;
+        }
+        Ok(())
+    }
+
+    // Writes tightly packed pixels into a sub-rect of level 0. A texture
+    // without data starts out zeroed.
+    pub fn update_region(&mut self, region: TextureRegion, data: &[u8]) -> Result<(), TextureError> {
+        if region.x + region.width > self.width || region.y + region.height > self.height {
+            return Err(TextureError::OutOfBounds(region));
+        }
+        let bpp = self.format.bytes_per_pixel();
+        let row_len = region.width * bpp;
+        if data.len() != row_len * region.height {
+            return Err(TextureError::SizeMismatch { expected: row_len * region.height, actual: data.len() });
+        }
+
+        if self.levels.is_empty() {
+            self.levels.push(Arc::new(vec![0; self.byte_len()]));
+        }
+        // Copies only if another handle still shares the old pixels
+        let pixels = Arc::make_mut(&mut self.levels[0]);
+        for (row, src) in data.chunks_exact(row_len.max(1)).enumerate() {
+            let start = ((region.y + row) * self.width + region.x) * bpp;
+            pixels[start..start + row_len].copy_from_slice(src);
+        }
+
+        self.mark_dirty(region);
+        if self.mipmaps {
+            self.generate_mipmaps()?;
+        }
+        Ok(())
+    }
+
+    // Box-filters level 0 down to 1x1 and keeps regenerating on later updates
+    pub fn generate_mipmaps(&mut self) -> Result<(), TextureError> {
+        if self.format == TextureFormat::Depth32 {
+            return Err(TextureError::UnsupportedFormat(self.format));
+        }
+        self.mipmaps = true;
+        if self.levels.is_empty() {
+            return Ok(());
+        }
+
+        let bpp = self.format.bytes_per_pixel();
+        self.levels.truncate(1);
+        let mut level = 0;
+        while self.level_size(level) != (1, 1) {
+            let (src_w, src_h) = self.level_size(level);
+            let (dst_w, dst_h) = self.level_size(level + 1);
+            let src = &self.levels[level];
+            let mut dst = vec![0u8; dst_w * dst_h * bpp];
+            for y in 0..dst_h {
+                for x in 0..dst_w {
+                    // Odd edges clamp, so the last row/column is counted twice
+                    let xs = [(x * 2).min(src_w - 1), (x * 2 + 1).min(src_w - 1)];
+                    let ys = [(y * 2).min(src_h - 1), (y * 2 + 1).min(src_h - 1)];
+                    for c in 0..bpp {
+                        let sum: u32 = ys
+                            .iter()
+                            .flat_map(|sy| xs.iter().map(move |sx| src[(sy * src_w + sx) * bpp + c] as u32))
+                            .sum();
+                        dst[(y * dst_w + x) * bpp + c] = ((sum + 2) / 4) as u8;
+                    }
+                }
+            }
+            self.levels.push(Arc::new(dst));
+            level += 1;
+        }
+
+        self.dirty = Some(TextureRegion::new(0, 0, self.width, self.height));
+        Ok(())
+    }
+
+    // For backends: the region to re-upload, cleared once taken
+    pub fn take_dirty(&mut self) -> Option<TextureRegion> {
+        self.dirty.take()
+    }
+
+    fn mark_dirty(&mut self, region: TextureRegion) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(&region),
+            None => region,
+        });
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A `width` x `height` R8 texture whose pixels hold `value(x, y)`
+    fn r8(width: usize, height: usize, value: impl Fn(usize, usize) -> u8) -> Texture {
+        let mut texture = Texture::new(TextureId(1), width, height, TextureFormat::R8);
+        let data = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| value(x, y)).collect();
+        texture.set_data(data).unwrap();
+        texture.take_dirty();
+        texture
+    }
+
+    fn rows(texture: &Texture, level: usize) -> Vec<Vec<u8>> {
+        let (width, _) = texture.level_size(level);
+        texture.levels[level].chunks(width).map(|row| row.to_vec()).collect()
+    }
+
+    #[test]
+    fn region_updates_write_their_rows_and_grow_the_dirty_region() {
+        let mut texture = r8(4, 3, |_, _| 0);
+        texture.update_region(TextureRegion::new(1, 1, 2, 2), &[1, 2, 3, 4]).unwrap();
+        assert_eq!(rows(&texture, 0), [[0, 0, 0, 0], [0, 1, 2, 0], [0, 3, 4, 0]]);
+        assert_eq!(texture.dirty, Some(TextureRegion::new(1, 1, 2, 2)));
+
+        texture.update_region(TextureRegion::new(3, 0, 1, 1), &[9]).unwrap();
+        assert_eq!(texture.pixel(3, 0), Some(&[9][..]));
+        assert_eq!(texture.take_dirty(), Some(TextureRegion::new(1, 0, 3, 3)));
+        assert_eq!(texture.dirty, None);
+    }
 
+    #[test]
+    fn region_updates_start_from_zeroed_pixels_and_keep_other_handles() {
+        let mut texture = Texture::new(TextureId(1), 2, 2, TextureFormat::Rgba8);
+        texture.update_region(TextureRegion::new(1, 0, 1, 1), &[1, 2, 3, 4]).unwrap();
+        assert_eq!(texture.data().unwrap(), &[0, 0, 0, 0, 1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let before = texture.clone();
+        texture.update_region(TextureRegion::new(0, 1, 1, 1), &[5, 6, 7, 8]).unwrap();
+        assert_eq!(before.pixel(0, 1), Some(&[0, 0, 0, 0][..]));
+        assert_eq!(texture.pixel(0, 1), Some(&[5, 6, 7, 8][..]));
+    }
 
+    #[test]
+    fn region_updates_outside_the_texture_or_of_the_wrong_size_are_rejected() {
+        let mut texture = r8(4, 4, |_, _| 7);
+        let region = TextureRegion::new(3, 0, 2, 1);
+        assert!(matches!(texture.update_region(region, &[1, 2]), Err(TextureError::OutOfBounds(r)) if r == region));
+        assert!(matches!(
+            texture.update_region(TextureRegion::new(0, 0, 2, 2), &[1, 2, 3]),
+            Err(TextureError::SizeMismatch { expected: 4, actual: 3 })
+        ));
+        // Nothing was written
+        assert!(texture.data().unwrap().iter().all(|v| *v == 7));
+        assert_eq!(texture.dirty, None);
+    }
+
+    #[test]
+    fn mipmaps_halve_down_to_one_pixel_averaging_each_block() {
+        let mut texture = r8(5, 2, |x, y| (x * 10 + y * 100) as u8);
+        texture.generate_mipmaps().unwrap();
+
+        assert_eq!(texture.level_count(), 3);
+        let sizes: Vec<_> = (0..3).map(|level| texture.level_size(level)).collect();
+        assert_eq!(sizes, [(5, 2), (2, 1), (1, 1)]);
+        let lens: Vec<_> = texture.levels.iter().map(|level| level.len()).collect();
+        assert_eq!(lens, [10, 2, 1]);
+        // (0 + 10 + 100 + 110) / 4 and (20 + 30 + 120 + 130) / 4; the odd
+        // fifth column is dropped at this size
+        assert_eq!(rows(&texture, 1), [[55, 75]]);
+        assert_eq!(rows(&texture, 2), [[65]]);
+        assert_eq!(texture.dirty, Some(TextureRegion::new(0, 0, 5, 2)));
+
+        // Later updates regenerate the chain
+        texture.update_region(TextureRegion::new(0, 0, 2, 2), &[255; 4]).unwrap();
+        assert_eq!(rows(&texture, 1), [[255, 75]]);
+    }
+
+    #[test]
+    fn one_texel_wide_levels_count_their_texel_twice() {
+        let mut texture = r8(1, 4, |_, y| [0, 100, 200, 40][y]);
+        texture.generate_mipmaps().unwrap();
+        assert_eq!(rows(&texture, 1), [[50], [120]]);
+        assert_eq!(rows(&texture, 2), [[85]]);
+
+        let mut depth = Texture::new(TextureId(2), 2, 2, TextureFormat::Depth32);
+        assert!(matches!(depth.generate_mipmaps(), Err(TextureError::UnsupportedFormat(TextureFormat::Depth32))));
+    }
+}
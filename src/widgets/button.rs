@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+use std::rc::Rc;
 use crate::platform::Cx;
 use crate::platform::event::{Event, MouseButton};
 use crate::platform::area::Area;
@@ -5,11 +7,15 @@ use crate::draw::Cx2d;
 use crate::draw::draw_list_2d::DrawList2d;
 use crate::draw::quad::DrawQuad;
 use crate::draw::text::DrawText;
+use crate::draw::font_collection::FontCollection;
 use crate::draw::color::Color;
 use crate::draw::math::Vec2;
 use crate::widgets::widget::{Widget, DrawStep};
 use crate::widgets::theme::Theme;
 
+// OKLCH lightness step between the normal, hovered and pressed backgrounds
+pub const BUTTON_STATE_SHIFT: f32 = 0.08;
+
 pub enum ButtonState {
     Normal,
     Hover,
@@ -20,10 +26,13 @@ pub enum ButtonState {
 pub struct Button {
     pub text: String,
     pub state: ButtonState,
+    // Background in the normal state; hover and press are derived from it
+    pub color: Color,
     pub draw_list: DrawList2d,
     pub area: Area,
     pub draw_bg: DrawQuad,
     pub draw_text: DrawText,
+    pub fonts: Rc<RefCell<FontCollection>>,
     pub padding: Vec2,
     pub on_click: Option<Box<dyn FnMut(&mut Cx)>>,
 }
@@ -35,6 +44,7 @@ impl Button {
         Self {
             text: text.to_string(),
             state: ButtonState::Normal,
+            color: theme.primary_color,
             draw_list: DrawList2d::new(cx),
             area: cx.create_area(),
             draw_bg: DrawQuad::new()
@@ -43,6 +53,7 @@ impl Button {
             draw_text: DrawText::new()
                 .with_text(text)
                 .with_style(theme.button_text_style),
+            fonts: FontCollection::shared(),
             padding: Vec2::new(theme.spacing_medium, theme.spacing_medium),
             on_click: None,
         }
@@ -57,6 +68,7 @@ impl Button {
     }
 
     pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
         self.draw_bg = self.draw_bg.with_color(color);
         self
     }
@@ -66,6 +78,11 @@ impl Button {
         self
     }
 
+    pub fn with_fonts(mut self, fonts: Rc<RefCell<FontCollection>>) -> Self {
+        self.fonts = fonts;
+        self
+    }
+
     pub fn with_padding(mut self, padding: Vec2) -> Self {
         self.padding = padding;
         self
@@ -74,102 +91,79 @@ impl Button {
 
 impl Widget for Button {
     fn handle_event(&mut self, cx: &mut Cx, event: &Event) {
+        let previous = std::mem::discriminant(&self.state);
         match event {
-            Event::MouseDown { button: MouseButton::Left, x, y, .. } => {
-                if let Some(area_data) = cx.areas.get(&self.area) {
-                    let (ax, ay, aw, ah) = area_data.rect;
-                    if *x >= ax && *x <= ax + aw && *y >= ay && *y <= ay + ah {
-                        self.state = ButtonState::Pressed;
-                    }
-                }
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } if cx.area_contains(self.area, Vec2::new(*x, *y)) => {
+                self.state = ButtonState::Pressed;
             },
             Event::MouseUp { button: MouseButton::Left, x, y, .. } => {
-                if let Some(area_data) = cx.areas.get(&self.area) {
-                    let (ax, ay, aw, ah) = area_data.rect;
-                    if *x >= ax && *x <= ax + aw && *y >= ay && *y <= ay + ah {
-                        if let ButtonState::Pressed = self.state {
-                            if let Some(on_click) = &mut self.on_click {
-                                on_click(cx);
-                            }
+                if cx.area_contains(self.area, Vec2::new(*x, *y)) {
+                    if let ButtonState::Pressed = self.state {
+                        if let Some(on_click) = &mut self.on_click {
+                            on_click(cx);
                         }
-                        self.state = ButtonState::Hover;
-                    } else {
-                        self.state = ButtonState::Normal;
                     }
+                    self.state = ButtonState::Hover;
+                } else {
+                    self.state = ButtonState::Normal;
                 }
             },
             Event::MouseMove { x, y, .. } => {
-                if let Some(area_data) = cx.areas.get(&self.area) {
-                    let (ax, ay, aw, ah) = area_data.rect;
-                    if *x >= ax && *x <= ax + aw && *y >= ay && *y <= ay + ah {
-                        if let ButtonState::Normal = self.state {
-                            self.state = ButtonState::Hover;
-                        }
-                    } else {
-                        if let ButtonState::Hover = self.state {
-                            self.state = ButtonState::Normal;
-                        }
+                if cx.area_contains(self.area, Vec2::new(*x, *y)) {
+                    if let ButtonState::Normal = self.state {
+                        self.state = ButtonState::Hover;
                     }
+                } else if let ButtonState::Hover = self.state {
+                    self.state = ButtonState::Normal;
                 }
             },
             _ => {}
         }
-    }
 
-    fn draw(&mut self, cx: &mut Cx2d) -> DrawStep {
-        self.draw_list.begin(cx);
-
-        // Update button appearance based on state
-        match self.state {
-            ButtonState::Normal => {
-                // Use default colors
-            },
-            ButtonState::Hover => {
-                // Lighten the background color
-                let mut color = self.draw_bg.color;
-                color.r = (color.r + 0.1).min(1.0);
-                color.g = (color.g + 0.1).min(1.0);
-                color.b = (color.b + 0.1).min(1.0);
-                self.draw_bg.color = color;
-            },
-            ButtonState::Pressed => {
-                // Darken the background color
-                let mut color = self.draw_bg.color;
-                color.r = (color.r - 0.1).max(0.0);
-                color.g = (color.g - 0.1).max(0.0);
-                color.b = (color.b - 0.1).max(0.0);
-                self.draw_bg.color = color;
-            },
-            ButtonState::Disabled => {
-                // Gray out the button
-                self.draw_bg.color = Color::new(0.5, 0.5, 0.5, 0.5);
-                self.draw_text.style.color = Color::new(0.7, 0.7, 0.7, 0.7);
-            },
+        if std::mem::discriminant(&self.state) != previous {
+            self.draw_list.redraw();
         }
+    }
 
+    fn draw(&mut self, cx: &mut Cx2d) -> DrawStep {
         // Calculate button size based on text
         let text_size = Vec2::new(100.0, 20.0); // Placeholder for actual text measurement
-        let button_size = Vec2::new(
-            text_size.x + self.padding.x * 2.0,
-            text_size.y + self.padding.y * 2.0,
-        );
+        let button_size = text_size + self.padding * 2.0;
 
         // Add the button to the current turtle
         if let Some(rect) = cx.add_turtle_item(button_size) {
-            // Draw the button background
-            self.draw_bg.draw(cx, self.draw_list.id(), &rect);
+            if self.draw_list.begin_cached(cx, &rect) {
+                // Update button appearance based on state
+                match self.state {
+                    ButtonState::Normal => {
+                        self.draw_bg.color = self.color;
+                    },
+                    ButtonState::Hover => {
+                        self.draw_bg.color = self.color.lighten(BUTTON_STATE_SHIFT);
+                    },
+                    ButtonState::Pressed => {
+                        self.draw_bg.color = self.color.darken(BUTTON_STATE_SHIFT);
+                    },
+                    ButtonState::Disabled => {
+                        // Gray out the button
+                        self.draw_bg.color = Color::new(0.5, 0.5, 0.5, 0.5);
+                        self.draw_text.style.color = Color::new(0.7, 0.7, 0.7, 0.7);
+                    },
+                }
 
-            // Draw the button text
-            let text_rect = rect.clone();
-            self.draw_text.draw(cx, self.draw_list.id(), &text_rect, &Default::default());
+                // Draw the button background
+                self.draw_bg.draw(cx, self.draw_list.id(), &rect);
+
+                // Draw the button text
+                let text_rect = rect.clone();
+                self.draw_text.draw(cx, self.draw_list.id(), &text_rect, &mut self.fonts.borrow_mut());
+            }
+            self.draw_list.end(cx);
 
             // Update the area for event handling
-            cx.set_area_rect(self.area, rect.x(), rect.y(), rect.width(), rect.height());
-            cx.set_area_draw_list(self.area, self.draw_list.id());
+            cx.update_area(self.area, &rect, self.draw_list.id());
         }
 
-        self.draw_list.end(cx);
-
         DrawStep::done()
     }
 }
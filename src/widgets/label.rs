@@ -1,9 +1,12 @@
+use std::cell::RefCell;
+use std::rc::Rc;
 use crate::platform::Cx;
 use crate::platform::event::Event;
 use crate::platform::area::Area;
 use crate::draw::Cx2d;
 use crate::draw::draw_list_2d::DrawList2d;
 use crate::draw::text::{DrawText, TextStyle, TextAlign};
+use crate::draw::font_collection::FontCollection;
 use crate::draw::color::Color;
 use crate::draw::math::Vec2;
 
@@ -16,6 +19,7 @@ pub struct Label {
     pub draw_list: DrawList2d,
     pub area: Area,
     pub draw_text: DrawText,
+    pub fonts: Rc<RefCell<FontCollection>>,
     pub padding: Vec2,
 }
 
@@ -30,6 +34,7 @@ impl Label {
             draw_text: DrawText::new()
                 .with_text(text)
                 .with_style(theme.default_text_style),
+            fonts: FontCollection::shared(),
             padding: Vec2::new(theme.spacing_small, theme.spacing_small),
         }
     }
@@ -54,10 +59,23 @@ impl Label {
         self
     }
 
+    pub fn with_fonts(mut self, fonts: Rc<RefCell<FontCollection>>) -> Self {
+        self.fonts = fonts;
+        self
+    }
+
     pub fn with_padding(mut self, padding: Vec2) -> Self {
         self.padding = padding;
         self
     }
+
+    pub fn set_text(&mut self, text: &str) {
+        if self.text != text {
+            self.text = text.to_string();
+            self.draw_text = self.draw_text.clone().with_text(text);
+            self.draw_list.redraw();
+        }
+    }
 }
 
 impl Widget for Label {
@@ -66,27 +84,22 @@ impl Widget for Label {
     }
 
     fn draw(&mut self, cx: &mut Cx2d) -> DrawStep {
-        self.draw_list.begin(cx);
-
         // Calculate label size based on text
         let text_size = Vec2::new(100.0, 20.0); // Placeholder for actual text measurement
-        let label_size = Vec2::new(
-            text_size.x + self.padding.x * 2.0,
-            text_size.y + self.padding.y * 2.0,
-        );
+        let label_size = text_size + self.padding * 2.0;
 
         // Add the label to the current turtle
         if let Some(rect) = cx.add_turtle_item(label_size) {
             // Draw the label text
-            self.draw_text.draw(cx, self.draw_list.id(), &rect, &Default::default());
+            if self.draw_list.begin_cached(cx, &rect) {
+                self.draw_text.draw(cx, self.draw_list.id(), &rect, &mut self.fonts.borrow_mut());
+            }
+            self.draw_list.end(cx);
 
             // Update the area for event handling
-            cx.set_area_rect(self.area, rect.x(), rect.y(), rect.width(), rect.height());
-            cx.set_area_draw_list(self.area, self.draw_list.id());
+            cx.update_area(self.area, &rect, self.draw_list.id());
         }
 
-        self.draw_list.end(cx);
-
         DrawStep::done()
     }
 }
@@ -1,5 +1,8 @@
+use std::fmt;
 use crate::draw::color::Color;
-use crate::draw::text::TextStyle;
+use crate::draw::text::{TextStyle, MIN_LARGE_TEXT_CONTRAST};
+use crate::draw::font_collection::{FontWeight, FontStyle};
+use crate::widgets::button::BUTTON_STATE_SHIFT;
 
 #[derive(Clone)]
 pub struct Theme {
@@ -46,6 +49,8 @@ impl Default for Theme {
             default_text_style: TextStyle {
                 font_size: 16.0,
                 font_name: "default".to_string(),
+                font_weight: FontWeight::NORMAL,
+                font_style: FontStyle::Normal,
                 color: Color::from_hex(0x000000),
                 align: crate::draw::text::TextAlign::Left,
                 line_height: 1.2,
@@ -53,6 +58,8 @@ impl Default for Theme {
             heading_text_style: TextStyle {
                 font_size: 24.0,
                 font_name: "default".to_string(),
+                font_weight: FontWeight::NORMAL,
+                font_style: FontStyle::Normal,
                 color: Color::from_hex(0x000000),
                 align: crate::draw::text::TextAlign::Left,
                 line_height: 1.2,
@@ -60,6 +67,8 @@ impl Default for Theme {
             button_text_style: TextStyle {
                 font_size: 16.0,
                 font_name: "default".to_string(),
+                font_weight: FontWeight::NORMAL,
+                font_style: FontStyle::Normal,
                 color: Color::from_hex(0xFFFFFF),
                 align: crate::draw::text::TextAlign::Center,
                 line_height: 1.2,
@@ -78,6 +87,69 @@ impl Default for Theme {
     }
 }
 
+// A foreground/background pair the built-in widgets draw that misses its
+// WCAG AA minimum
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContrastViolation {
+    pub foreground_name: &'static str,
+    pub background_name: &'static str,
+    pub foreground: Color,
+    pub background: Color,
+    pub ratio: f32,
+    pub min_contrast: f32,
+    // The foreground with its lightness moved just far enough to pass
+    pub suggested: Color,
+}
+
+impl fmt::Display for ContrastViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} on {}: {:.2}:1, needs {}:1, try {}",
+            self.foreground_name,
+            self.background_name,
+            self.ratio,
+            self.min_contrast,
+            self.suggested.to_hex_string()
+        )
+    }
+}
+
+impl Theme {
+    // Checks the text and control colors the built-in widgets combine,
+    // including the button's hover and pressed backgrounds
+    pub fn audit(&self) -> Vec<ContrastViolation> {
+        let button_text = self.button_text_style.color;
+        let button_min = self.button_text_style.min_contrast();
+        let pairs = [
+            ("default_text_style.color", self.default_text_style.color, "background_color", self.background_color, self.default_text_style.min_contrast()),
+            ("heading_text_style.color", self.heading_text_style.color, "background_color", self.background_color, self.heading_text_style.min_contrast()),
+            ("foreground_color", self.foreground_color, "background_color", self.background_color, TextStyle::default().min_contrast()),
+            ("button_text_style.color", button_text, "primary_color", self.primary_color, button_min),
+            ("button_text_style.color", button_text, "primary_color (hovered)", self.primary_color.lighten(BUTTON_STATE_SHIFT), button_min),
+            ("button_text_style.color", button_text, "primary_color (pressed)", self.primary_color.darken(BUTTON_STATE_SHIFT), button_min),
+            // Controls need to stand out from the page, WCAG 1.4.11
+            ("primary_color", self.primary_color, "background_color", self.background_color, MIN_LARGE_TEXT_CONTRAST),
+        ];
+
+        pairs
+            .into_iter()
+            .filter_map(|(foreground_name, foreground, background_name, background, min_contrast)| {
+                let ratio = foreground.contrast_ratio(background);
+                (ratio < min_contrast).then(|| ContrastViolation {
+                    foreground_name,
+                    background_name,
+                    foreground,
+                    background,
+                    ratio,
+                    min_contrast,
+                    suggested: foreground.with_contrast(background, min_contrast),
+                })
+            })
+            .collect()
+    }
+}
+
 pub struct DarkTheme;
 
 impl DarkTheme {
@@ -97,6 +169,8 @@ impl DarkTheme {
             default_text_style: TextStyle {
                 font_size: 16.0,
                 font_name: "default".to_string(),
+                font_weight: FontWeight::NORMAL,
+                font_style: FontStyle::Normal,
                 color: Color::from_hex(0xFFFFFF),
                 align: crate::draw::text::TextAlign::Left,
                 line_height: 1.2,
@@ -104,6 +178,8 @@ impl DarkTheme {
             heading_text_style: TextStyle {
                 font_size: 24.0,
                 font_name: "default".to_string(),
+                font_weight: FontWeight::NORMAL,
+                font_style: FontStyle::Normal,
                 color: Color::from_hex(0xFFFFFF),
                 align: crate::draw::text::TextAlign::Left,
                 line_height: 1.2,
@@ -111,6 +187,8 @@ impl DarkTheme {
             button_text_style: TextStyle {
                 font_size: 16.0,
                 font_name: "default".to_string(),
+                font_weight: FontWeight::NORMAL,
+                font_style: FontStyle::Normal,
                 color: Color::from_hex(0xFFFFFF),
                 align: crate::draw::text::TextAlign::Center,
                 line_height: 1.2,
@@ -4,7 +4,6 @@ use crate::platform::area::Area;
 use crate::draw::Cx2d;
 use crate::draw::draw_list_2d::DrawList2d;
 use crate::draw::layout::Layout;
-use crate::draw::turtle::Walk;
 use crate::widgets::widget::{Widget, DrawStep};
 
 pub struct View {
@@ -65,12 +64,15 @@ impl Widget for View {
             }
         }
 
-        let _rect = cx.walk_turtle(Walk::Compute);
-
-        cx.end_turtle();
+        let turtle = cx.end_turtle();
 
         self.draw_list.end(cx);
 
+        // Binds the view to its item in the enclosing turtle
+        if let Some(turtle) = turtle {
+            cx.update_area(self.area, &turtle.rect, self.draw_list.id());
+        }
+
         if needs_redraw {
             DrawStep::redraw()
         } else {
@@ -6,6 +6,7 @@ use crate::platform::pass::{PassId, PassClearColor};
 use crate::draw::Cx2d;
 use crate::draw::draw_list_2d::DrawList2d;
 use crate::draw::quad::DrawQuad;
+use crate::draw::contrast_overlay::ContrastOverlay;
 use crate::draw::math::Vec2;
 use crate::widgets::widget::{Widget, DrawStep, WidgetRef};
 use crate::widgets::view::View;
@@ -21,6 +22,8 @@ pub struct Window {
     pub content: WidgetRef,
     pub theme: Theme,
     pub size: Vec2,
+    // Created the first time `cx.debug.contrast_overlay` is on
+    pub contrast_overlay: Option<ContrastOverlay>,
 }
 
 impl Window {
@@ -59,6 +62,7 @@ impl Window {
             content: WidgetRef::new(content),
             theme,
             size: Vec2::new(800.0, 600.0),
+            contrast_overlay: None,
         }
     }
 
@@ -102,12 +106,25 @@ impl Widget for Window {
     }
 
     fn draw(&mut self, cx: &mut Cx2d) -> DrawStep {
-        // Begin drawing
-        self.draw_list.begin(cx);
+        // The overlay hangs off this list, so it has to be open every frame,
+        // and once more after the overlay is turned off to take it down
+        if cx.debug.contrast_overlay {
+            self.draw_list.redraw();
+        } else if let Some(overlay) = &mut self.contrast_overlay {
+            if overlay.clear(cx) {
+                self.draw_list.redraw();
+            }
+        }
 
-        // Draw background
+        // The background only changes with the window size; content lists
+        // nest inside this one and redraw on their own
         let rect = crate::draw::rect::Rect::new(0.0, 0.0, self.size.x, self.size.y);
-        self.draw_bg.draw(cx, self.draw_list.id(), &rect);
+        if self.draw_list.begin_cached(cx, &rect) {
+            self.draw_bg.draw(cx, self.draw_list.id(), &rect);
+        }
+
+        // Content never draws outside the window
+        cx.push_clip(rect);
 
         // Create a turtle for the content
         cx.begin_sized_turtle(self.size, crate::draw::layout::Layout::vertical());
@@ -118,7 +135,13 @@ impl Widget for Window {
         // End turtle
         cx.end_turtle();
 
-        // End drawing
+        cx.pop_clip();
+
+        if cx.debug.contrast_overlay {
+            let overlay = self.contrast_overlay.get_or_insert_with(|| ContrastOverlay::new(cx));
+            overlay.draw(cx, self.draw_list.id());
+        }
+
         self.draw_list.end(cx);
 
         // Set the main draw list for the pass